@@ -0,0 +1,73 @@
+//! Compare the parser backends on generated corpora shaped like common inputs.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use parsing_rs::parser::{parse, parse_with, Backend, Parser};
+use parsing_rs::value::Value;
+
+// an array of tweet-like objects: nested objects, short strings and ids.
+fn twitter(n: usize) -> String {
+    let statuses: Vec<String> = (0..n)
+        .map(|i| {
+            format!(
+                r#"{{"id": {}, "text": "status number {} with some words in it", "truncated": false, "user": {{"id": {}, "name": "user {}", "followers_count": {}, "verified": {}}}, "entities": {{"hashtags": [], "urls": [{{"url": "http://t.co/{}"}}]}}, "in_reply_to": null}}"#,
+                1_000_000 + i,
+                i,
+                i % 97,
+                i % 97,
+                i * 13,
+                i % 2 == 0,
+                i
+            )
+        })
+        .collect();
+    format!(r#"{{"statuses": [{}]}}"#, statuses.join(", "))
+}
+
+// rows of floating point numbers, like coordinates or measurements.
+fn numbers(n: usize) -> String {
+    let rows: Vec<String> = (0..n)
+        .map(|i| format!("[{}, {}.25, {}e-3]", i, i * 7, i * 31))
+        .collect();
+    format!("[{}]", rows.join(", "))
+}
+
+// an object of long string values.
+fn strings(n: usize) -> String {
+    let fields: Vec<String> = (0..n)
+        .map(|i| {
+            format!(
+                r#""key{}": "{}""#,
+                i,
+                "lorem ipsum dolor sit amet ".repeat(8)
+            )
+        })
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn bench_backends(c: &mut Criterion) {
+    let corpora = [
+        ("twitter", twitter(1_000)),
+        ("numbers", numbers(10_000)),
+        ("strings", strings(2_000)),
+    ];
+    for (name, doc) in &corpora {
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(doc.len() as u64));
+        group.bench_function("tokens", |b| b.iter(|| parse(black_box(doc)).unwrap()));
+        group.bench_function("tokens-reused", |b| {
+            let mut parser = Parser::new();
+            b.iter(|| parser.parse(black_box(doc)).unwrap())
+        });
+        group.bench_function("events", |b| {
+            b.iter(|| parse_with(black_box(doc), Backend::Events).unwrap())
+        });
+        let tape = parse_with(doc, Backend::Events).unwrap().to_tape_bytes();
+        group.bench_function("tape", |b| {
+            b.iter(|| Value::from_tape_bytes(black_box(&tape)).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
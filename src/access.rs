@@ -0,0 +1,680 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Lookups by key, slash-separated path and JSON pointer, with typed defaults
+/// for config readers, borrowed object views and in-place updates by path.
+use crate::event::{Event, EventReader};
+use crate::value::{GenericValue, Value};
+
+/// Types the `get_*_or` accessors can read out of a `Value`.
+pub trait FromValue: Sized {
+    /// The value as `Self`, or `None` when it has another type or is out of
+    /// range.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Option<Value> {
+        Some(value.clone())
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Option<bool> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Option<f64> {
+        match value {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Only integral numbers within range are read as integers.
+///
+/// The upper bound is exclusive at `MAX + 1`, as `i64::MAX` and `u64::MAX`
+/// round up to that power of two when converted to `f64`.
+macro_rules! from_value_int {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Option<$t> {
+                match value {
+                    Value::Number(n)
+                        if n.fract() == 0.0
+                            && *n >= <$t>::MIN as f64
+                            && *n < <$t>::MAX as f64 + 1.0 =>
+                    {
+                        Some(*n as $t)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+from_value_int!(i32, i64, u16, u32, u64, usize);
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Option<Vec<T>> {
+        match value {
+            Value::Array(v) => v.iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Option<Option<T>> {
+        match value {
+            Value::Null => Some(None),
+            v => T::from_value(v).map(Some),
+        }
+    }
+}
+
+/// A `FromValue` target that also accepts sloppily typed values: numbers
+/// written as strings, e.g. `"42"`, booleans written as `1`/`0` or as
+/// strings, and numbers or booleans where a string is expected. For example
+/// `v.get_or("port", Lenient(80u16)).0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Lenient<T>(pub T);
+
+macro_rules! from_value_lenient_number {
+    ($($t:ty),*) => {$(
+        impl FromValue for Lenient<$t> {
+            fn from_value(value: &Value) -> Option<Lenient<$t>> {
+                let parsed;
+                let value = match value {
+                    Value::String(s) => {
+                        let n: f64 = s.trim().parse().ok().filter(|n: &f64| n.is_finite())?;
+                        parsed = Value::Number(n);
+                        &parsed
+                    }
+                    v => v,
+                };
+                <$t>::from_value(value).map(Lenient)
+            }
+        }
+    )*};
+}
+
+from_value_lenient_number!(f64, i32, i64, u16, u32, u64, usize);
+
+impl FromValue for Lenient<bool> {
+    fn from_value(value: &Value) -> Option<Lenient<bool>> {
+        let b = match value {
+            Value::Bool(b) => *b,
+            Value::Number(n) if *n == 1.0 => true,
+            Value::Number(n) if *n == 0.0 => false,
+            Value::String(s) => match s.trim() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(Lenient(b))
+    }
+}
+
+impl FromValue for Lenient<String> {
+    fn from_value(value: &Value) -> Option<Lenient<String>> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(_) => Some(crate::serializer::to_string(value)),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+        .map(Lenient)
+    }
+}
+
+/// Whether a member is absent, explicitly `null`, or has a value, which
+/// PATCH-style updates treat differently: leave alone, clear, or set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldState<'a> {
+    Missing,
+    Null,
+    Present(&'a Value),
+}
+
+impl FieldState<'_> {
+    /// The member as `None` when missing, `Some(None)` when `null` and
+    /// `Some(Some(_))` otherwise.
+    pub fn extract<T: FromValue>(self) -> Result<Option<Option<T>>, &'static str> {
+        match self {
+            FieldState::Missing => Ok(None),
+            FieldState::Null => Ok(Some(None)),
+            FieldState::Present(v) => T::from_value(v)
+                .map(|t| Some(Some(t)))
+                .ok_or("unexpected type for field."),
+        }
+    }
+}
+
+impl Value {
+    /// The member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(m) => m.get(key),
+            _ => None,
+        }
+    }
+
+    /// The member `key` of an object, telling a missing member from a `null`
+    /// one. Values other than objects have no members.
+    pub fn get_field(&self, key: &str) -> FieldState<'_> {
+        match self.get(key) {
+            None => FieldState::Missing,
+            Some(Value::Null) => FieldState::Null,
+            Some(v) => FieldState::Present(v),
+        }
+    }
+
+    /// The value at `path`, whose `/`-separated segments are object keys or
+    /// array indices, e.g. `servers/0/port`. The empty path is the value
+    /// itself.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        path.split('/').try_fold(self, |v, segment| match v {
+            Value::Object(m) => m.get(segment),
+            Value::Array(a) => segment.parse::<usize>().ok().and_then(|i| a.get(i)),
+            _ => None,
+        })
+    }
+
+    /// The value at the JSON pointer (RFC 6901) `pointer`, e.g. `/a/0` or
+    /// `/a~1b` for the key `a/b`. The empty pointer is the value itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        lookup(self, &pointer_tokens(pointer)?)
+    }
+
+    /// The member `key` as a `T`, or `default` when it is missing or of
+    /// another type.
+    pub fn get_or<T: FromValue>(&self, key: &str, default: T) -> T {
+        self.get_or_else(key, || default)
+    }
+
+    /// Like `get_or`, computing the default only when it is needed.
+    pub fn get_or_else<T: FromValue>(&self, key: &str, default: impl FnOnce() -> T) -> T {
+        self.get(key)
+            .and_then(T::from_value)
+            .unwrap_or_else(default)
+    }
+
+    /// The value at `path` as a `T`, or `default` when it is missing or of
+    /// another type.
+    pub fn get_path_or<T: FromValue>(&self, path: &str, default: T) -> T {
+        self.get_path_or_else(path, || default)
+    }
+
+    /// Like `get_path_or`, computing the default only when it is needed.
+    pub fn get_path_or_else<T: FromValue>(&self, path: &str, default: impl FnOnce() -> T) -> T {
+        self.get_path(path)
+            .and_then(T::from_value)
+            .unwrap_or_else(default)
+    }
+}
+
+// the value at the unescaped pointer `tokens` below `value`.
+pub(crate) fn lookup<'a>(value: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(value, |v, token| match v {
+        Value::Object(m) => m.get(token),
+        Value::Array(a) => token.parse::<usize>().ok().and_then(|i| a.get(i)),
+        _ => None,
+    })
+}
+
+pub(crate) fn lookup_mut<'a>(value: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(value, |v, token| match v {
+        Value::Object(m) => m.get_mut(token),
+        Value::Array(a) => token.parse::<usize>().ok().and_then(move |i| a.get_mut(i)),
+        _ => None,
+    })
+}
+
+// the index `token` names in an array of `len` elements, where `-` and `len`
+// are one past the end.
+pub(crate) fn array_index(token: &str, len: usize) -> Option<usize> {
+    match token {
+        "-" => Some(len),
+        _ => token.parse().ok().filter(|&i| i <= len),
+    }
+}
+
+// set the value at `tokens` below `root`, adding an object member or
+// appending to an array when the index is one past its end, and return the
+// value replaced.
+pub(crate) fn set_at(
+    root: &mut Value,
+    tokens: &[String],
+    value: Value,
+) -> Result<Option<Value>, &'static str> {
+    let (last, parent) = match tokens.split_last() {
+        Some(split) => split,
+        None => return Ok(Some(std::mem::replace(root, value))),
+    };
+    match lookup_mut(root, parent) {
+        Some(Value::Object(m)) => Ok(m.insert(last.clone(), value)),
+        Some(Value::Array(a)) => match array_index(last, a.len()) {
+            Some(i) if i < a.len() => Ok(Some(std::mem::replace(&mut a[i], value))),
+            Some(_) => {
+                a.push(value);
+                Ok(None)
+            }
+            None => Err("pointer not found."),
+        },
+        _ => Err("pointer not found."),
+    }
+}
+
+// remove and return the value at `tokens` below `root`.
+pub(crate) fn remove_at(root: &mut Value, tokens: &[String]) -> Result<Value, &'static str> {
+    let (last, parent) = tokens.split_last().ok_or("cannot remove the root.")?;
+    match lookup_mut(root, parent) {
+        Some(Value::Object(m)) => m.remove(last).ok_or("pointer not found."),
+        Some(Value::Array(a)) => match array_index(last, a.len()) {
+            Some(i) if i < a.len() => Ok(a.remove(i)),
+            _ => Err("pointer not found."),
+        },
+        _ => Err("pointer not found."),
+    }
+}
+
+// the unescaped reference tokens of a JSON pointer.
+pub(crate) fn pointer_tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(vec![]);
+    }
+    let tokens = pointer.strip_prefix('/')?.split('/');
+    Some(
+        tokens
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// Read the subtree of `value` at the JSON pointer `pointer` as a `T`.
+pub fn from_value_at<T: FromValue>(value: &Value, pointer: &str) -> Result<T, &'static str> {
+    let target = value.pointer(pointer).ok_or("pointer not found.")?;
+    T::from_value(target).ok_or("unexpected type at pointer.")
+}
+
+/// Like `from_value_at` for the JSON text `s`, building only the addressed
+/// subtree: everything before it is scanned without being built, and nothing
+/// after it is read.
+pub fn from_str_at<T: FromValue>(s: &str, pointer: &str) -> Result<T, &'static str> {
+    let tokens = pointer_tokens(pointer).ok_or("pointer not found.")?;
+    let mut events = EventReader::new(s.as_bytes());
+    let next =
+        |events: &mut EventReader<&[u8]>| events.next_event()?.ok_or("unexpected end of input.");
+    let mut event = next(&mut events)?;
+    for token in &tokens {
+        event = match event {
+            Event::StartObject => loop {
+                match next(&mut events)? {
+                    Event::Key(k) if k == *token => break next(&mut events)?,
+                    Event::Key(_) => {
+                        events.skip_value()?;
+                    }
+                    _ => return Err("pointer not found."),
+                }
+            },
+            Event::StartArray => {
+                let index = token.parse::<usize>().map_err(|_| "pointer not found.")?;
+                for _ in 0..index {
+                    if !events.skip_value()? {
+                        return Err("pointer not found.");
+                    }
+                }
+                match next(&mut events)? {
+                    Event::EndArray => return Err("pointer not found."),
+                    e => e,
+                }
+            }
+            _ => return Err("pointer not found."),
+        };
+    }
+    let value = events.build_value(event)?;
+    T::from_value(&value).ok_or("unexpected type at pointer.")
+}
+
+/// A borrowed object whose keys are read and looked up as `&str` whatever the
+/// string type of the value, e.g. the inline strings of a `CompactValue`.
+#[derive(Debug)]
+pub struct ObjectView<'a, N = f64, S = String> {
+    map: &'a HashMap<S, GenericValue<N, S>>,
+}
+
+// derived Clone and Copy would require N and S to be Copy.
+impl<N, S> Clone for ObjectView<'_, N, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N, S> Copy for ObjectView<'_, N, S> {}
+
+impl<'a, N, S: Borrow<str> + Eq + Hash> ObjectView<'a, N, S> {
+    pub fn get(&self, key: &str) -> Option<&'a GenericValue<N, S>> {
+        self.map.get(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// The members in the map's iteration order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a GenericValue<N, S>)> {
+        self.map.iter().map(|(k, v)| (k.borrow(), v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> {
+        self.map.keys().map(|k| k.borrow())
+    }
+}
+
+impl<N, S> GenericValue<N, S> {
+    /// A view of the object, or `None` for other values.
+    pub fn as_object_view(&self) -> Option<ObjectView<'_, N, S>> {
+        match self {
+            GenericValue::Object(map) => Some(ObjectView { map }),
+            _ => None,
+        }
+    }
+}
+
+/// A place in a value found by `entry_path`, which may not exist yet.
+pub struct PathEntry<'a> {
+    // the deepest existing value on the path.
+    node: &'a mut Value,
+    // the segments below `node` still to be created.
+    missing: Vec<String>,
+}
+
+impl<'a> PathEntry<'a> {
+    /// The value at the path, inserting `default` there if it is missing.
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.or_insert_with(|| default)
+    }
+
+    /// The value at the path, inserting the result of `default` there if it
+    /// is missing. Missing containers on the way are created as objects,
+    /// except that the index right after the end of an array appends to it.
+    pub fn or_insert_with(self, default: impl FnOnce() -> Value) -> &'a mut Value {
+        if self.missing.is_empty() {
+            return self.node;
+        }
+        let mut node = self.node;
+        for segment in &self.missing {
+            if let Value::Null = node {
+                *node = Value::Object(HashMap::new());
+            }
+            node = match node {
+                Value::Object(m) => m.entry(segment.clone()).or_insert(Value::Null),
+                Value::Array(a) => {
+                    a.push(Value::Null);
+                    a.last_mut().unwrap()
+                }
+                _ => unreachable!("entry_path checked the path"),
+            };
+        }
+        *node = default();
+        node
+    }
+
+    /// Call `f` on the value at the path if it exists.
+    pub fn and_modify(self, f: impl FnOnce(&mut Value)) -> PathEntry<'a> {
+        if self.missing.is_empty() {
+            f(self.node);
+        }
+        self
+    }
+}
+
+impl Value {
+    /// The entry at `path`, as in `get_path`, for updating it in place, e.g.
+    /// `v.entry_path("a/b")?.or_insert_with(|| Value::Array(vec![]))`.
+    ///
+    /// Nulls on the path count as missing. Fails when the path goes through a
+    /// number, string or boolean, or indexes an array past its end.
+    pub fn entry_path(&mut self, path: &str) -> Result<PathEntry<'_>, &'static str> {
+        let segments: Vec<&str> = match path {
+            "" => vec![],
+            _ => path.split('/').collect(),
+        };
+        // find how much of the path exists before borrowing mutably.
+        let mut existing = 0;
+        let mut v: &Value = self;
+        for segment in &segments {
+            let child = match v {
+                Value::Object(m) => m.get(*segment),
+                Value::Array(a) => match segment.parse::<usize>() {
+                    Ok(n) if n < a.len() => a.get(n),
+                    Ok(n) if n == a.len() => None,
+                    _ => return Err("invalid array index in path."),
+                },
+                Value::Null => None,
+                _ => return Err("path goes through a scalar."),
+            };
+            match child {
+                Some(child) => {
+                    v = child;
+                    existing += 1;
+                }
+                None => break,
+            }
+        }
+        let mut node = self;
+        for segment in &segments[..existing] {
+            node = match node {
+                Value::Object(m) => m.get_mut(*segment).unwrap(),
+                Value::Array(a) => &mut a[segment.parse::<usize>().unwrap()],
+                _ => unreachable!(),
+            };
+        }
+        Ok(PathEntry {
+            node,
+            missing: segments[existing..]
+                .iter()
+                .map(|s| (*s).to_owned())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use crate::smallstr::CompactValue;
+
+    #[test]
+    fn test_get_or() {
+        let v = parse(
+            r#"{"name": "app", "debug": true, "workers": 4.5,
+                "servers": [{"host": "a", "port": 8080}], "tags": ["x", "y"], "proxy": null}"#,
+        )
+        .unwrap();
+        assert_eq!(v.get_or("name", String::new()), "app");
+        assert!(v.get_or("debug", false));
+        assert_eq!(v.get_or("missing", 7u32), 7);
+        // wrong type or not integral falls back to the default.
+        assert_eq!(v.get_or("name", 1i64), 1);
+        assert_eq!(v.get_or("workers", 1usize), 1);
+        assert_eq!(v.get_or("workers", 1.0), 4.5);
+        assert_eq!(v.get_or::<Vec<String>>("tags", vec![]), vec!["x", "y"]);
+        assert_eq!(v.get_or("proxy", Some("p".to_owned())), None);
+        assert_eq!(v.get_path_or("servers/0/port", 80u16), 8080);
+        assert_eq!(v.get_path_or("servers/1/port", 80u16), 80);
+        assert_eq!(v.get_path_or("servers/x/port", 80u16), 80);
+        assert_eq!(
+            v.get_path_or_else("servers/0/user", || "root".to_owned()),
+            "root"
+        );
+        assert_eq!(v.get_path(""), Some(&v));
+        assert_eq!(Value::Null.get_or("a", 1u64), 1);
+    }
+
+    #[test]
+    fn test_lenient() {
+        let v = parse(
+            r#"{"port": "8080", "ratio": " 0.5 ", "debug": 1, "verbose": "false",
+                "id": 12345, "ok": true, "big": "1e10", "ids": ["1", 2]}"#,
+        )
+        .unwrap();
+        assert_eq!(v.get_or("port", 80u16), 80);
+        assert_eq!(v.get_or("port", Lenient(80u16)).0, 8080);
+        assert_eq!(v.get_or("ratio", Lenient(1.0)).0, 0.5);
+        assert!(v.get_or("debug", Lenient(false)).0);
+        assert!(!v.get_or("verbose", Lenient(true)).0);
+        assert_eq!(v.get_or("id", Lenient(String::new())).0, "12345");
+        assert_eq!(v.get_or("ok", Lenient(String::new())).0, "true");
+        assert_eq!(v.get_or("big", Lenient(7u32)).0, 7);
+        assert_eq!(
+            v.get_or::<Vec<Lenient<u64>>>("ids", vec![]),
+            vec![Lenient(1), Lenient(2)]
+        );
+        assert_eq!(
+            Lenient::<i32>::from_value(&Value::String("NaN".to_owned())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_field() {
+        let v = parse(r#"{"name": "x", "email": null}"#).unwrap();
+        assert_eq!(v.get_field("phone"), FieldState::Missing);
+        assert_eq!(v.get_field("email"), FieldState::Null);
+        assert_eq!(
+            v.get_field("name"),
+            FieldState::Present(&Value::String("x".to_owned()))
+        );
+        assert_eq!(v.get_field("phone").extract::<String>(), Ok(None));
+        assert_eq!(v.get_field("email").extract::<String>(), Ok(Some(None)));
+        assert_eq!(
+            v.get_field("name").extract::<String>(),
+            Ok(Some(Some("x".to_owned())))
+        );
+        assert!(v.get_field("name").extract::<u32>().is_err());
+    }
+
+    #[test]
+    fn test_entry_path() {
+        let mut v = parse(r#"{"a": {"list": [1]}, "n": null, "s": "x"}"#).unwrap();
+        if let Value::Array(a) = v
+            .entry_path("a/list")
+            .unwrap()
+            .or_insert_with(|| Value::Array(vec![]))
+        {
+            a.push(Value::Number(2.0));
+        }
+        v.entry_path("a/b/c").unwrap().or_insert(Value::Bool(true));
+        v.entry_path("n/m").unwrap().or_insert(Value::Number(1.0));
+        v.entry_path("a/list/2")
+            .unwrap()
+            .or_insert(Value::Number(3.0));
+        v.entry_path("a/list/0")
+            .unwrap()
+            .and_modify(|n| *n = Value::Number(0.0))
+            .or_insert(Value::Null);
+        assert_eq!(
+            v,
+            parse(r#"{"a": {"list": [0, 2, 3], "b": {"c": true}}, "n": {"m": 1}, "s": "x"}"#)
+                .unwrap()
+        );
+        assert!(v.entry_path("s/t").is_err());
+        assert!(v.entry_path("a/list/9").is_err());
+        assert!(v.entry_path("a/list/x").is_err());
+    }
+
+    #[test]
+    fn test_object_view() {
+        let v = crate::parser::parse_compact(r#"{"a": 1, "b": {"c": true}}"#).unwrap();
+        let view = v.as_object_view().unwrap();
+        assert_eq!(view.len(), 2);
+        assert!(view.contains_key("b"));
+        assert_eq!(view.get("a"), Some(&CompactValue::Number(1.0)));
+        assert!(view.get("z").is_none());
+        let mut keys: Vec<&str> = view.keys().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["a", "b"]);
+        let inner = view
+            .get("b")
+            .and_then(CompactValue::as_object_view)
+            .unwrap();
+        assert_eq!(
+            inner.iter().collect::<Vec<_>>(),
+            vec![("c", &CompactValue::Bool(true))]
+        );
+        assert!(Value::Null.as_object_view().is_none());
+    }
+
+    #[test]
+    fn test_from_value_at() {
+        let s = r#"{"meta": {"skip": [1, {"x": 2}]}, "data": {"items": [{"id": 7}, {"id": 8}],
+                    "a/b": "slash"}, "rest": [1, 2"#;
+        // the document is cut short after the addressed subtrees.
+        assert_eq!(from_str_at::<u32>(s, "/data/items/1/id"), Ok(8));
+        assert_eq!(
+            from_str_at::<String>(s, "/data/a~1b"),
+            Ok("slash".to_owned())
+        );
+        assert_eq!(
+            from_str_at::<u32>(s, "/data/items/2/id"),
+            Err("pointer not found.")
+        );
+        assert_eq!(
+            from_str_at::<String>(s, "/data/items/0/id"),
+            Err("unexpected type at pointer.")
+        );
+        let v = parse(r#"{"data": {"items": [{"id": 7}], "": {"~": true}}}"#).unwrap();
+        assert_eq!(from_value_at::<u64>(&v, "/data/items/0/id"), Ok(7));
+        assert_eq!(from_value_at::<bool>(&v, "/data//~0"), Ok(true));
+        assert_eq!(from_value_at::<Value>(&v, ""), Ok(v.clone()));
+        assert!(from_value_at::<u64>(&v, "data").is_err());
+    }
+
+    #[test]
+    fn test_from_value_int() {
+        let n = |n: f64| Value::Number(n);
+        let two63 = 2f64.powi(63);
+        assert_eq!(i64::from_value(&n(two63)), None);
+        // the largest f64 below 2^63.
+        assert_eq!(i64::from_value(&n(two63 - 1024.0)), Some(i64::MAX - 1023));
+        assert_eq!(i64::from_value(&n(-two63)), Some(i64::MIN));
+        assert_eq!(u64::from_value(&n(2f64.powi(64))), None);
+        assert_eq!(i32::from_value(&n(2147483647.0)), Some(i32::MAX));
+        assert_eq!(i32::from_value(&n(2147483648.0)), None);
+        assert_eq!(u16::from_value(&n(-1.0)), None);
+        assert_eq!(u16::from_value(&n(1.5)), None);
+    }
+}
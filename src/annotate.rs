@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+/// Per-node annotations such as source spans, comments, provenance or
+/// validation state, kept in a map parallel to the `Value` tree.
+use crate::event::{Event, EventReader};
+use crate::sink::{ValueBuilder, ValueSink};
+use crate::value::Value;
+
+/// Annotations keyed by the JSON pointer of the node they belong to, `""`
+/// being the root. The tree itself is left untouched, so annotated and plain
+/// values share every API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotations<A> {
+    map: HashMap<String, A>,
+}
+
+impl<A> Default for Annotations<A> {
+    fn default() -> Annotations<A> {
+        Annotations {
+            map: HashMap::new(),
+        }
+    }
+}
+
+impl<A> Annotations<A> {
+    pub fn new() -> Annotations<A> {
+        Annotations::default()
+    }
+
+    pub fn get(&self, pointer: &str) -> Option<&A> {
+        self.map.get(pointer)
+    }
+
+    pub fn get_mut(&mut self, pointer: &str) -> Option<&mut A> {
+        self.map.get_mut(pointer)
+    }
+
+    /// Annotate the node at `pointer`, returning its previous annotation.
+    pub fn insert(&mut self, pointer: impl Into<String>, annotation: A) -> Option<A> {
+        self.map.insert(pointer.into(), annotation)
+    }
+
+    pub fn remove(&mut self, pointer: &str) -> Option<A> {
+        self.map.remove(pointer)
+    }
+
+    /// The annotated pointers and their annotations, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &A)> {
+        self.map.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// A range of byte offsets into the source text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parse `s` and annotate every node with its span in `s`.
+pub fn parse_with_spans(s: &str) -> Result<(Value, Annotations<Span>), &'static str> {
+    let mut events = EventReader::new(s.as_bytes());
+    let mut builder = ValueBuilder::new();
+    let mut spans = Annotations::new();
+    // open containers: their pointer, start offset and the position being read.
+    let mut open: Vec<(String, usize, Position)> = vec![];
+    while let Some(event) = events.next_event()? {
+        let (start, end) = (events.event_start(), events.offset());
+        let pointer = match (&event, open.last_mut()) {
+            (Event::Key(k), Some((_, _, position))) => {
+                *position = Position::Key(k.replace('~', "~0").replace('/', "~1"));
+                builder.key(k.clone())?;
+                continue;
+            }
+            (Event::EndObject, _) | (Event::EndArray, _) => {
+                let (pointer, start, _) = open.pop().ok_or("unbalanced container.")?;
+                spans.insert(pointer, Span { start, end });
+                if let Event::EndObject = event {
+                    builder.end_object()?;
+                } else {
+                    builder.end_array()?;
+                }
+                continue;
+            }
+            (_, None) => String::new(),
+            (_, Some((parent, _, position))) => {
+                if let Position::Index(i) = position {
+                    *i = Some(i.map_or(0, |i| i + 1));
+                }
+                match position {
+                    Position::Key(k) => format!("{}/{}", parent, k),
+                    Position::Index(i) => format!("{}/{}", parent, i.unwrap_or(0)),
+                }
+            }
+        };
+        match event {
+            Event::StartObject => {
+                builder.start_object()?;
+                open.push((pointer, start, Position::Key(String::new())));
+                continue;
+            }
+            Event::StartArray => {
+                builder.start_array()?;
+                open.push((pointer, start, Position::Index(None)));
+                continue;
+            }
+            Event::Null => builder.null()?,
+            Event::Bool(b) => builder.bool(b)?,
+            Event::Number(n) => builder.number(n)?,
+            Event::String(s) => builder.string(s)?,
+            Event::Key(_) | Event::EndObject | Event::EndArray => unreachable!(),
+        }
+        spans.insert(pointer, Span { start, end });
+    }
+    Ok((builder.finish()?, spans))
+}
+
+// the escaped key or the index of the value being read in a container.
+enum Position {
+    Key(String),
+    Index(Option<usize>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_parse_with_spans() {
+        let s = r#" {"a": [1, "xy"], "b/c": {"d": null}} "#;
+        let (v, spans) = parse_with_spans(s).unwrap();
+        assert_eq!(v, parse(s).unwrap());
+        let text = |p: &str| {
+            let span = spans.get(p).unwrap();
+            &s[span.start..span.end]
+        };
+        assert_eq!(text(""), r#"{"a": [1, "xy"], "b/c": {"d": null}}"#);
+        assert_eq!(text("/a"), r#"[1, "xy"]"#);
+        assert_eq!(text("/a/0"), "1");
+        assert_eq!(text("/a/1"), r#""xy""#);
+        assert_eq!(text("/b~1c"), r#"{"d": null}"#);
+        assert_eq!(text("/b~1c/d"), "null");
+        assert_eq!(spans.len(), 6);
+
+        let mut notes: Annotations<&str> = Annotations::new();
+        assert_eq!(notes.insert("/a", "from defaults"), None);
+        assert_eq!(notes.insert("/a", "overridden"), Some("from defaults"));
+        assert_eq!(notes.get("/a"), Some(&"overridden"));
+        assert!(parse_with_spans("[1,").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+/// Random `Value` generation for fuzzing and property tests. Enabled by the
+/// `arbitrary` feature.
+use crate::value::Value;
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitraryOptions {
+    /// How deeply arrays and objects may nest; at 0 only scalars are made.
+    pub max_depth: usize,
+    /// The most elements in an array or entries in an object, and the most
+    /// characters in a string.
+    pub max_len: usize,
+}
+
+impl Default for ArbitraryOptions {
+    fn default() -> ArbitraryOptions {
+        ArbitraryOptions {
+            max_depth: 4,
+            max_len: 8,
+        }
+    }
+}
+
+/// Build a random `Value` from `u` within the bounds of `options`.
+///
+/// Numbers are always finite, so the value survives a trip through JSON text.
+pub fn arbitrary_value(u: &mut Unstructured<'_>, options: &ArbitraryOptions) -> Result<Value> {
+    value(u, options, options.max_depth)
+}
+
+fn value(u: &mut Unstructured<'_>, options: &ArbitraryOptions, depth: usize) -> Result<Value> {
+    let kinds = if depth == 0 { 4 } else { 6 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => {
+            let n: f64 = u.arbitrary()?;
+            Value::Number(if n.is_finite() { n } else { 0.0 })
+        }
+        3 => Value::String(string(u, options)?),
+        4 => {
+            let len = u.int_in_range(0..=options.max_len)?;
+            let mut v = Vec::with_capacity(len);
+            for _ in 0..len {
+                v.push(value(u, options, depth - 1)?);
+            }
+            Value::Array(v)
+        }
+        _ => {
+            let len = u.int_in_range(0..=options.max_len)?;
+            let mut m = HashMap::with_capacity(len);
+            for _ in 0..len {
+                m.insert(string(u, options)?, value(u, options, depth - 1)?);
+            }
+            Value::Object(m)
+        }
+    })
+}
+
+fn string(u: &mut Unstructured<'_>, options: &ArbitraryOptions) -> Result<String> {
+    let s: String = u.arbitrary()?;
+    Ok(s.chars().take(options.max_len).collect())
+}
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Value> {
+        arbitrary_value(u, &ArbitraryOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn depth(v: &Value) -> usize {
+        match v {
+            Value::Array(v) => 1 + v.iter().map(depth).max().unwrap_or(0),
+            Value::Object(m) => 1 + m.values().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_value() {
+        let bytes: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let options = ArbitraryOptions {
+            max_depth: 2,
+            max_len: 3,
+        };
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..50 {
+            let v = arbitrary_value(&mut u, &options).unwrap();
+            assert!(depth(&v) <= 2);
+            if let Value::Array(a) = &v {
+                assert!(a.len() <= 3);
+            }
+        }
+        let mut u = Unstructured::new(&bytes);
+        assert!(Value::arbitrary(&mut u).is_ok());
+    }
+}
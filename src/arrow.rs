@@ -0,0 +1,190 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Conversion between arrays of flat objects and Arrow record batches, for
+/// handing parsed documents to analytics code. Enabled by the `arrow`
+/// feature.
+///
+/// Each member becomes a column, named by its key and ordered by key.
+/// Numbers become `Float64` columns, and a missing member or `null` is a
+/// null entry.
+use crate::value::Value;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, NullArray, RecordBatch, RecordBatchOptions,
+    StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+
+/// Convert an array of objects whose members are all `null`, booleans,
+/// numbers or strings into a record batch. All non-null values of a member
+/// must have the same type.
+pub fn to_record_batch(value: &Value) -> Result<RecordBatch, &'static str> {
+    let rows = match value {
+        Value::Array(v) => v
+            .iter()
+            .map(|row| match row {
+                Value::Object(m) => Ok(m),
+                _ => Err("expected an array of objects."),
+            })
+            .collect::<Result<Vec<&HashMap<String, Value>>, &'static str>>()?,
+        _ => return Err("expected an array of objects."),
+    };
+    let keys: BTreeSet<&String> = rows.iter().flat_map(|m| m.keys()).collect();
+    let mut fields = vec![];
+    let mut columns = vec![];
+    for key in keys {
+        let cells: Vec<&Value> = rows
+            .iter()
+            .map(|m| m.get(key).unwrap_or(&Value::Null))
+            .collect();
+        let column = column(&cells)?;
+        fields.push(Field::new(key, column.data_type().clone(), true));
+        columns.push(column);
+    }
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+        .map_err(|_| "cannot build record batch.")
+}
+
+// build the column of one member from its value in every row.
+fn column(cells: &[&Value]) -> Result<ArrayRef, &'static str> {
+    let first = cells.iter().find(|v| !v.is_null());
+    let column: ArrayRef = match first {
+        None => Arc::new(NullArray::new(cells.len())),
+        Some(Value::Bool(_)) => Arc::new(
+            cells
+                .iter()
+                .map(|v| match v {
+                    Value::Null => Ok(None),
+                    Value::Bool(b) => Ok(Some(*b)),
+                    _ => Err("mixed types in column."),
+                })
+                .collect::<Result<BooleanArray, &'static str>>()?,
+        ),
+        Some(Value::Number(_)) => Arc::new(
+            cells
+                .iter()
+                .map(|v| match v {
+                    Value::Null => Ok(None),
+                    Value::Number(n) => Ok(Some(*n)),
+                    _ => Err("mixed types in column."),
+                })
+                .collect::<Result<Float64Array, &'static str>>()?,
+        ),
+        Some(Value::String(_)) => Arc::new(
+            cells
+                .iter()
+                .map(|v| match v {
+                    Value::Null => Ok(None),
+                    Value::String(s) => Ok(Some(s.as_str())),
+                    _ => Err("mixed types in column."),
+                })
+                .collect::<Result<StringArray, &'static str>>()?,
+        ),
+        Some(_) => return Err("nested values are not supported."),
+    };
+    Ok(column)
+}
+
+/// Convert a record batch into an array of objects, one per row with a member
+/// per column. Null entries become `null` members.
+///
+/// Supports null, boolean, integer, floating point and string columns.
+pub fn from_record_batch(batch: &RecordBatch) -> Result<Value, &'static str> {
+    let mut rows = vec![HashMap::new(); batch.num_rows()];
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        for (i, row) in rows.iter_mut().enumerate() {
+            let cell = if column.is_null(i) {
+                Value::Null
+            } else {
+                cell(column, i)?
+            };
+            row.insert(field.name().clone(), cell);
+        }
+    }
+    Ok(Value::Array(rows.into_iter().map(Value::Object).collect()))
+}
+
+fn cell(column: &ArrayRef, i: usize) -> Result<Value, &'static str> {
+    macro_rules! number {
+        ($t:ty) => {
+            Value::Number(column.as_primitive::<$t>().value(i) as f64)
+        };
+    }
+    Ok(match column.data_type() {
+        DataType::Null => Value::Null,
+        DataType::Boolean => Value::Bool(column.as_boolean().value(i)),
+        DataType::Int8 => number!(Int8Type),
+        DataType::Int16 => number!(Int16Type),
+        DataType::Int32 => number!(Int32Type),
+        DataType::Int64 => number!(Int64Type),
+        DataType::UInt8 => number!(UInt8Type),
+        DataType::UInt16 => number!(UInt16Type),
+        DataType::UInt32 => number!(UInt32Type),
+        DataType::UInt64 => number!(UInt64Type),
+        DataType::Float32 => number!(Float32Type),
+        DataType::Float64 => number!(Float64Type),
+        DataType::Utf8 => Value::String(column.as_string::<i32>().value(i).to_owned()),
+        DataType::LargeUtf8 => Value::String(column.as_string::<i64>().value(i).to_owned()),
+        _ => return Err("unsupported arrow type."),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_with, Backend};
+    use arrow_array::Int32Array;
+
+    #[test]
+    fn test_record_batch() {
+        let value = parse_with(
+            r#"[{"id": 1, "name": "a", "ok": true, "x": null},
+                {"id": -2.5, "ok": null, "x": null}]"#,
+            Backend::Events,
+        )
+        .unwrap();
+        let batch = to_record_batch(&value).unwrap();
+        let types: Vec<(&str, &DataType)> = batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("id", &DataType::Float64),
+                ("name", &DataType::Utf8),
+                ("ok", &DataType::Boolean),
+                ("x", &DataType::Null),
+            ]
+        );
+        let expected = parse_with(
+            r#"[{"id": 1, "name": "a", "ok": true, "x": null},
+                {"id": -2.5, "name": null, "ok": null, "x": null}]"#,
+            Backend::Events,
+        );
+        assert_eq!(from_record_batch(&batch), expected);
+
+        let bad = parse_with(r#"[{"a": 1}, {"a": "x"}]"#, Backend::Events).unwrap();
+        assert_eq!(to_record_batch(&bad).err(), Some("mixed types in column."));
+        assert_eq!(
+            from_record_batch(&to_record_batch(&Value::Array(vec![])).unwrap()),
+            Ok(Value::Array(vec![]))
+        );
+
+        let schema = Schema::new(vec![Field::new("n", DataType::Int32, true)]);
+        let ints: ArrayRef = Arc::new(Int32Array::from(vec![Some(7), None]));
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![ints]).unwrap();
+        assert_eq!(
+            from_record_batch(&batch),
+            Ok(parse_with(r#"[{"n": 7}, {"n": null}]"#, Backend::Events).unwrap())
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Typed streams of newline-delimited JSON from tokio readers. Enabled by the
+/// `async` feature.
+use crate::access::FromValue;
+use crate::parser::{parse_with, Backend};
+use futures_core::Stream;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader, Lines};
+
+/// The stream returned by `ndjson_stream`.
+pub struct NdjsonStream<R, T> {
+    lines: Lines<BufReader<R>>,
+    done: bool,
+    item: PhantomData<fn() -> T>,
+}
+
+/// Read one JSON document per line from `reader` as a `T`, skipping blank
+/// lines. The stream ends after the first error.
+pub fn ndjson_stream<T: FromValue, R: AsyncRead + Unpin>(reader: R) -> NdjsonStream<R, T> {
+    NdjsonStream {
+        lines: BufReader::new(reader).lines(),
+        done: false,
+        item: PhantomData,
+    }
+}
+
+impl<R: AsyncRead + Unpin, T: FromValue> Stream for NdjsonStream<R, T> {
+    type Item = Result<T, &'static str>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.done {
+            let line = match Pin::new(&mut self.lines).poll_next_line(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(Some(line))) => line,
+                Poll::Ready(Ok(None)) => break,
+                Poll::Ready(Err(_)) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err("failed to read input.")));
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let item = parse_with(&line, Backend::Events)
+                .and_then(|v| T::from_value(&v).ok_or("unexpected type in line."));
+            self.done = item.is_err();
+            return Poll::Ready(Some(item));
+        }
+        self.done = true;
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // collect the stream without pulling in a stream combinator crate.
+    async fn collect<S: Stream + Unpin>(mut s: S) -> Vec<S::Item> {
+        let mut items = vec![];
+        while let Some(item) = std::future::poll_fn(|cx| Pin::new(&mut s).poll_next(cx)).await {
+            items.push(item);
+        }
+        items
+    }
+
+    #[test]
+    fn test_ndjson_stream() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let input = "[1, 2]\n\n[-3]\r\n[4, \"x\"]\n[5]\n";
+        let items = rt.block_on(collect(ndjson_stream::<Vec<i64>, _>(input.as_bytes())));
+        assert_eq!(
+            items,
+            vec![
+                Ok(vec![1, 2]),
+                Ok(vec![-3]),
+                Err("unexpected type in line.")
+            ]
+        );
+        let items = rt.block_on(collect(ndjson_stream::<f64, _>("1\n{".as_bytes())));
+        assert_eq!(items.len(), 2);
+        assert!(items[1].is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+
+/// A `Json<T>` extractor and response for axum handlers, reading with
+/// `FromValue` and writing with the serializer. Enabled by the `axum`
+/// feature.
+use crate::access::FromValue;
+use crate::chunked::parse_chunks;
+use crate::serializer::to_string;
+use crate::value::Value;
+use axum_core::extract::{FromRequest, Request};
+use axum_core::response::{IntoResponse, Response};
+use bytes::Bytes;
+use http::header::{HeaderValue, CONTENT_TYPE};
+use http::StatusCode;
+
+/// Extracts a request body read as a `T`, or responds with `T` written as
+/// JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Json<T>(pub T);
+
+/// Why a request body could not be extracted as `Json<T>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonRejection {
+    /// The request's content type is not `application/json` or a `+json` type.
+    MissingContentType,
+    /// The body could not be read, with the status to respond with, e.g.
+    /// `PAYLOAD_TOO_LARGE` past the route's `DefaultBodyLimit`.
+    Body(StatusCode),
+    /// The body is not valid JSON, or nests deeper than `MAX_DEPTH`.
+    Syntax(&'static str),
+    /// The body is valid JSON but not a `T`.
+    Data,
+}
+
+impl JsonRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            JsonRejection::MissingContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            JsonRejection::Body(status) => *status,
+            JsonRejection::Syntax(_) => StatusCode::BAD_REQUEST,
+            JsonRejection::Data => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl fmt::Display for JsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonRejection::MissingContentType => write!(f, "expected a json content type."),
+            JsonRejection::Body(StatusCode::PAYLOAD_TOO_LARGE) => write!(f, "body too large."),
+            JsonRejection::Body(_) => write!(f, "failed to read body."),
+            JsonRejection::Syntax(e) => write!(f, "{}", e),
+            JsonRejection::Data => write!(f, "unexpected type in body."),
+        }
+    }
+}
+
+impl IntoResponse for JsonRejection {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+impl<T: FromValue, S: Send + Sync> FromRequest<S> for Json<T> {
+    type Rejection = JsonRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Json<T>, JsonRejection> {
+        if !is_json(req.headers().get(CONTENT_TYPE)) {
+            return Err(JsonRejection::MissingContentType);
+        }
+        // reading as `Bytes` applies the `DefaultBodyLimit`, and the chunked
+        // parser's default limits reject documents deeper than `MAX_DEPTH`.
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| JsonRejection::Body(e.status()))?;
+        let value = parse_chunks([body]).map_err(JsonRejection::Syntax)?;
+        T::from_value(&value).map(Json).ok_or(JsonRejection::Data)
+    }
+}
+
+impl<T: Into<Value>> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        let body = to_string(&self.0.into());
+        ([(CONTENT_TYPE, "application/json")], body).into_response()
+    }
+}
+
+// whether the content type is `application/json` or `application/*+json`,
+// ignoring parameters like the charset.
+fn is_json(content_type: Option<&HeaderValue>) -> bool {
+    let mime = match content_type.and_then(|v| v.to_str().ok()) {
+        Some(v) => v
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase(),
+        None => return false,
+    };
+    match mime.strip_prefix("application/") {
+        Some(subtype) => subtype == "json" || subtype.ends_with("+json"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::parse_body;
+    use crate::parser::{parse, ParseLimits};
+    use axum_core::body::Body;
+
+    fn request(content_type: &str, body: impl Into<Body>) -> Request {
+        http::Request::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(body.into())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_json() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let extract = |content_type, body: &str| {
+            rt.block_on(Json::<Vec<f64>>::from_request(
+                request(content_type, body.to_owned()),
+                &(),
+            ))
+        };
+        assert_eq!(
+            extract("application/json; charset=utf-8", "[1, 2]"),
+            Ok(Json(vec![1.0, 2.0]))
+        );
+        assert_eq!(
+            extract("text/plain", "[1]"),
+            Err(JsonRejection::MissingContentType)
+        );
+        assert_eq!(
+            extract("application/problem+json", "{}"),
+            Err(JsonRejection::Data)
+        );
+        assert!(matches!(
+            extract("application/json", "[1"),
+            Err(JsonRejection::Syntax(_))
+        ));
+        let deep = "[".repeat(200_000) + &"]".repeat(200_000);
+        assert_eq!(
+            extract("application/json", &deep),
+            Err(JsonRejection::Syntax("json nested too deeply."))
+        );
+        let large = format!("[{}0]", "0, ".repeat(1_000_000));
+        assert_eq!(
+            extract("application/json", &large),
+            Err(JsonRejection::Body(StatusCode::PAYLOAD_TOO_LARGE))
+        );
+
+        let value = parse(r#"{"a": [1, "x"]}"#).unwrap();
+        let response = Json(value.clone()).into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        let body = response.into_body();
+        assert_eq!(
+            rt.block_on(parse_body(body, &ParseLimits::default())),
+            Ok(value)
+        );
+    }
+}
@@ -0,0 +1,89 @@
+/// Binary data embedded in JSON strings as standard base64 (RFC 4648, `+` and
+/// `/` with `=` padding).
+use crate::value::Value;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+impl Value {
+    /// Encode `bytes` as a base64 string value.
+    pub fn from_bytes_base64(bytes: &[u8]) -> Value {
+        let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    s.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+                } else {
+                    s.push('=');
+                }
+            }
+        }
+        Value::String(s)
+    }
+
+    /// Decode a base64 string value. The padding may be left out; returns
+    /// `None` for other values and for malformed input.
+    pub fn as_base64_bytes(&self) -> Option<Vec<u8>> {
+        let s = match self {
+            Value::String(s) => s.as_bytes(),
+            _ => return None,
+        };
+        let data = match s.iter().position(|&c| c == b'=') {
+            // at most two padding characters, completing the last group.
+            Some(i)
+                if s[i..].iter().all(|&c| c == b'=') && s.len() % 4 == 0 && s.len() - i <= 2 =>
+            {
+                &s[..i]
+            }
+            Some(_) => return None,
+            None => s,
+        };
+        if data.len() % 4 == 1 {
+            return None;
+        }
+        let mut out = Vec::with_capacity(data.len() * 3 / 4);
+        for chunk in data.chunks(4) {
+            let mut n = 0u32;
+            for (i, &c) in chunk.iter().enumerate() {
+                let d = ALPHABET.iter().position(|&a| a == c)? as u32;
+                n |= d << (18 - 6 * i);
+            }
+            for i in 0..chunk.len() - 1 {
+                out.push((n >> (16 - 8 * i)) as u8);
+            }
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let cases: &[(&[u8], &str)] = &[
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\xff\xfe\x00", "//4A"),
+        ];
+        for (bytes, s) in cases {
+            let v = Value::from_bytes_base64(bytes);
+            assert_eq!(v, Value::String(s.to_string()));
+            assert_eq!(v.as_base64_bytes().unwrap(), bytes.to_vec());
+        }
+        let decode = |s: &str| Value::String(s.to_owned()).as_base64_bytes();
+        assert_eq!(decode("Zm8"), Some(b"fo".to_vec()));
+        assert_eq!(decode("Zg=a"), None);
+        assert_eq!(decode("Z==="), None);
+        assert_eq!(decode("Zm9vY"), None);
+        assert_eq!(decode("Zm-v"), None);
+        assert_eq!(Value::Null.as_base64_bytes(), None);
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// A cache of parsed JSON files, reparsing a file when its modification time
+/// changes.
+use crate::parser::{parse_with, Backend};
+use crate::value::Value;
+
+#[derive(Debug, Default)]
+pub struct DocumentCache {
+    entries: Mutex<HashMap<PathBuf, (SystemTime, Arc<Value>)>>,
+}
+
+impl DocumentCache {
+    pub fn new() -> DocumentCache {
+        DocumentCache::default()
+    }
+
+    /// The document at `path`, parsed on first access and again whenever the
+    /// file's modification time differs from when it was last parsed.
+    /// A document failing to parse is not cached.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Result<Arc<Value>, &'static str> {
+        let path = path.as_ref();
+        let modified = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|_| "failed to read input.")?;
+        if let Some((time, value)) = self.lock().get(path) {
+            if *time == modified {
+                return Ok(value.clone());
+            }
+        }
+        // parse without holding the lock, so other documents stay available.
+        let text = fs::read_to_string(path).map_err(|_| "failed to read input.")?;
+        let value = Arc::new(parse_with(&text, Backend::Events)?);
+        self.lock()
+            .insert(path.to_owned(), (modified, value.clone()));
+        Ok(value)
+    }
+
+    /// Drop the cached document at `path`, if any.
+    pub fn invalidate<P: AsRef<Path>>(&self, path: P) {
+        self.lock().remove(path.as_ref());
+    }
+
+    /// Drop every cached document.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, (SystemTime, Arc<Value>)>> {
+        // a panic elsewhere cannot leave the map half-updated.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn test_document_cache() {
+        let path = std::env::temp_dir().join(format!("cache-test-{}.json", std::process::id()));
+        fs::write(&path, r#"{"a": 1}"#).unwrap();
+        let cache = DocumentCache::new();
+        let first = cache.get(&path).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get(&path).unwrap()));
+
+        fs::write(&path, r#"{"a": 2}"#).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let second = cache.get(&path).unwrap();
+        assert_eq!(second.pointer("/a"), Some(&Value::Number(2.0)));
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cache.get(&path), Err("failed to read input."));
+    }
+}
@@ -0,0 +1,165 @@
+/// CBOR encoding and decoding of `Value`s, mirroring the `msgpack` module.
+/// Enabled by the `cbor` feature.
+///
+/// Integral numbers are written as CBOR integers and other numbers as floats
+/// of the narrowest width that holds them exactly. On decoding, integers
+/// become numbers, byte strings become an array of byte values (as
+/// MessagePack binary data does) and tags are dropped in favour of the value
+/// they wrap; non-string map keys are rejected.
+use crate::event::{Event, EventReader};
+use crate::value::Value;
+use ciborium::value::{Integer, Value as Cbor};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+/// Encode `value` as CBOR.
+pub fn to_cbor(value: &Value) -> Vec<u8> {
+    let mut out = vec![];
+    ciborium::ser::into_writer(&to_ciborium(value), &mut out)
+        .expect("writing to a vec cannot fail");
+    out
+}
+
+/// Decode a single CBOR data item spanning all of `bytes`.
+pub fn from_cbor(bytes: &[u8]) -> Result<Value, &'static str> {
+    let mut rest = bytes;
+    let value: Cbor = ciborium::de::from_reader(&mut rest).map_err(|_| "cannot decode cbor.")?;
+    if !rest.is_empty() {
+        return Err("trailing bytes after cbor.");
+    }
+    from_ciborium(value)
+}
+
+/// Convert the JSON document in `reader` to CBOR written to `writer`, one
+/// parse event at a time, so the document is never held in memory.
+///
+/// Arrays and objects become indefinite-length arrays and maps, since their
+/// lengths are not known when they start; scalars are encoded as `to_cbor`
+/// encodes them.
+pub fn transcode<R: Read, W: Write>(reader: R, mut writer: W) -> Result<(), &'static str> {
+    let mut events = EventReader::new(reader);
+    while let Some(event) = events.next_event()? {
+        // containers start with an indefinite-length header and end with a
+        // break byte.
+        let byte = match event {
+            Event::StartArray => 0x9f,
+            Event::StartObject => 0xbf,
+            Event::EndArray | Event::EndObject => 0xff,
+            Event::Key(s) | Event::String(s) => {
+                write_item(&mut writer, Cbor::Text(s))?;
+                continue;
+            }
+            Event::Null => {
+                write_item(&mut writer, Cbor::Null)?;
+                continue;
+            }
+            Event::Bool(b) => {
+                write_item(&mut writer, Cbor::Bool(b))?;
+                continue;
+            }
+            Event::Number(n) => {
+                write_item(&mut writer, to_ciborium(&Value::Number(n)))?;
+                continue;
+            }
+        };
+        writer
+            .write_all(&[byte])
+            .map_err(|_| "failed to write output.")?;
+    }
+    writer.flush().map_err(|_| "failed to write output.")
+}
+
+fn write_item<W: Write>(writer: &mut W, item: Cbor) -> Result<(), &'static str> {
+    ciborium::ser::into_writer(&item, writer).map_err(|_| "failed to write output.")
+}
+
+fn to_ciborium(value: &Value) -> Cbor {
+    match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(*b),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(64) => {
+            Cbor::Integer(Integer::try_from(*n as i128).expect("integer in cbor range"))
+        }
+        Value::Number(n) => Cbor::Float(*n),
+        Value::String(s) => Cbor::Text(s.clone()),
+        Value::Array(v) => Cbor::Array(v.iter().map(to_ciborium).collect()),
+        Value::Object(m) => Cbor::Map(
+            m.iter()
+                .map(|(k, v)| (Cbor::Text(k.clone()), to_ciborium(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn from_ciborium(value: Cbor) -> Result<Value, &'static str> {
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => Value::Number(i128::from(i) as f64),
+        Cbor::Float(f) => Value::Number(f),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(b) => Value::Array(b.into_iter().map(|b| Value::Number(b as f64)).collect()),
+        Cbor::Tag(_, v) => from_ciborium(*v)?,
+        Cbor::Array(v) => Value::Array(
+            v.into_iter()
+                .map(from_ciborium)
+                .collect::<Result<Vec<Value>, &'static str>>()?,
+        ),
+        Cbor::Map(entries) => {
+            let mut m = std::collections::HashMap::with_capacity(entries.len());
+            for (k, v) in entries {
+                let k = match k {
+                    Cbor::Text(s) => s,
+                    _ => return Err("cbor map keys must be strings."),
+                };
+                m.insert(k, from_ciborium(v)?);
+            }
+            Value::Object(m)
+        }
+        _ => return Err("unsupported cbor data item."),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse, parse_with, Backend};
+
+    #[test]
+    fn test_cbor() {
+        let v = parse(r#"{"a": [1, 0.5, true, null], "b": "text", "c": {}}"#).unwrap();
+        assert_eq!(from_cbor(&to_cbor(&v)).unwrap(), v);
+        assert_eq!(to_cbor(&Value::Number(1.0)), vec![0x01]);
+        assert_eq!(to_cbor(&Value::Number(-1.0)), vec![0x20]);
+        assert_eq!(to_cbor(&Value::Number(0.5)), vec![0xf9, 0x38, 0x00]);
+        assert_eq!(to_cbor(&Value::Number(0.1))[0], 0xfb);
+        let big = Value::Number(-(2f64.powi(40)));
+        assert_eq!(from_cbor(&to_cbor(&big)).unwrap(), big);
+
+        // a two-byte byte string, and an epoch timestamp tag.
+        assert_eq!(
+            from_cbor(&[0x42, 0x00, 0xff]).unwrap(),
+            Value::Array(vec![Value::Number(0.0), Value::Number(255.0)])
+        );
+        assert_eq!(from_cbor(&[0xc1, 0x01]).unwrap(), Value::Number(1.0));
+        assert!(from_cbor(&[0x01, 0x02]).is_err());
+        assert!(from_cbor(&[0xa1, 0x01, 0x01]).is_err());
+        assert!(from_cbor(&[]).is_err());
+    }
+
+    #[test]
+    fn test_transcode() {
+        let doc = r#"{"a": [1, -2, 0.5, true, null], "b": "text", "c": {"d": []}}"#;
+        let mut out = vec![];
+        transcode(doc.as_bytes(), &mut out).unwrap();
+        assert_eq!(
+            from_cbor(&out).unwrap(),
+            parse_with(doc, Backend::Events).unwrap()
+        );
+        let mut out = vec![];
+        transcode("[1, [\"x\"]]".as_bytes(), &mut out).unwrap();
+        assert_eq!(out, vec![0x9f, 0x01, 0x9f, 0x61, b'x', 0xff, 0xff]);
+        assert!(transcode("[1, 2".as_bytes(), vec![]).is_err());
+        assert!(transcode("[1] 2".as_bytes(), vec![]).is_err());
+    }
+}
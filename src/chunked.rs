@@ -0,0 +1,324 @@
+/// A push parser fed the document a chunk at a time, for input arriving in
+/// pieces such as network bodies, keeping only an unfinished token between
+/// chunks.
+use crate::lexer::decode_string;
+use crate::number::parse_number;
+use crate::parser::{LimitCheck, ParseLimits};
+use crate::sink::{ValueBuilder, ValueSink};
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Value,
+    FirstElement,
+    FirstKey,
+    Key,
+    Colon,
+    AfterValue,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// Parses a document split into chunks at arbitrary byte positions into a
+/// `ValueSink`, as soon as each token is complete.
+pub struct ChunkedParser<S: ValueSink = ValueBuilder> {
+    sink: S,
+    // bytes of the token cut off by the end of the last chunk.
+    pending: Vec<u8>,
+    // how far into `pending` a string has been scanned for its closing quote.
+    scanned: usize,
+    stack: Vec<Container>,
+    state: State,
+    limits: LimitCheck,
+}
+
+impl ChunkedParser {
+    /// A parser building a `Value`.
+    pub fn new() -> ChunkedParser {
+        ChunkedParser::with_sink(ValueBuilder::new())
+    }
+}
+
+impl Default for ChunkedParser {
+    fn default() -> ChunkedParser {
+        ChunkedParser::new()
+    }
+}
+
+impl<S: ValueSink> ChunkedParser<S> {
+    pub fn with_sink(sink: S) -> ChunkedParser<S> {
+        ChunkedParser {
+            sink,
+            pending: vec![],
+            scanned: 0,
+            stack: vec![],
+            state: State::Value,
+            limits: LimitCheck::new(ParseLimits::default()),
+        }
+    }
+
+    /// Fail once the document exceeds `limits`, `ParseLimits::default()`
+    /// unless set here. Set them before feeding the first chunk.
+    pub fn set_limits(&mut self, limits: ParseLimits) {
+        self.limits = LimitCheck::new(limits);
+    }
+
+    /// Parse the next chunk of the document.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), &'static str> {
+        if self.pending.is_empty() {
+            let used = self.tokens(chunk, false)?;
+            self.pending.extend_from_slice(&chunk[used..]);
+        } else {
+            let mut pending = std::mem::take(&mut self.pending);
+            pending.extend_from_slice(chunk);
+            let used = self.tokens(&pending, false)?;
+            pending.drain(..used);
+            self.pending = pending;
+        }
+        Ok(())
+    }
+
+    /// End the document and return what the sink built.
+    pub fn finish(mut self) -> Result<S::Output, &'static str> {
+        let pending = std::mem::take(&mut self.pending);
+        let used = self.tokens(&pending, true)?;
+        if used < pending.len() {
+            return Err("unexpected end of input.");
+        }
+        match self.state {
+            State::Done => self.sink.finish(),
+            _ => Err("unexpected end of input."),
+        }
+    }
+
+    // parse the complete tokens at the start of `b`, returning how many
+    // bytes they took. With `last`, a number or keyword may end at the end.
+    fn tokens(&mut self, b: &[u8], last: bool) -> Result<usize, &'static str> {
+        let mut i = 0;
+        loop {
+            while i < b.len() && b[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            if i == b.len() {
+                return Ok(i);
+            }
+            let end = match b[i] {
+                b'"' => match self.string_end(b, i) {
+                    Some(end) => end,
+                    None => {
+                        // fail before buffering more of a string that is
+                        // already too long.
+                        self.limits.string(b.len() - i - 1, self.expects_key())?;
+                        return Ok(i);
+                    }
+                },
+                b'-' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' => {
+                    match b[i..]
+                        .iter()
+                        .position(|c| !c.is_ascii_alphanumeric() && !b"-+.".contains(c))
+                    {
+                        Some(len) => i + len,
+                        None if last => b.len(),
+                        None => return Ok(i),
+                    }
+                }
+                _ => i + 1,
+            };
+            self.token(&b[i..end])?;
+            self.scanned = 0;
+            i = end;
+        }
+    }
+
+    // the end of the string starting at `start`, remembering how far the
+    // scan got when it is not complete yet.
+    fn string_end(&mut self, b: &[u8], start: usize) -> Option<usize> {
+        let mut i = start + 1 + self.scanned;
+        while i < b.len() {
+            match b[i] {
+                b'"' => return Some(i + 1),
+                b'\\' if i + 1 == b.len() => break,
+                b'\\' => i += 2,
+                _ => i += 1,
+            }
+        }
+        self.scanned = i.min(b.len()) - start - 1;
+        None
+    }
+
+    fn token(&mut self, t: &[u8]) -> Result<(), &'static str> {
+        let c = t[0];
+        match self.state {
+            State::Done => return Err("trailing string after json."),
+            State::FirstElement if c == b']' => return self.end(),
+            State::FirstKey if c == b'}' => return self.end(),
+            State::FirstKey | State::Key => {
+                if c != b'"' {
+                    return Err("expected string");
+                }
+                self.limits.string(t.len() - 2, true)?;
+                self.sink.key(string(t)?)?;
+                self.state = State::Colon;
+                return Ok(());
+            }
+            State::Colon => {
+                if c != b':' {
+                    return Err("colon expected.");
+                }
+                self.state = State::Value;
+                return Ok(());
+            }
+            State::AfterValue => {
+                return match (c, self.stack.last()) {
+                    (b',', Some(Container::Object)) => {
+                        self.state = State::Key;
+                        Ok(())
+                    }
+                    (b',', Some(Container::Array)) => {
+                        self.state = State::Value;
+                        Ok(())
+                    }
+                    (b'}', Some(Container::Object)) | (b']', Some(Container::Array)) => self.end(),
+                    _ => Err("comma or closing bracket expected."),
+                };
+            }
+            State::Value | State::FirstElement => {}
+        }
+        self.limits.value()?;
+        match c {
+            b'{' => {
+                self.limits.open(false)?;
+                self.sink.start_object()?;
+                self.stack.push(Container::Object);
+                self.state = State::FirstKey;
+                return Ok(());
+            }
+            b'[' => {
+                self.limits.open(true)?;
+                self.sink.start_array()?;
+                self.stack.push(Container::Array);
+                self.state = State::FirstElement;
+                return Ok(());
+            }
+            b'"' => {
+                self.limits.string(t.len() - 2, false)?;
+                self.sink.string(string(t)?)?
+            }
+            b'-' | b'0'..=b'9' => {
+                let n = parse_number(t).ok_or("cannot parse the string into the numbers.")?;
+                self.sink.number(n)?
+            }
+            _ => match t {
+                b"true" => self.sink.bool(true)?,
+                b"false" => self.sink.bool(false)?,
+                b"null" => self.sink.null()?,
+                _ if c.is_ascii_alphabetic() => return Err("Unsupported keyword or number."),
+                _ => return Err("unexpected character."),
+            },
+        }
+        self.after_value();
+        Ok(())
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(self.state, State::FirstKey | State::Key)
+    }
+
+    // close the innermost container.
+    fn end(&mut self) -> Result<(), &'static str> {
+        self.limits.close();
+        match self.stack.pop() {
+            Some(Container::Object) => self.sink.end_object()?,
+            _ => self.sink.end_array()?,
+        }
+        self.after_value();
+        Ok(())
+    }
+
+    fn after_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::AfterValue
+        };
+    }
+}
+
+fn string(t: &[u8]) -> Result<String, &'static str> {
+    if t.iter().any(|&c| c < 0x20) {
+        return Err("control character in string.");
+    }
+    decode_string(t).map(|s| s.into_owned())
+}
+
+/// Parse a document given as chunks.
+pub fn parse_chunks<I, B>(chunks: I) -> Result<Value, &'static str>
+where
+    I: IntoIterator<Item = B>,
+    B: AsRef<[u8]>,
+{
+    let mut parser = ChunkedParser::new();
+    for chunk in chunks {
+        parser.feed(chunk.as_ref())?;
+    }
+    parser.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_with, Backend, MAX_DEPTH};
+
+    #[test]
+    fn test_chunked() {
+        let s = r#" {"a": [1, -2.5e3, "x\"éy", true, null], "b": {}, "c": []} "#;
+        let expected = parse_with(s, Backend::Events).unwrap();
+        for size in 1..s.len() {
+            let chunks = s.as_bytes().chunks(size);
+            assert_eq!(parse_chunks(chunks), Ok(expected.clone()), "{}", size);
+        }
+        assert_eq!(parse_chunks(["12", "34"]), Ok(Value::Number(1234.0)));
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!(parse_chunks([&deep]), Err("json nested too deeply."));
+        assert!(parse_chunks([&deep[1..deep.len() - 1]]).is_ok());
+        let mut parser = ChunkedParser::new();
+        parser.set_limits(ParseLimits {
+            max_depth: 1,
+            ..ParseLimits::default()
+        });
+        assert_eq!(parser.feed(b"[1, [2]]"), Err("json nested too deeply."));
+        let mut parser = ChunkedParser::new();
+        parser.set_limits(ParseLimits {
+            max_key_len: 1,
+            max_string_len: 3,
+            max_array_len: 2,
+            ..ParseLimits::default()
+        });
+        assert_eq!(parser.feed(b"{\"a\": [\"abc\", {\"b\": 1}]"), Ok(()));
+        assert_eq!(parser.feed(b", \"c\": [1, 2, 3]}"), Err("array too long."));
+        let mut parser = ChunkedParser::new();
+        parser.set_limits(ParseLimits {
+            max_string_len: 3,
+            ..ParseLimits::default()
+        });
+        // an unfinished string is rejected once it is too long.
+        assert_eq!(parser.feed(b"[\"abcd"), Err("string too long."));
+        for bad in &[
+            "[1,]",
+            "{\"a\" 1}",
+            "[1] 2",
+            "[tru]",
+            "[1",
+            "\"a\u{1}\"",
+            "{1: 2}",
+        ] {
+            let chunks = bad.as_bytes().chunks(1);
+            assert!(parse_chunks(chunks).is_err(), "{}", bad);
+        }
+    }
+}
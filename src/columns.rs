@@ -0,0 +1,248 @@
+use std::io::Read;
+
+/// Pull selected fields out of every element of an array into columns, for
+/// analytics code that only needs a few fields of each row.
+use crate::access::{lookup, pointer_tokens};
+use crate::event::{Event, EventReader};
+use crate::value::Value;
+
+/// The values of one field across all elements, typed by the values found.
+/// A missing field or `null` is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    /// Only `null`s or missing fields so far, as many as counted.
+    Null(usize),
+    Bool(Vec<Option<bool>>),
+    Number(Vec<Option<f64>>),
+    String(Vec<Option<String>>),
+    /// Values of several types, or arrays and objects.
+    Mixed(Vec<Value>),
+}
+
+impl Column {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Null(n) => *n,
+            Column::Bool(v) => v.len(),
+            Column::Number(v) => v.len(),
+            Column::String(v) => v.len(),
+            Column::Mixed(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value in the row `i`.
+    pub fn get(&self, i: usize) -> Option<Value> {
+        if i >= self.len() {
+            return None;
+        }
+        Some(match self {
+            Column::Null(_) => Value::Null,
+            Column::Bool(v) => v[i].map_or(Value::Null, Value::Bool),
+            Column::Number(v) => v[i].map_or(Value::Null, Value::Number),
+            Column::String(v) => v[i].clone().map_or(Value::Null, Value::String),
+            Column::Mixed(v) => v[i].clone(),
+        })
+    }
+
+    fn push(&mut self, value: Value) {
+        match (&mut *self, value) {
+            (Column::Null(n), Value::Null) => *n += 1,
+            (Column::Null(n), Value::Bool(b)) => *self = Column::Bool(nulls(*n, b)),
+            (Column::Null(n), Value::Number(x)) => *self = Column::Number(nulls(*n, x)),
+            (Column::Null(n), Value::String(s)) => *self = Column::String(nulls(*n, s)),
+            (Column::Bool(v), Value::Null) => v.push(None),
+            (Column::Bool(v), Value::Bool(b)) => v.push(Some(b)),
+            (Column::Number(v), Value::Null) => v.push(None),
+            (Column::Number(v), Value::Number(x)) => v.push(Some(x)),
+            (Column::String(v), Value::Null) => v.push(None),
+            (Column::String(v), Value::String(s)) => v.push(Some(s)),
+            (Column::Mixed(v), value) => v.push(value),
+            (_, value) => {
+                let mut values: Vec<Value> = (0..self.len()).filter_map(|i| self.get(i)).collect();
+                values.push(value);
+                *self = Column::Mixed(values);
+            }
+        }
+    }
+}
+
+fn nulls<T>(n: usize, value: T) -> Vec<Option<T>> {
+    let mut v: Vec<Option<T>> = std::iter::repeat_with(|| None).take(n).collect();
+    v.push(Some(value));
+    v
+}
+
+fn compile(pointers: &[&str]) -> Result<Vec<Vec<String>>, &'static str> {
+    pointers
+        .iter()
+        .map(|p| pointer_tokens(p).ok_or("pointer not found."))
+        .collect()
+}
+
+/// Extract the fields at the JSON `pointers` from every element of the array
+/// `value`, one column per pointer.
+pub fn extract_columns(value: &Value, pointers: &[&str]) -> Result<Vec<Column>, &'static str> {
+    let paths = compile(pointers)?;
+    let elements = match value {
+        Value::Array(v) => v,
+        _ => return Err("expect array"),
+    };
+    let mut columns = vec![Column::Null(0); paths.len()];
+    for element in elements {
+        for (column, path) in columns.iter_mut().zip(&paths) {
+            column.push(lookup(element, path).cloned().unwrap_or(Value::Null));
+        }
+    }
+    Ok(columns)
+}
+
+/// Extract columns like `extract_columns` from the array at the root of the
+/// document in `reader`, building `Value`s only for the selected fields.
+pub fn extract_columns_from<R: Read>(
+    reader: R,
+    pointers: &[&str],
+) -> Result<Vec<Column>, &'static str> {
+    let paths = compile(pointers)?;
+    let mut events = EventReader::new(reader);
+    if events.next_event()? != Some(Event::StartArray) {
+        return Err("expect array");
+    }
+    let mut columns = vec![Column::Null(0); paths.len()];
+    let mut cells = vec![Value::Null; paths.len()];
+    loop {
+        match events.next_event()? {
+            Some(Event::EndArray) => break,
+            Some(event) => read_element(&mut events, event, &paths, &mut cells)?,
+            None => return Err("unexpected end of input."),
+        }
+        for (column, cell) in columns.iter_mut().zip(&mut cells) {
+            column.push(std::mem::replace(cell, Value::Null));
+        }
+    }
+    // reject anything after the array.
+    events.next_event()?;
+    Ok(columns)
+}
+
+// an open container and the position of the value currently being read in it.
+enum Frame {
+    Object(Option<String>),
+    Array(Option<usize>),
+}
+
+// whether the open containers `frames` lead along `path`.
+fn is_prefix(frames: &[Frame], path: &[String]) -> bool {
+    frames.len() <= path.len()
+        && frames.iter().zip(path).all(|(frame, token)| match frame {
+            Frame::Object(key) => key.as_ref() == Some(token),
+            Frame::Array(index) => index.is_some() && token.parse().ok() == *index,
+        })
+}
+
+// read the element starting with `first` into `cells`, skipping the parts of
+// it no path leads into.
+fn read_element<R: Read>(
+    events: &mut EventReader<R>,
+    first: Event,
+    paths: &[Vec<String>],
+    cells: &mut [Value],
+) -> Result<(), &'static str> {
+    let mut frames: Vec<Frame> = vec![];
+    let mut event = first;
+    loop {
+        match event {
+            Event::Key(k) => {
+                if let Some(Frame::Object(key)) = frames.last_mut() {
+                    *key = Some(k);
+                }
+            }
+            Event::EndObject | Event::EndArray => {
+                frames.pop();
+            }
+            event => {
+                if let Some(Frame::Array(index)) = frames.last_mut() {
+                    *index = Some(index.map_or(0, |i| i + 1));
+                }
+                let is_container = matches!(event, Event::StartObject | Event::StartArray);
+                let depth = frames.len();
+                if paths
+                    .iter()
+                    .any(|p| p.len() == depth && is_prefix(&frames, p))
+                {
+                    // a selected value, which other paths may lead into.
+                    let value = events.build_value(event)?;
+                    for (cell, path) in cells.iter_mut().zip(paths) {
+                        if is_prefix(&frames, path) {
+                            *cell = lookup(&value, &path[depth..])
+                                .cloned()
+                                .unwrap_or(Value::Null);
+                        }
+                    }
+                } else if is_container && paths.iter().any(|p| is_prefix(&frames, p)) {
+                    frames.push(match event {
+                        Event::StartObject => Frame::Object(None),
+                        _ => Frame::Array(None),
+                    });
+                } else if is_container {
+                    // skip the rest of the container and its closing bracket.
+                    while events.skip_value()? {}
+                    events.next_event()?;
+                }
+            }
+        }
+        if frames.is_empty() {
+            return Ok(());
+        }
+        event = events.next_event()?.ok_or("unexpected end of input.")?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_with, Backend};
+
+    #[test]
+    fn test_extract_columns() {
+        let doc = r#"[{"id": 1, "user": {"name": "a", "tags": [true]}, "x": 1},
+            {"id": 2, "user": {"name": null, "tags": [false, 1]}, "x": "b"},
+            {"skip": [[{}]], "user": {"tags": []}},
+            {"id": -3, "user": 5}]"#;
+        let pointers = ["/id", "/user/name", "/user/tags/0", "/x", "/none"];
+        let expected = vec![
+            Column::Number(vec![Some(1.0), Some(2.0), None, Some(-3.0)]),
+            Column::String(vec![Some("a".to_owned()), None, None, None]),
+            Column::Bool(vec![Some(true), Some(false), None, None]),
+            Column::Mixed(vec![
+                Value::Number(1.0),
+                Value::String("b".to_owned()),
+                Value::Null,
+                Value::Null,
+            ]),
+            Column::Null(4),
+        ];
+        let value = parse_with(doc, Backend::Events).unwrap();
+        assert_eq!(extract_columns(&value, &pointers), Ok(expected.clone()));
+        assert_eq!(
+            extract_columns_from(doc.as_bytes(), &pointers),
+            Ok(expected)
+        );
+        assert_eq!(
+            extract_columns_from("[[1, 2], 3]".as_bytes(), &["", "/1"]),
+            Ok(vec![
+                Column::Mixed(vec![
+                    Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]),
+                    Value::Number(3.0),
+                ]),
+                Column::Number(vec![Some(2.0), None]),
+            ])
+        );
+        assert!(extract_columns_from("{}".as_bytes(), &["/a"]).is_err());
+        assert!(extract_columns_from("[{\"a\": [}]".as_bytes(), &["/b"]).is_err());
+        assert!(extract_columns(&value, &["a"]).is_err());
+    }
+}
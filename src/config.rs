@@ -0,0 +1,399 @@
+use std::fs;
+use std::path::Path;
+
+/// Load application configuration: a file, defaults, environment variables
+/// and command-line overrides, read into a typed value.
+use crate::access::FromValue;
+use crate::defaults::apply_defaults;
+use crate::env::overlay_vars;
+use crate::parser::{parse_with, Backend};
+use crate::value::Value;
+
+/// Loads configuration files, layering in order:
+///
+/// 1. the file, JSON or, by its extension, JSON with comments and trailing
+///    commas (`.jsonc`) or JSON5 (`.json5`),
+/// 2. `defaults` for every member the file leaves out,
+/// 3. environment variables starting with `env_prefix`, as `overlay_env`
+///    reads them,
+/// 4. overrides like `db.port=5432`, mapped as environment variables are
+///    with `.` separating the path.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    defaults: Option<Value>,
+    env_prefix: Option<String>,
+    overrides: Vec<(String, String)>,
+}
+
+impl ConfigLoader {
+    pub fn new() -> ConfigLoader {
+        ConfigLoader::default()
+    }
+
+    pub fn defaults(mut self, defaults: Value) -> ConfigLoader {
+        self.defaults = Some(defaults);
+        self
+    }
+
+    pub fn env_prefix(mut self, prefix: &str) -> ConfigLoader {
+        self.env_prefix = Some(prefix.to_owned());
+        self
+    }
+
+    /// Add overrides written `path=value`, e.g. from command-line arguments.
+    pub fn overrides<I, A>(mut self, args: I) -> Result<ConfigLoader, &'static str>
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<str>,
+    {
+        for arg in args {
+            let (path, value) = arg
+                .as_ref()
+                .split_once('=')
+                .ok_or("override is not path=value.")?;
+            let name = format!("{}__{}", OVERRIDE_PREFIX, path.replace('.', "__"));
+            self.overrides.push((name, value.to_owned()));
+        }
+        Ok(self)
+    }
+
+    /// Load the configuration with the file at `path`.
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Value, &'static str> {
+        self.load_from(path.as_ref(), std::env::vars())
+    }
+
+    /// Load the configuration like `load` and read it as a `T`.
+    pub fn load_as<T: FromValue, P: AsRef<Path>>(&self, path: P) -> Result<T, &'static str> {
+        T::from_value(&self.load(path)?).ok_or("unexpected type in config.")
+    }
+
+    // `load` with the environment variables `vars`.
+    fn load_from<I>(&self, path: &Path, vars: I) -> Result<Value, &'static str>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let text = fs::read_to_string(path).map_err(|_| "failed to read input.")?;
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => text,
+            Some("jsonc") => strip_jsonc(&text)?,
+            Some("json5") => json5_to_json(&text)?,
+            _ => return Err("unsupported config format."),
+        };
+        let mut value = parse_with(&text, Backend::Events)?;
+        if let Some(defaults) = &self.defaults {
+            apply_defaults(&mut value, defaults);
+        }
+        if let Some(prefix) = &self.env_prefix {
+            overlay_vars(&mut value, prefix, vars)?;
+        }
+        overlay_vars(&mut value, OVERRIDE_PREFIX, self.overrides.iter().cloned())?;
+        Ok(value)
+    }
+}
+
+// overrides go through `overlay_vars` as variables with this prefix.
+const OVERRIDE_PREFIX: &str = "OVERRIDE";
+
+// blank out the comments of `s` and the commas right before a closing
+// bracket, keeping every other byte where it was.
+fn strip_jsonc(s: &str) -> Result<String, &'static str> {
+    let b = s.as_bytes();
+    let mut out = b.to_vec();
+    // the last comma seen, until something other than trivia follows it.
+    let mut comma = None;
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'"' => {
+                comma = None;
+                i += 1;
+                while i < b.len() && b[i] != b'"' {
+                    i += if b[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'/' if b.get(i + 1) == Some(&b'/') => {
+                let end = b[i..]
+                    .iter()
+                    .position(|&c| c == b'\n')
+                    .map_or(b.len(), |p| i + p);
+                blank(&mut out[i..end]);
+                i = end;
+                continue;
+            }
+            b'/' if b.get(i + 1) == Some(&b'*') => {
+                let end = s[i + 2..].find("*/").ok_or("unterminated comment.")? + i + 4;
+                blank(&mut out[i..end]);
+                i = end;
+                continue;
+            }
+            b',' => comma = Some(i),
+            b']' | b'}' => {
+                if let Some(c) = comma.take() {
+                    out[c] = b' ';
+                }
+            }
+            c if c.is_ascii_whitespace() => {}
+            _ => comma = None,
+        }
+        i += 1;
+    }
+    // only ASCII bytes outside strings were replaced with spaces.
+    String::from_utf8(out).map_err(|_| "invalid utf-8 in string.")
+}
+
+fn blank(bytes: &mut [u8]) {
+    for c in bytes.iter_mut().filter(|c| **c != b'\n') {
+        *c = b' ';
+    }
+}
+
+// rewrite the JSON5 in `s` as JSON: comments and trailing commas go, keys
+// are quoted, strings are double-quoted and numbers are written as decimals.
+fn json5_to_json(s: &str) -> Result<String, &'static str> {
+    let c: Vec<char> = s.chars().collect();
+    let mut out = String::with_capacity(s.len());
+    // where the last comma went in `out`, until something other than trivia
+    // follows it.
+    let mut comma = None;
+    let mut i = 0;
+    while i < c.len() {
+        let t = skip_trivia(&c, i)?;
+        if t > i {
+            out.push(' ');
+            i = t;
+            continue;
+        }
+        match c[i] {
+            ',' => {
+                comma = Some(out.len());
+                out.push(',');
+                i += 1;
+                continue;
+            }
+            ']' | '}' => {
+                if let Some(at) = comma {
+                    out.replace_range(at..at + 1, " ");
+                }
+                out.push(c[i]);
+                i += 1;
+            }
+            '"' | '\'' => i = json5_string(&c, i, &mut out)?,
+            '+' | '-' | '.' | '0'..='9' => i = json5_number(&c, i, &mut out)?,
+            ch if is_ident(ch) => {
+                let end = (i..c.len()).find(|&j| !is_ident(c[j])).unwrap_or(c.len());
+                let word: String = c[i..end].iter().collect();
+                match word.as_str() {
+                    "Infinity" | "NaN" => return Err("infinity or nan in config."),
+                    _ if c.get(skip_trivia(&c, end)?) == Some(&':') => {
+                        out.push('"');
+                        out.push_str(&word);
+                        out.push('"');
+                    }
+                    _ => out.push_str(&word),
+                }
+                i = end;
+            }
+            ch => {
+                out.push(ch);
+                i += 1;
+            }
+        }
+        comma = None;
+    }
+    Ok(out)
+}
+
+// the index after the whitespace and comments starting at `i`.
+fn skip_trivia(c: &[char], mut i: usize) -> Result<usize, &'static str> {
+    loop {
+        match (c.get(i), c.get(i + 1)) {
+            (Some(ch), _) if ch.is_whitespace() || *ch == '\u{feff}' => i += 1,
+            (Some('/'), Some('/')) => {
+                i = (i..c.len()).find(|&j| c[j] == '\n').unwrap_or(c.len());
+            }
+            (Some('/'), Some('*')) => {
+                i = (i + 2..c.len())
+                    .find(|&j| c[j] == '*' && c.get(j + 1) == Some(&'/'))
+                    .ok_or("unterminated comment.")?
+                    + 2;
+            }
+            _ => return Ok(i),
+        }
+    }
+}
+
+fn is_ident(ch: char) -> bool {
+    ch == '_' || ch == '$' || ch.is_alphanumeric()
+}
+
+// write the string quoted at `i` as a JSON string, returning the index after
+// it.
+fn json5_string(c: &[char], i: usize, out: &mut String) -> Result<usize, &'static str> {
+    let quote = c[i];
+    let mut i = i + 1;
+    out.push('"');
+    loop {
+        match *c.get(i).ok_or("unterminated string.")? {
+            ch if ch == quote => break,
+            '"' => out.push_str("\\\""),
+            '\\' => {
+                i += 1;
+                match *c.get(i).ok_or("unterminated string.")? {
+                    // a line continuation.
+                    '\r' if c.get(i + 1) == Some(&'\n') => i += 1,
+                    '\n' | '\r' | '\u{2028}' | '\u{2029}' => {}
+                    ch @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {
+                        out.push('\\');
+                        out.push(ch);
+                    }
+                    'u' => {
+                        let hex = c.get(i + 1..i + 5).ok_or("invalid unicode escape.")?;
+                        if !hex.iter().all(|h| h.is_ascii_hexdigit()) {
+                            return Err("invalid unicode escape.");
+                        }
+                        out.push_str("\\u");
+                        out.extend(hex);
+                        i += 4;
+                    }
+                    'x' => {
+                        let hex = c.get(i + 1..i + 3).ok_or("invalid hex escape.")?;
+                        if !hex.iter().all(|h| h.is_ascii_hexdigit()) {
+                            return Err("invalid hex escape.");
+                        }
+                        out.push_str("\\u00");
+                        out.extend(hex);
+                        i += 2;
+                    }
+                    'v' => out.push_str("\\u000b"),
+                    '0' if !c.get(i + 1).is_some_and(char::is_ascii_digit) => {
+                        out.push_str("\\u0000")
+                    }
+                    '1'..='9' => return Err("invalid escape."),
+                    ch => out.push(ch),
+                }
+            }
+            '\n' | '\r' => return Err("unterminated string."),
+            ch => out.push(ch),
+        }
+        i += 1;
+    }
+    out.push('"');
+    Ok(i + 1)
+}
+
+// write the number at `i` in decimal, returning the index after it.
+fn json5_number(c: &[char], mut i: usize, out: &mut String) -> Result<usize, &'static str> {
+    match c[i] {
+        '-' => {
+            out.push('-');
+            i += 1;
+        }
+        '+' => i += 1,
+        _ => {}
+    }
+    if c[i..].starts_with(&['I']) || c[i..].starts_with(&['N']) {
+        return Err("infinity or nan in config.");
+    }
+    if c[i..].starts_with(&['0', 'x']) || c[i..].starts_with(&['0', 'X']) {
+        let end = (i + 2..c.len())
+            .find(|&j| !c[j].is_ascii_hexdigit())
+            .unwrap_or(c.len());
+        let hex: String = c[i + 2..end].iter().collect();
+        let n = u64::from_str_radix(&hex, 16).map_err(|_| "invalid number.")?;
+        out.push_str(&n.to_string());
+        return Ok(end);
+    }
+    if c.get(i) == Some(&'.') {
+        out.push('0');
+    }
+    while let Some(&ch) = c.get(i) {
+        match ch {
+            '0'..='9' => out.push(ch),
+            '.' if !c.get(i + 1).is_some_and(char::is_ascii_digit) => {}
+            '.' => out.push(ch),
+            'e' | 'E' => {
+                out.push(ch);
+                if let Some(&sign @ ('+' | '-')) = c.get(i + 1) {
+                    out.push(sign);
+                    i += 1;
+                }
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+    Ok(i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[derive(Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        debug: bool,
+    }
+
+    impl FromValue for Server {
+        fn from_value(value: &Value) -> Option<Server> {
+            Some(Server {
+                host: value.get_path_or("server/host", None)?,
+                port: value.get_path_or("server/port", None)?,
+                debug: value.get_or("debug", None)?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("config-test-{}.jsonc", std::process::id()));
+        fs::write(
+            &path,
+            "{\n  // where to listen\n  \"server\": {\"host\": \"a/*b*/\", /* port */},\n}\n",
+        )
+        .unwrap();
+        let loader = ConfigLoader::new()
+            .defaults(parse(r#"{"server": {"port": 80}, "debug": false}"#).unwrap())
+            .env_prefix("APP")
+            .overrides(["debug=true"])
+            .unwrap();
+        let vars = vec![("APP__SERVER__PORT".to_owned(), "9000".to_owned())];
+        let value = loader.load_from(&path, vars).unwrap();
+        assert_eq!(
+            Server::from_value(&value),
+            Some(Server {
+                host: "a/*b*/".to_owned(),
+                port: 9000,
+                debug: true,
+            })
+        );
+        assert!(ConfigLoader::new().overrides(["debug"]).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_json5() {
+        let text = r#"// a comment
+            {
+                unquoted: 'single "quoted"',
+                'key\x41': "line \
+continued",
+                hex: -0xFF, lead: .5, trail: +2., exp: 1.e2,
+                list: [1, /* two */ 2,],
+            }"#;
+        assert_eq!(
+            parse(&json5_to_json(text).unwrap()),
+            parse(
+                r#"{"unquoted": "single \"quoted\"", "keyA": "line continued",
+                    "hex": -255, "lead": 0.5, "trail": 2, "exp": 100,
+                    "list": [1, 2]}"#
+            )
+        );
+        for text in &["{a: Infinity}", "[-NaN]", "['a]", "[1 /* x]", r#"["\1"]"#] {
+            assert!(json5_to_json(text).and_then(|s| parse(&s)).is_err());
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::panic;
+use std::path::Path;
+
+/// Run the cases of the JSON parsing test suite
+/// (https://github.com/nst/JSONTestSuite) against a parser backend.
+use crate::parser::{parse_checked, parse_with, Backend};
+
+/// What the suite expects of a case, from the prefix of its file name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expectation {
+    /// `y_`: the input must be accepted.
+    Accept,
+    /// `n_`: the input must be rejected.
+    Reject,
+    /// `i_`: parsers may either accept or reject the input.
+    Either,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Accepted,
+    Rejected,
+    Panicked,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    /// The file name of the case.
+    pub name: String,
+    pub expected: Expectation,
+    pub outcome: Outcome,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        !matches!(
+            (self.expected, self.outcome),
+            (_, Outcome::Panicked)
+                | (Expectation::Accept, Outcome::Rejected)
+                | (Expectation::Reject, Outcome::Accepted)
+        )
+    }
+}
+
+/// Parse every `y_`, `n_` and `i_` `.json` file in `dir`, usually the suite's
+/// `test_parsing` directory, and report the outcome of each, sorted by name.
+///
+/// The token backend goes through `parse_checked` so that the deeply nested
+/// cases are rejected rather than overflowing the stack.
+pub fn run(dir: impl AsRef<Path>, backend: Backend) -> Result<Vec<CaseResult>, &'static str> {
+    let entries = fs::read_dir(dir).map_err(|_| "failed to read input.")?;
+    let mut results = vec![];
+    for entry in entries {
+        let path = entry.map_err(|_| "failed to read input.")?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) if name.ends_with(".json") => name.to_owned(),
+            _ => continue,
+        };
+        let expected = match name.get(..2) {
+            Some("y_") => Expectation::Accept,
+            Some("n_") => Expectation::Reject,
+            Some("i_") => Expectation::Either,
+            _ => continue,
+        };
+        let bytes = fs::read(&path).map_err(|_| "failed to read input.")?;
+        let outcome = match panic::catch_unwind(|| accepts(&bytes, backend)) {
+            Ok(true) => Outcome::Accepted,
+            Ok(false) => Outcome::Rejected,
+            Err(_) => Outcome::Panicked,
+        };
+        results.push(CaseResult {
+            name,
+            expected,
+            outcome,
+        });
+    }
+    results.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(results)
+}
+
+fn accepts(bytes: &[u8], backend: Backend) -> bool {
+    let s = match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    match backend {
+        Backend::Tokens => parse_checked(s).is_ok(),
+        Backend::Events => parse_with(s, Backend::Events).is_ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let dir = std::env::temp_dir().join(format!("conformance-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cases: &[(&str, &[u8])] = &[
+            ("y_array.json", b"[1, \"a\"]"),
+            ("n_trailing_comma.json", b"[1,]"),
+            ("n_bad_utf8.json", b"[\"\xff\"]"),
+            ("i_deep.json", &[b'['; 1000]),
+            ("readme.txt", b"not a case"),
+        ];
+        for (name, content) in cases {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let results = run(&dir, Backend::Events).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "i_deep.json",
+                "n_bad_utf8.json",
+                "n_trailing_comma.json",
+                "y_array.json"
+            ]
+        );
+        assert!(results.iter().all(|r| r.passed()));
+        assert_eq!(results[3].outcome, Outcome::Accepted);
+    }
+
+    // set JSON_TEST_SUITE to the suite's test_parsing directory to run it.
+    #[test]
+    fn test_suite() {
+        let dir = match std::env::var_os("JSON_TEST_SUITE") {
+            Some(dir) => dir,
+            None => return,
+        };
+        for backend in &[Backend::Tokens, Backend::Events] {
+            let results = run(&dir, *backend).unwrap();
+            for r in results.iter().filter(|r| !r.passed()) {
+                println!("{:?} {}: {:?}", backend, r.name, r.outcome);
+            }
+            assert!(results.iter().all(|r| r.outcome != Outcome::Panicked));
+        }
+    }
+}
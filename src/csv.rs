@@ -0,0 +1,163 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::serializer;
+/// Serialize arrays of objects to CSV.
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// The columns to write, in order. When `None` every column found in the
+    /// rows is written, sorted by name.
+    pub columns: Option<Vec<String>>,
+    /// When set, nested objects and arrays are flattened into one column per
+    /// leaf named by joining the keys (or indices) with this separator, e.g.
+    /// `address.city`. Otherwise nested values are written as compact JSON.
+    pub flatten: Option<String>,
+    pub delimiter: char,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            columns: None,
+            flatten: Some(".".to_owned()),
+            delimiter: ',',
+        }
+    }
+}
+
+/// Serialize an array of objects to CSV with a header row, one row per object.
+pub fn to_csv(value: &Value, options: &CsvOptions) -> Result<String, &'static str> {
+    let items = match value {
+        Value::Array(v) => v,
+        _ => return Err("array of objects expected."),
+    };
+    let mut rows = vec![];
+    for item in items {
+        let mut row = HashMap::new();
+        match item {
+            Value::Object(m) => {
+                for (k, v) in m {
+                    flatten(k.clone(), v, options.flatten.as_deref(), &mut row);
+                }
+            }
+            _ => return Err("array of objects expected."),
+        }
+        rows.push(row);
+    }
+    let columns = match &options.columns {
+        Some(columns) => columns.clone(),
+        None => {
+            let names: BTreeSet<&String> = rows.iter().flat_map(|r| r.keys()).collect();
+            names.into_iter().cloned().collect()
+        }
+    };
+    let mut out = String::new();
+    write_record(
+        &mut out,
+        columns.iter().map(|c| c.as_str()),
+        options.delimiter,
+    );
+    for row in &rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| row.get(c).map_or_else(String::new, |v| cell(v)))
+            .collect();
+        write_record(
+            &mut out,
+            cells.iter().map(|c| c.as_str()),
+            options.delimiter,
+        );
+    }
+    Ok(out)
+}
+
+// collect the columns of `value` into `row`, descending into nested values
+// when a separator is given.
+fn flatten<'a>(
+    name: String,
+    value: &'a Value,
+    separator: Option<&str>,
+    row: &mut HashMap<String, &'a Value>,
+) {
+    match (value, separator) {
+        (Value::Object(m), Some(sep)) if !m.is_empty() => {
+            for (k, v) in m {
+                flatten(format!("{}{}{}", name, sep, k), v, separator, row);
+            }
+        }
+        (Value::Array(a), Some(sep)) if !a.is_empty() => {
+            for (i, v) in a.iter().enumerate() {
+                flatten(format!("{}{}{}", name, sep, i), v, separator, row);
+            }
+        }
+        _ => {
+            row.insert(name, value);
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => serializer::to_string(v),
+    }
+}
+
+fn write_record<'a>(out: &mut String, cells: impl Iterator<Item = &'a str>, delimiter: char) {
+    for (i, c) in cells.enumerate() {
+        if i > 0 {
+            out.push(delimiter);
+        }
+        if c.contains([delimiter, '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&c.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(c);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_to_csv() {
+        let mut v = parse(
+            r#"[{"name": "a", "n": 1, "addr": {"city": "x, y"}},
+                {"ok": true, "tags": ["p", "q"]}]"#,
+        )
+        .unwrap();
+        if let Value::Array(rows) = &mut v {
+            if let Value::Object(m) = &mut rows[1] {
+                m.insert("name".to_owned(), Value::String("say \"hi\"".to_owned()));
+            }
+        }
+        assert_eq!(
+            to_csv(&v, &CsvOptions::default()).unwrap(),
+            "addr.city,n,name,ok,tags.0,tags.1\r\n\
+             \"x, y\",1,a,,,\r\n\
+             ,,\"say \"\"hi\"\"\",true,p,q\r\n"
+        );
+        let options = CsvOptions {
+            columns: Some(vec!["name".to_owned(), "tags".to_owned()]),
+            flatten: None,
+            delimiter: ';',
+        };
+        assert_eq!(
+            to_csv(&v, &options).unwrap(),
+            "name;tags\r\na;\r\n\"say \"\"hi\"\"\";\"[\"\"p\"\",\"\"q\"\"]\"\r\n"
+        );
+        assert_eq!(
+            to_csv(&parse("[]").unwrap(), &CsvOptions::default()).unwrap(),
+            "\r\n"
+        );
+        assert!(to_csv(&parse("[1]").unwrap(), &CsvOptions::default()).is_err());
+        assert!(to_csv(&parse("{}").unwrap(), &CsvOptions::default()).is_err());
+    }
+}
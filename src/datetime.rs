@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+/// RFC 3339 timestamps in `Value`s, backed by chrono. Enabled by the `chrono`
+/// feature.
+use crate::value::Value;
+use chrono::{DateTime, FixedOffset, SecondsFormat, TimeZone};
+
+impl Value {
+    /// Parse a string value as an RFC 3339 timestamp, e.g.
+    /// `2024-05-01T12:30:00+02:00`. Returns `None` for other values.
+    pub fn as_datetime(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Value::String(s) => DateTime::parse_from_rfc3339(s).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Timestamps are stored as RFC 3339 strings, with `Z` for UTC and fractional
+/// seconds in milli, micro or nanosecond precision when there are any.
+impl<Tz: TimeZone> From<DateTime<Tz>> for Value
+where
+    Tz::Offset: Display,
+{
+    fn from(dt: DateTime<Tz>) -> Value {
+        Value::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serializer;
+    use chrono::Utc;
+
+    #[test]
+    fn test_datetime() {
+        let v = Value::String("2024-05-01T12:30:00.5+02:00".to_owned());
+        let dt = v.as_datetime().unwrap();
+        assert_eq!(dt.timestamp_millis(), 1_714_559_400_500);
+        assert_eq!(Value::from(dt).as_datetime(), Some(dt));
+        assert_eq!(
+            serializer::to_string(&Value::from(dt.with_timezone(&Utc))),
+            r#""2024-05-01T10:30:00.500Z""#
+        );
+        assert_eq!(Value::String("2024-05-01".to_owned()).as_datetime(), None);
+        assert_eq!(Value::Number(0.0).as_datetime(), None);
+    }
+}
@@ -0,0 +1,43 @@
+/// Fill in missing configuration from a defaults document.
+use crate::value::Value;
+
+/// Copy every member of `defaults` that `value` lacks into it, recursing into
+/// members that are objects in both. Present members are kept as they are,
+/// `null` ones included, and arrays are not merged.
+pub fn apply_defaults(value: &mut Value, defaults: &Value) {
+    if let (Value::Object(m), Value::Object(d)) = (value, defaults) {
+        for (key, default) in d {
+            match m.get_mut(key) {
+                Some(present) => apply_defaults(present, default),
+                None => {
+                    m.insert(key.clone(), default.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_apply_defaults() {
+        let mut value = parse(r#"{"db": {"host": "db1", "pool": null}, "tags": ["a"]}"#).unwrap();
+        let defaults = parse(
+            r#"{"db": {"host": "localhost", "port": 5432, "pool": {"size": 4}},
+                "tags": ["x", "y"], "debug": false}"#,
+        )
+        .unwrap();
+        apply_defaults(&mut value, &defaults);
+        assert_eq!(
+            value,
+            parse(
+                r#"{"db": {"host": "db1", "port": 5432, "pool": null},
+                    "tags": ["a"], "debug": false}"#
+            )
+            .unwrap()
+        );
+    }
+}
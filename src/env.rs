@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+/// Overlay environment variables onto configuration `Value`s, 12-factor style.
+use crate::number::parse_number;
+use crate::parser::{parse_with, Backend};
+use crate::value::Value;
+
+/// Overlay the environment variables starting with `prefix` followed by `__`
+/// onto `value`, e.g. `APP__DB__PORT=5432` sets `db.port` for the prefix `APP`.
+///
+/// See `overlay_vars` for how names and values are mapped.
+pub fn overlay_env(value: &mut Value, prefix: &str) -> Result<(), &'static str> {
+    overlay_vars(value, prefix, std::env::vars())
+}
+
+/// Overlay `vars` onto `value` like `overlay_env`.
+///
+/// Each name is split on `__` into a path; segments match existing keys
+/// case-insensitively, index existing arrays, and otherwise create lowercase
+/// keys. A variable replacing a number or a boolean must parse as one, one
+/// replacing an array or an object is parsed as JSON and one replacing a
+/// string stays a string. New values become `true`, `false`, `null` or a
+/// number when they read as one, and a string otherwise.
+pub fn overlay_vars<I>(value: &mut Value, prefix: &str, vars: I) -> Result<(), &'static str>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let prefix = format!("{}__", prefix);
+    for (name, raw) in vars {
+        let path = match name.strip_prefix(&prefix) {
+            Some(path) if !path.is_empty() => path,
+            _ => continue,
+        };
+        let mut slot = &mut *value;
+        for segment in path.split("__") {
+            slot = child(slot, segment)?;
+        }
+        *slot = coerce(slot, &raw)?;
+    }
+    Ok(())
+}
+
+fn child<'a>(slot: &'a mut Value, segment: &str) -> Result<&'a mut Value, &'static str> {
+    if let Value::Null = slot {
+        *slot = Value::Object(HashMap::new());
+    }
+    match slot {
+        Value::Object(m) => {
+            let key = m
+                .keys()
+                .find(|k| k.eq_ignore_ascii_case(segment))
+                .cloned()
+                .unwrap_or_else(|| segment.to_lowercase());
+            Ok(m.entry(key).or_insert(Value::Null))
+        }
+        Value::Array(v) => segment
+            .parse::<usize>()
+            .ok()
+            .and_then(move |i| v.get_mut(i))
+            .ok_or("environment variable indexes past an array."),
+        _ => Err("environment variable path crosses a scalar."),
+    }
+}
+
+fn coerce(current: &Value, raw: &str) -> Result<Value, &'static str> {
+    match current {
+        Value::String(_) => Ok(Value::String(raw.to_owned())),
+        Value::Number(_) => number(raw).ok_or("environment variable is not a number."),
+        Value::Bool(_) => match raw.to_ascii_lowercase().as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err("environment variable is not a boolean."),
+        },
+        Value::Array(_) | Value::Object(_) => parse_with(raw, Backend::Events),
+        Value::Null => Ok(match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ => number(raw).unwrap_or_else(|| Value::String(raw.to_owned())),
+        }),
+    }
+}
+
+fn number(raw: &str) -> Option<Value> {
+    parse_number(raw.as_bytes()).map(Value::Number)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_overlay_vars() {
+        let mut v = parse(
+            r#"{"db": {"port": 5432, "host": "x", "tls": false},
+                "userName": "root", "hosts": ["a", "b"], "tags": []}"#,
+        )
+        .unwrap();
+        overlay_vars(
+            &mut v,
+            "APP",
+            vars(&[
+                ("APP__DB__PORT", "6543"),
+                ("APP__DB__TLS", "TRUE"),
+                ("APP__USERNAME", "42"),
+                ("APP__HOSTS__1", "c"),
+                ("APP__TAGS", "[1, -2]"),
+                ("APP__CACHE__TTL", "30"),
+                ("APP__CACHE__NAME", "lru"),
+                ("OTHER__DB__PORT", "1"),
+                ("APP", "ignored"),
+            ]),
+        )
+        .unwrap();
+        let exp = parse(
+            r#"{"db": {"port": 6543, "host": "x", "tls": true},
+                "userName": "42", "hosts": ["a", "c"], "tags": [1, -2],
+                "cache": {"ttl": 30, "name": "lru"}}"#,
+        )
+        .unwrap();
+        assert_eq!(v, exp);
+
+        let mut v = parse(r#"{"db": {"port": 5432}, "hosts": []}"#).unwrap();
+        for (name, raw) in &[
+            ("APP__DB__PORT", "x"),
+            ("APP__DB__PORT__X", "1"),
+            ("APP__HOSTS__0", "a"),
+        ] {
+            assert!(overlay_vars(&mut v, "APP", vars(&[(name, raw)])).is_err());
+        }
+    }
+}
@@ -0,0 +1,869 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::time::Instant;
+
+/// A pull parser producing events from any reader without building a `Value`,
+/// so documents larger than memory can be processed.
+use crate::number::parse_number;
+use crate::parser::{LimitCheck, ParseLimits};
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Value,
+    FirstKey,
+    Key,
+    FirstElement,
+    AfterValue,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+// how many bytes `skip_value` scans between reads of the clock.
+const SKIP_DEADLINE_BYTES: usize = 64 * 1024;
+
+pub struct EventReader<R: Read> {
+    reader: BufReader<R>,
+    stack: Vec<Container>,
+    state: State,
+    offset: usize,
+    start: usize,
+    deadline: Option<Instant>,
+    limits: Option<LimitCheck>,
+    events: usize,
+    // events read ahead by `peek` or put back by `rewind`, returned next.
+    pending: VecDeque<Event>,
+    // the events returned since the outermost checkpoint.
+    recorded: Option<Vec<Event>>,
+    // bumped whenever recording starts, so stale checkpoints are detected.
+    generation: usize,
+}
+
+/// A position in the events of an `EventReader` to `rewind` to.
+#[derive(Debug)]
+pub struct Checkpoint {
+    index: usize,
+    generation: usize,
+    // whether recording started with this checkpoint.
+    outermost: bool,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader {
+            reader: BufReader::new(reader),
+            stack: vec![],
+            state: State::Value,
+            offset: 0,
+            start: 0,
+            deadline: None,
+            limits: None,
+            events: 0,
+            pending: VecDeque::new(),
+            recorded: None,
+            generation: 0,
+        }
+    }
+
+    /// The number of objects and arrays currently open.
+    pub fn depth(&self) -> usize {
+        // the parser is past the events still pending.
+        self.pending
+            .iter()
+            .fold(self.stack.len() as isize, |depth, event| match event {
+                Event::StartObject | Event::StartArray => depth - 1,
+                Event::EndObject | Event::EndArray => depth + 1,
+                _ => depth,
+            }) as usize
+    }
+
+    /// The number of bytes consumed from the reader so far, including those
+    /// of events peeked or rewound over.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The offset of the first byte of the last event's token, such as the
+    /// opening quote of a key or string.
+    pub fn event_start(&self) -> usize {
+        self.start
+    }
+
+    /// Fail with "parse timed out." once `deadline` has passed. The clock
+    /// is only read every few events, or every few kilobytes of a value
+    /// skipped with `skip_value`, so the bound is not exact.
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Fail once the document exceeds `limits`, which are not checked unless
+    /// set here. Set them before reading the first event. Values skipped
+    /// with `skip_value` count towards the length of their array, but
+    /// nothing inside them is checked.
+    pub fn set_limits(&mut self, limits: ParseLimits) {
+        self.limits = Some(LimitCheck::new(limits));
+    }
+
+    /// Read the next event, or `None` once the document is complete.
+    /// After an error every further call returns `None`.
+    pub fn next_event(&mut self) -> Result<Option<Event>, &'static str> {
+        let event = match self.pending.pop_front() {
+            Some(event) => event,
+            None => match self.read()? {
+                Some(event) => event,
+                None => return Ok(None),
+            },
+        };
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push(event.clone());
+        }
+        Ok(Some(event))
+    }
+
+    /// The event `next_event` returns next, without consuming it.
+    pub fn peek(&mut self) -> Result<Option<&Event>, &'static str> {
+        if self.pending.is_empty() {
+            if let Some(event) = self.read()? {
+                self.pending.push_back(event);
+            }
+        }
+        Ok(self.pending.front())
+    }
+
+    /// Remember the current position, so the events read after it can be
+    /// read again with `rewind`. Events are kept from the outermost checkpoint
+    /// until it is rewound to or released.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let outermost = self.recorded.is_none();
+        if outermost {
+            self.recorded = Some(vec![]);
+            self.generation += 1;
+        }
+        Checkpoint {
+            index: self.recorded.as_ref().map_or(0, Vec::len),
+            generation: self.generation,
+            outermost,
+        }
+    }
+
+    /// Go back to `checkpoint`, so the events read since are read again.
+    /// Fails if an enclosing checkpoint was rewound to or released.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), &'static str> {
+        let recorded = self.checkpointed(&checkpoint)?;
+        let events = recorded.split_off(checkpoint.index);
+        for event in events.into_iter().rev() {
+            self.pending.push_front(event);
+        }
+        self.release(checkpoint);
+        Ok(())
+    }
+
+    /// Drop `checkpoint` without going back to it. Releasing the outermost
+    /// checkpoint stops keeping events.
+    pub fn release(&mut self, checkpoint: Checkpoint) {
+        if checkpoint.outermost && self.checkpointed(&checkpoint).is_ok() {
+            self.recorded = None;
+        }
+    }
+
+    fn checkpointed(&mut self, checkpoint: &Checkpoint) -> Result<&mut Vec<Event>, &'static str> {
+        match &mut self.recorded {
+            Some(recorded)
+                if checkpoint.generation == self.generation
+                    && checkpoint.index <= recorded.len() =>
+            {
+                Ok(recorded)
+            }
+            _ => Err("checkpoint no longer valid."),
+        }
+    }
+
+    // read the next event from the input.
+    fn read(&mut self) -> Result<Option<Event>, &'static str> {
+        let event = match self.deadline {
+            Some(deadline) if self.events & 63 == 0 && Instant::now() >= deadline => {
+                Err("parse timed out.")
+            }
+            _ => self.advance(),
+        };
+        self.events += 1;
+        if event.is_err() {
+            self.state = State::Failed;
+        }
+        event
+    }
+
+    /// Read the next complete value, or `None` once the document is complete.
+    pub fn next_value(&mut self) -> Result<Option<Value>, &'static str> {
+        match self.next_event()? {
+            Some(event) => self.build_value(event).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Skip the value the next event would start, scanning past it without
+    /// decoding it. Where a member's key comes next, the whole member is
+    /// skipped. Return false, consuming nothing, at the end of a container or
+    /// the document, so `while reader.skip_value()? {}` skips the rest of the
+    /// innermost container.
+    ///
+    /// Inside the skipped value only the nesting of brackets and the ends of
+    /// strings are checked.
+    pub fn skip_value(&mut self) -> Result<bool, &'static str> {
+        if !self.pending.is_empty() || self.recorded.is_some() {
+            return self.skip_events();
+        }
+        let skipped = self.skip();
+        if skipped.is_err() {
+            self.state = State::Failed;
+        }
+        skipped
+    }
+
+    // skip a value event by event, for when the events are buffered or
+    // recorded.
+    fn skip_events(&mut self) -> Result<bool, &'static str> {
+        match self.peek()? {
+            None | Some(Event::EndObject) | Some(Event::EndArray) => return Ok(false),
+            Some(Event::Key(_)) => {
+                self.next_event()?;
+            }
+            Some(_) => {}
+        }
+        let mut depth = 0;
+        loop {
+            match self.require_event()? {
+                Event::StartObject | Event::StartArray => depth += 1,
+                Event::EndObject | Event::EndArray => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn skip(&mut self) -> Result<bool, &'static str> {
+        loop {
+            match self.state {
+                State::Failed | State::Done => return Ok(false),
+                State::AfterValue if self.stack.is_empty() => {
+                    self.state = State::Done;
+                    continue;
+                }
+                _ => {}
+            }
+            let b = self.skip_whitespace()?.ok_or("unexpected end of input.")?;
+            self.start = self.offset;
+            match (self.state, b) {
+                (State::Value, _) => {
+                    if let Some(limits) = &mut self.limits {
+                        limits.value()?;
+                    }
+                    break;
+                }
+                (State::FirstElement, b']') | (State::FirstKey, b'}') => return Ok(false),
+                (State::FirstElement, _) => self.state = State::Value,
+                (State::FirstKey, _) | (State::Key, _) => {
+                    if b != b'"' {
+                        return Err("expected string");
+                    }
+                    self.skip_raw()?;
+                    if self.skip_whitespace()? != Some(b':') {
+                        return Err("colon expected.");
+                    }
+                    self.consume();
+                    self.state = State::Value;
+                }
+                (State::AfterValue, _) => match (b, self.stack.last()) {
+                    (b',', Some(Container::Object)) => {
+                        self.consume();
+                        self.state = State::Key;
+                    }
+                    (b',', Some(Container::Array)) => {
+                        self.consume();
+                        self.state = State::Value;
+                    }
+                    (b'}', Some(Container::Object)) | (b']', Some(Container::Array)) => {
+                        return Ok(false)
+                    }
+                    _ => return Err("comma or closing bracket expected."),
+                },
+                (State::Done, _) | (State::Failed, _) => unreachable!(),
+            }
+        }
+        self.skip_raw()?;
+        self.state = State::AfterValue;
+        Ok(true)
+    }
+
+    // consume the value or key starting at the next byte, a buffer at a time.
+    fn skip_raw(&mut self) -> Result<(), &'static str> {
+        let mut closers = vec![];
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut scalar = false;
+        // bytes scanned since the deadline was last checked.
+        let mut unchecked = 0;
+        loop {
+            if unchecked >= SKIP_DEADLINE_BYTES {
+                unchecked = 0;
+                if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+                    return Err("parse timed out.");
+                }
+            }
+            let buf = self
+                .reader
+                .fill_buf()
+                .map_err(|_| "failed to read input.")?;
+            if buf.is_empty() {
+                if scalar && closers.is_empty() {
+                    return Ok(());
+                }
+                return Err("unexpected end of input.");
+            }
+            let mut used = 0;
+            let mut done = false;
+            for &b in buf {
+                if in_string {
+                    used += 1;
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => {
+                            in_string = false;
+                            done = closers.is_empty();
+                        }
+                        b if b < 0x20 => return Err("control character in string."),
+                        _ => {}
+                    }
+                } else if scalar && (b.is_ascii_alphanumeric() || b"+-.".contains(&b)) {
+                    used += 1;
+                } else if scalar && closers.is_empty() {
+                    // a scalar outside any container ends before this byte.
+                    done = true;
+                } else {
+                    scalar = false;
+                    match b {
+                        b'"' => in_string = true,
+                        b'{' => closers.push(b'}'),
+                        b'[' => closers.push(b']'),
+                        b'}' | b']' => {
+                            if closers.pop() != Some(b) {
+                                return Err("comma or closing bracket expected.");
+                            }
+                            done = closers.is_empty();
+                        }
+                        b',' | b':' if !closers.is_empty() => {}
+                        b if b.is_ascii_whitespace() && !closers.is_empty() => {}
+                        b'-' | b'0'..=b'9' | b'a'..=b'z' => scalar = true,
+                        _ => return Err("unexpected character."),
+                    }
+                    used += 1;
+                }
+                if done {
+                    break;
+                }
+            }
+            self.reader.consume(used);
+            self.offset += used;
+            unchecked += used;
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    // build the value that starts with the already consumed `first` event,
+    // with an explicit stack so deep documents cannot overflow the call stack.
+    pub(crate) fn build_value(&mut self, first: Event) -> Result<Value, &'static str> {
+        let mut stack: Vec<(Value, Option<String>)> = vec![];
+        let mut event = first;
+        loop {
+            let value = match event {
+                Event::StartObject => {
+                    stack.push((Value::Object(HashMap::new()), None));
+                    event = self.require_event()?;
+                    continue;
+                }
+                Event::StartArray => {
+                    stack.push((Value::Array(vec![]), None));
+                    event = self.require_event()?;
+                    continue;
+                }
+                Event::Key(k) => {
+                    stack.last_mut().ok_or("unexpected key.")?.1 = Some(k);
+                    event = self.require_event()?;
+                    continue;
+                }
+                Event::EndObject | Event::EndArray => {
+                    stack.pop().ok_or("unexpected end of container.")?.0
+                }
+                Event::Null => Value::Null,
+                Event::Bool(b) => Value::Bool(b),
+                Event::Number(n) => Value::Number(n),
+                Event::String(s) => Value::String(s),
+            };
+            match stack.last_mut() {
+                None => return Ok(value),
+                Some((Value::Array(v), _)) => v.push(value),
+                Some((Value::Object(m), key)) => {
+                    m.insert(key.take().ok_or("key expected.")?, value);
+                }
+                Some(_) => return Err("unexpected value."),
+            }
+            event = self.require_event()?;
+        }
+    }
+
+    fn require_event(&mut self) -> Result<Event, &'static str> {
+        self.next_event()?.ok_or("unexpected end of input.")
+    }
+
+    fn advance(&mut self) -> Result<Option<Event>, &'static str> {
+        loop {
+            match self.state {
+                State::Failed => return Ok(None),
+                State::Done => {
+                    return match self.skip_whitespace()? {
+                        None => Ok(None),
+                        Some(_) => Err("trailing string after json."),
+                    }
+                }
+                State::AfterValue if self.stack.is_empty() => {
+                    self.state = State::Done;
+                    continue;
+                }
+                _ => {}
+            }
+            let b = self.skip_whitespace()?.ok_or("unexpected end of input.")?;
+            self.start = self.offset;
+            match self.state {
+                State::Value => return self.value(b).map(Some),
+                State::FirstElement if b == b']' => {
+                    self.consume();
+                    return Ok(Some(self.end()));
+                }
+                State::FirstElement => self.state = State::Value,
+                State::FirstKey if b == b'}' => {
+                    self.consume();
+                    return Ok(Some(self.end()));
+                }
+                State::FirstKey => self.state = State::Key,
+                State::Key => {
+                    if b != b'"' {
+                        return Err("expected string");
+                    }
+                    self.consume();
+                    let key = self.string(true)?;
+                    if self.skip_whitespace()? != Some(b':') {
+                        return Err("colon expected.");
+                    }
+                    self.consume();
+                    self.state = State::Value;
+                    return Ok(Some(Event::Key(key)));
+                }
+                State::AfterValue => {
+                    self.consume();
+                    match (b, self.stack.last()) {
+                        (b',', Some(Container::Object)) => self.state = State::Key,
+                        (b',', Some(Container::Array)) => self.state = State::Value,
+                        (b'}', Some(Container::Object)) | (b']', Some(Container::Array)) => {
+                            return Ok(Some(self.end()))
+                        }
+                        _ => return Err("comma or closing bracket expected."),
+                    }
+                }
+                State::Done | State::Failed => unreachable!(),
+            }
+        }
+    }
+
+    // close the innermost container.
+    fn end(&mut self) -> Event {
+        self.state = State::AfterValue;
+        if let Some(limits) = &mut self.limits {
+            limits.close();
+        }
+        match self.stack.pop() {
+            Some(Container::Object) => Event::EndObject,
+            _ => Event::EndArray,
+        }
+    }
+
+    fn value(&mut self, b: u8) -> Result<Event, &'static str> {
+        if let Some(limits) = &mut self.limits {
+            limits.value()?;
+            if b == b'{' || b == b'[' {
+                limits.open(b == b'[')?;
+            }
+        }
+        let event = match b {
+            b'{' => {
+                self.consume();
+                self.stack.push(Container::Object);
+                self.state = State::FirstKey;
+                return Ok(Event::StartObject);
+            }
+            b'[' => {
+                self.consume();
+                self.stack.push(Container::Array);
+                self.state = State::FirstElement;
+                return Ok(Event::StartArray);
+            }
+            b'"' => {
+                self.consume();
+                Event::String(self.string(false)?)
+            }
+            b'-' | b'0'..=b'9' => self.number()?,
+            b't' | b'f' | b'n' => self.keyword()?,
+            _ => return Err("unexpected character."),
+        };
+        self.state = State::AfterValue;
+        Ok(event)
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, &'static str> {
+        let buf = self
+            .reader
+            .fill_buf()
+            .map_err(|_| "failed to read input.")?;
+        Ok(buf.first().copied())
+    }
+
+    fn consume(&mut self) {
+        self.reader.consume(1);
+        self.offset += 1;
+    }
+
+    fn next_byte(&mut self) -> Result<u8, &'static str> {
+        let b = self.peek_byte()?.ok_or("unexpected end of input.")?;
+        self.consume();
+        Ok(b)
+    }
+
+    // skip whitespace and return the next byte without consuming it.
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, &'static str> {
+        loop {
+            match self.peek_byte()? {
+                Some(b) if b.is_ascii_whitespace() => self.consume(),
+                b => return Ok(b),
+            }
+        }
+    }
+
+    // read the rest of a key or string whose opening quote is already
+    // consumed.
+    fn string(&mut self, key: bool) -> Result<String, &'static str> {
+        let mut buf = vec![];
+        let start = self.offset;
+        loop {
+            if let Some(limits) = &self.limits {
+                limits.string(self.offset - start, key)?;
+            }
+            match self.next_byte()? {
+                b'"' => break,
+                b'\\' => match self.next_byte()? {
+                    b @ (b'"' | b'\\' | b'/') => buf.push(b),
+                    b'b' => buf.push(0x08),
+                    b'f' => buf.push(0x0c),
+                    b'n' => buf.push(b'\n'),
+                    b'r' => buf.push(b'\r'),
+                    b't' => buf.push(b'\t'),
+                    b'u' => {
+                        let c = self.unicode_escape()?;
+                        buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    _ => return Err("invalid escape in string."),
+                },
+                b if b < 0x20 => return Err("control character in string."),
+                b => buf.push(b),
+            }
+        }
+        String::from_utf8(buf).map_err(|_| "invalid utf-8 in string.")
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        let mut n = 0;
+        for _ in 0..4 {
+            let digit = (self.next_byte()? as char)
+                .to_digit(16)
+                .ok_or("invalid unicode escape.")?;
+            n = n * 16 + digit;
+        }
+        Ok(n)
+    }
+
+    // decode the `XXXX` after `\u`, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, &'static str> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if self.next_byte()? != b'\\' || self.next_byte()? != b'u' {
+                    return Err("unpaired surrogate in string.");
+                }
+                let low = self.hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err("unpaired surrogate in string.");
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err("unpaired surrogate in string."),
+            _ => high,
+        };
+        char::from_u32(code).ok_or("invalid unicode escape.")
+    }
+
+    fn number(&mut self) -> Result<Event, &'static str> {
+        let mut buf = vec![];
+        while let Some(b) = self.peek_byte()? {
+            if !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') {
+                break;
+            }
+            buf.push(b);
+            self.consume();
+        }
+        parse_number(&buf)
+            .map(Event::Number)
+            .ok_or("cannot parse the string into the numbers.")
+    }
+
+    fn keyword(&mut self) -> Result<Event, &'static str> {
+        let mut buf = vec![];
+        while let Some(b) = self.peek_byte()? {
+            if !b.is_ascii_alphabetic() {
+                break;
+            }
+            buf.push(b);
+            self.consume();
+        }
+        match buf.as_slice() {
+            b"true" => Ok(Event::Bool(true)),
+            b"false" => Ok(Event::Bool(false)),
+            b"null" => Ok(Event::Null),
+            _ => Err("Unsupported keyword or number."),
+        }
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    fn events(s: &str) -> Result<Vec<Event>, &'static str> {
+        EventReader::new(s.as_bytes()).collect()
+    }
+
+    #[test]
+    fn test_events() {
+        assert_eq!(
+            events(r#"{"a": [1, true, null], "b": {}}"#).unwrap(),
+            vec![
+                Event::StartObject,
+                Event::Key("a".to_owned()),
+                Event::StartArray,
+                Event::Number(1.0),
+                Event::Bool(true),
+                Event::Null,
+                Event::EndArray,
+                Event::Key("b".to_owned()),
+                Event::StartObject,
+                Event::EndObject,
+                Event::EndObject,
+            ]
+        );
+        assert_eq!(events("  -1.5e2 ").unwrap(), vec![Event::Number(-150.0)]);
+        assert_eq!(
+            events(r#""a\"\né😀""#).unwrap(),
+            vec![Event::String("a\"\n\u{e9}\u{1F600}".to_owned())]
+        );
+        assert_eq!(
+            events("[]").unwrap(),
+            vec![Event::StartArray, Event::EndArray]
+        );
+    }
+
+    #[test]
+    fn test_invalid_events() {
+        for s in &[
+            "",
+            "[1,]",
+            r#"{"a":1,}"#,
+            r#"{"a" 1}"#,
+            "[1 2]",
+            "01",
+            "1.",
+            "nul",
+            "[1] 2",
+            r#""\ud800""#,
+            "[",
+            r#"{"a":1]"#,
+        ] {
+            assert!(events(s).is_err(), "{}", s);
+        }
+        let mut reader = EventReader::new("[1,]".as_bytes());
+        assert!(reader.by_ref().any(|e| e.is_err()));
+        assert_eq!(reader.next_event(), Ok(None));
+    }
+
+    #[test]
+    fn test_next_value() {
+        let s = r#"{"key": false , "k2": [1, {"k3": null}]}"#;
+        let mut reader = EventReader::new(s.as_bytes());
+        assert_eq!(reader.next_value().unwrap(), Some(parse(s).unwrap()));
+        assert_eq!(reader.next_value().unwrap(), None);
+        assert_eq!(reader.offset(), s.len());
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let s = r#"{"a": [1, {"b": "x]\"}"}], "c": true, "d": -2.5e3, "e": {}}"#;
+        let mut reader = EventReader::new(s.as_bytes());
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartObject)));
+        assert_eq!(reader.skip_value(), Ok(true));
+        assert_eq!(reader.next_event(), Ok(Some(Event::Key("c".to_owned()))));
+        assert_eq!(reader.skip_value(), Ok(true));
+        assert_eq!(reader.next_event(), Ok(Some(Event::Key("d".to_owned()))));
+        assert_eq!(reader.next_event(), Ok(Some(Event::Number(-2500.0))));
+        assert_eq!(reader.skip_value(), Ok(true));
+        assert_eq!(reader.skip_value(), Ok(false));
+        assert_eq!(reader.next_event(), Ok(Some(Event::EndObject)));
+        assert_eq!(reader.skip_value(), Ok(false));
+        assert_eq!(reader.next_event(), Ok(None));
+        assert_eq!(reader.offset(), s.len());
+
+        let mut reader = EventReader::new("[[], 12] ".as_bytes());
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        while reader.skip_value().unwrap() {}
+        assert_eq!(reader.next_event(), Ok(Some(Event::EndArray)));
+        assert_eq!(reader.next_event(), Ok(None));
+        let mut reader = EventReader::new("12".as_bytes());
+        assert_eq!(reader.skip_value(), Ok(true));
+        assert_eq!(reader.next_event(), Ok(None));
+        for bad in &["[{]", "[\"a", "{\"a\" 1}", "[[1}"] {
+            let mut reader = EventReader::new(bad.as_bytes());
+            reader.next_event().unwrap();
+            assert!(reader.skip_value().is_err(), "{}", bad);
+            assert_eq!(reader.next_event(), Ok(None));
+        }
+
+        // a long skipped value still times out.
+        let s = format!("[[\"{}\"], 1]", "x".repeat(4 * SKIP_DEADLINE_BYTES));
+        let mut reader = EventReader::new(s.as_bytes());
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        reader.set_deadline(Instant::now());
+        assert_eq!(reader.skip_value(), Err("parse timed out."));
+        assert_eq!(reader.next_event(), Ok(None));
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ParseLimits {
+            max_depth: 3,
+            max_key_len: 1,
+            max_string_len: 3,
+            max_array_len: 2,
+        };
+        let read = |s: &str| {
+            let mut reader = EventReader::new(s.as_bytes());
+            reader.set_limits(limits);
+            reader.next_value()
+        };
+        let s = r#"{"a": ["abc", {"b": 1}]}"#;
+        assert_eq!(read(s), Ok(Some(parse(s).unwrap())));
+        for (s, e) in &[
+            (r#"{"ab": 1}"#, "key too long."),
+            (r#"["abcd"]"#, "string too long."),
+            ("[1, 2, 3]", "array too long."),
+            ("[[[[]]]]", "json nested too deeply."),
+        ] {
+            assert_eq!(read(s), Err(*e), "{}", s);
+        }
+        let mut reader = EventReader::new("[[1, 2, 3], 4, 5]".as_bytes());
+        reader.set_limits(limits);
+        reader.next_event().unwrap();
+        assert!(reader.skip_value().unwrap());
+        assert!(reader.skip_value().unwrap());
+        assert_eq!(reader.skip_value(), Err("array too long."));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let s = r#"[{"data": [1, 2], "type": "point"}, 3]"#;
+        let mut reader = EventReader::new(s.as_bytes());
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        assert_eq!(reader.peek(), Ok(Some(&Event::StartObject)));
+        assert_eq!(reader.depth(), 1);
+        // look for the type first, then read the whole object.
+        let start = reader.checkpoint();
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartObject)));
+        let inner = reader.checkpoint();
+        let kind = loop {
+            match reader.next_event().unwrap() {
+                Some(Event::Key(k)) if k == "type" => break reader.next_value().unwrap(),
+                Some(Event::Key(_)) => assert_eq!(reader.skip_value(), Ok(true)),
+                e => panic!("{:?}", e),
+            }
+        };
+        assert_eq!(kind, Some(Value::String("point".to_owned())));
+        reader.release(inner);
+        reader.rewind(start).unwrap();
+        assert_eq!(reader.depth(), 1);
+        let object = reader.next_value().unwrap().unwrap();
+        assert_eq!(
+            object,
+            parse(r#"{"type": "point", "data": [1, 2]}"#).unwrap()
+        );
+        assert_eq!(reader.skip_value(), Ok(true));
+        assert_eq!(reader.next_event(), Ok(Some(Event::EndArray)));
+        assert_eq!(reader.next_event(), Ok(None));
+
+        let mut reader = EventReader::new("[1]".as_bytes());
+        let outer = reader.checkpoint();
+        let inner = reader.checkpoint();
+        reader.rewind(outer).unwrap();
+        assert!(reader.rewind(inner).is_err());
+
+        // a checkpoint taken right after the outermost one.
+        let mut reader = EventReader::new("[1]".as_bytes());
+        let outer = reader.checkpoint();
+        let inner = reader.checkpoint();
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        reader.release(inner);
+        reader.rewind(outer).unwrap();
+        let outer = reader.checkpoint();
+        let inner = reader.checkpoint();
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        reader.rewind(inner).unwrap();
+        reader.rewind(outer).unwrap();
+        assert_eq!(reader.next_value(), Ok(Some(parse("[1]").unwrap())));
+    }
+}
@@ -0,0 +1,276 @@
+/// An event parser that performs no heap allocation, for targets without an
+/// allocator to spare: nesting is tracked on a stack the caller provides and
+/// scalars are returned as slices of the input.
+use crate::lexer::unicode_escape;
+use crate::number::parse_number;
+
+/// A container open in a `FixedReader`, as kept on the caller's stack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Container {
+    Object,
+    Array,
+}
+
+/// An event borrowing its text from the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawEvent<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// The key as written between its quotes, escapes not decoded.
+    Key(&'a str),
+    Null,
+    Bool(bool),
+    /// The number as written, checked against the JSON grammar.
+    Number(&'a str),
+    /// The string as written between its quotes, escapes not decoded; see
+    /// `lexer::decode_string`.
+    String(&'a str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Value,
+    // right after `{` or `[`, where the container may close at once.
+    FirstKey,
+    FirstElement,
+    Key,
+    AfterValue,
+    Done,
+}
+
+/// Reads the events of one document from a byte slice, nesting at most as
+/// deep as the stack it is given.
+pub struct FixedReader<'a, 's> {
+    input: &'a [u8],
+    pos: usize,
+    stack: &'s mut [Container],
+    depth: usize,
+    state: State,
+}
+
+impl<'a, 's> FixedReader<'a, 's> {
+    pub fn new(input: &'a [u8], stack: &'s mut [Container]) -> FixedReader<'a, 's> {
+        FixedReader {
+            input,
+            pos: 0,
+            stack,
+            depth: 0,
+            state: State::Value,
+        }
+    }
+
+    /// The offset of the next byte to read.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// The next event, or `None` once the document is complete.
+    pub fn next_event(&mut self) -> Result<Option<RawEvent<'a>>, &'static str> {
+        loop {
+            self.skip_whitespace();
+            let c = match self.input.get(self.pos) {
+                Some(&c) => c,
+                None if self.state == State::Done => return Ok(None),
+                None => return Err("unexpected end of input."),
+            };
+            match (self.state, c) {
+                (State::Done, _) => return Err("trailing string after json."),
+                (State::AfterValue, b',') => {
+                    self.pos += 1;
+                    self.state = match self.stack[self.depth - 1] {
+                        Container::Object => State::Key,
+                        Container::Array => State::Value,
+                    };
+                }
+                (State::AfterValue, b'}') | (State::FirstKey, b'}') => {
+                    return self.close(Container::Object).map(Some)
+                }
+                (State::AfterValue, b']') | (State::FirstElement, b']') => {
+                    return self.close(Container::Array).map(Some)
+                }
+                (State::AfterValue, _) => return Err("expect comma or end of container."),
+                (State::Key, b'"') | (State::FirstKey, b'"') => {
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.input.get(self.pos) != Some(&b':') {
+                        return Err("expect colon.");
+                    }
+                    self.pos += 1;
+                    self.state = State::Value;
+                    return Ok(Some(RawEvent::Key(key)));
+                }
+                (State::Key, _) | (State::FirstKey, _) => return Err("expect key."),
+                (State::Value, _) | (State::FirstElement, _) => return self.value(c).map(Some),
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self, c: u8) -> Result<RawEvent<'a>, &'static str> {
+        let event = match c {
+            b'{' | b'[' => {
+                let (container, event, state) = match c {
+                    b'{' => (Container::Object, RawEvent::StartObject, State::FirstKey),
+                    _ => (Container::Array, RawEvent::StartArray, State::FirstElement),
+                };
+                *self
+                    .stack
+                    .get_mut(self.depth)
+                    .ok_or("json nested too deeply.")? = container;
+                self.depth += 1;
+                self.pos += 1;
+                self.state = state;
+                return Ok(event);
+            }
+            b'"' => RawEvent::String(self.string()?),
+            b'-' | b'0'..=b'9' => {
+                let start = self.pos;
+                while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') =
+                    self.input.get(self.pos)
+                {
+                    self.pos += 1;
+                }
+                let number = &self.input[start..self.pos];
+                if parse_number(number).is_none() {
+                    return Err("invalid number.");
+                }
+                // the number is ASCII, so this cannot fail.
+                RawEvent::Number(std::str::from_utf8(number).unwrap())
+            }
+            _ => {
+                let rest = &self.input[self.pos..];
+                let (event, len) = if rest.starts_with(b"null") {
+                    (RawEvent::Null, 4)
+                } else if rest.starts_with(b"true") {
+                    (RawEvent::Bool(true), 4)
+                } else if rest.starts_with(b"false") {
+                    (RawEvent::Bool(false), 5)
+                } else {
+                    return Err("unexpected character.");
+                };
+                self.pos += len;
+                event
+            }
+        };
+        self.state = self.after_value();
+        Ok(event)
+    }
+
+    fn close(&mut self, container: Container) -> Result<RawEvent<'a>, &'static str> {
+        if self.depth == 0 || self.stack[self.depth - 1] != container {
+            return Err("unexpected end of container.");
+        }
+        self.depth -= 1;
+        self.pos += 1;
+        self.state = self.after_value();
+        Ok(match container {
+            Container::Object => RawEvent::EndObject,
+            Container::Array => RawEvent::EndArray,
+        })
+    }
+
+    fn after_value(&self) -> State {
+        if self.depth == 0 {
+            State::Done
+        } else {
+            State::AfterValue
+        }
+    }
+
+    // read the string whose opening quote is at `pos` and return its content.
+    fn string(&mut self) -> Result<&'a str, &'static str> {
+        let start = self.pos + 1;
+        let mut i = start;
+        loop {
+            match self.input.get(i) {
+                Some(b'"') => break,
+                Some(b'\\') => match self.input.get(i + 1) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => i += 2,
+                    Some(b'u') => {
+                        let mut rest = self.input[i + 2..].iter().copied();
+                        unicode_escape(&mut rest)?;
+                        i = self.input.len() - rest.len();
+                    }
+                    _ => return Err("invalid escape in string."),
+                },
+                Some(&c) if c < 0x20 => return Err("control character in string."),
+                Some(_) => i += 1,
+                None => return Err("unterminated string."),
+            }
+        }
+        self.pos = i + 1;
+        std::str::from_utf8(&self.input[start..i]).map_err(|_| "invalid utf-8 in string.")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn events<'a>(s: &'a str, stack: &mut [Container]) -> Result<Vec<RawEvent<'a>>, &'static str> {
+        let mut reader = FixedReader::new(s.as_bytes(), stack);
+        let mut events = vec![];
+        while let Some(event) = reader.next_event()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn test_fixed_reader() {
+        let mut stack = [Container::Array; 2];
+        assert_eq!(
+            events(
+                r#" {"a\"b": [-1.5e3, "x", true, null], "c": {}} "#,
+                &mut stack
+            ),
+            Ok(vec![
+                RawEvent::StartObject,
+                RawEvent::Key(r#"a\"b"#),
+                RawEvent::StartArray,
+                RawEvent::Number("-1.5e3"),
+                RawEvent::String("x"),
+                RawEvent::Bool(true),
+                RawEvent::Null,
+                RawEvent::EndArray,
+                RawEvent::Key("c"),
+                RawEvent::StartObject,
+                RawEvent::EndObject,
+                RawEvent::EndObject,
+            ])
+        );
+        assert_eq!(
+            events("[[[1]]]", &mut stack),
+            Err("json nested too deeply.")
+        );
+        assert_eq!(events("1", &mut []), Ok(vec![RawEvent::Number("1")]));
+        assert_eq!(
+            events(r#""\u00e9\ud83d\ude00\n""#, &mut []),
+            Ok(vec![RawEvent::String(r#"\u00e9\ud83d\ude00\n"#)])
+        );
+        for s in &[
+            "[1,]",
+            "[1 2]",
+            "{\"a\" 1}",
+            "[}",
+            "01",
+            "[",
+            "1 1",
+            "{1: 2}",
+            "nul",
+            r#""\q""#,
+            r#""\u12""#,
+            r#""\ud800""#,
+            r#""\"#,
+        ] {
+            assert!(events(s, &mut stack).is_err(), "{}", s);
+        }
+    }
+}
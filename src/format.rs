@@ -0,0 +1,208 @@
+/// Reformat JSON documents with comments, changing only the whitespace between
+/// tokens so that comments, key order and the spelling of every value are kept.
+use crate::lexer::{generate_tokens_with_trivia, Token, TokenType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Spaces per level of nesting.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions { indent: 2 }
+    }
+}
+
+// what goes between the last written token and the next one.
+#[derive(Clone, Copy, PartialEq)]
+enum Sep {
+    None,
+    Space,
+    Newline,
+}
+
+struct Formatter {
+    out: String,
+    indent: usize,
+    depth: usize,
+    pending: Sep,
+    // a line comment was written, so the next token must start a new line.
+    must_break: bool,
+    // the source had a blank line before the next token.
+    blank: bool,
+}
+
+impl Formatter {
+    fn write(&mut self, text: &str) {
+        if self.must_break && self.pending != Sep::Newline {
+            self.pending = Sep::Newline;
+        }
+        match self.pending {
+            Sep::None => {}
+            Sep::Space => self.out.push(' '),
+            Sep::Newline if self.out.is_empty() => {}
+            Sep::Newline => {
+                if self.blank {
+                    self.out.push('\n');
+                }
+                self.out.push('\n');
+                self.out
+                    .extend(std::iter::repeat_n(' ', self.depth * self.indent));
+            }
+        }
+        self.out.push_str(text);
+        self.pending = Sep::None;
+        self.must_break = false;
+    }
+}
+
+/// Reindent `src`, which may hold `//` and `/* */` comments, putting every
+/// element of a non-empty array or object on its own line.
+///
+/// Comments on the same line as the token before them stay there, others get
+/// a line of their own; single blank lines between elements are kept. Already
+/// formatted documents come out unchanged.
+pub fn format_document(src: &str, options: &FormatOptions) -> Result<String, &'static str> {
+    let tokens = generate_tokens_with_trivia(src)?;
+    let mut f = Formatter {
+        out: String::with_capacity(src.len()),
+        indent: options.indent,
+        depth: 0,
+        pending: Sep::None,
+        must_break: false,
+        blank: false,
+    };
+    let text = |t: &Token| &src[t.start..t.start + t.s.len()];
+    for (i, token) in tokens.iter().enumerate() {
+        let newlines = newlines_before(&tokens, i);
+        f.blank = newlines > 1 && !matches!(f.out.chars().last(), Some('{') | Some('['));
+        match token._type {
+            TokenType::Whitespace => {}
+            TokenType::Comment => {
+                let line_comment = token.s.starts_with(b"//");
+                if newlines == 0 && i > 0 {
+                    let after = f.pending;
+                    f.pending = Sep::Space;
+                    f.write(text(token));
+                    f.pending = after;
+                } else {
+                    f.pending = Sep::Newline;
+                    f.write(text(token));
+                    f.pending = Sep::Newline;
+                }
+                f.must_break = line_comment;
+            }
+            TokenType::LeftBracket | TokenType::LeftSquareBracket => {
+                f.write(text(token));
+                if !is_close(next_significant(&tokens, i)) {
+                    f.depth += 1;
+                    f.pending = Sep::Newline;
+                }
+            }
+            TokenType::RightBracket | TokenType::RightSquareBracket => {
+                let previous = tokens[..i]
+                    .iter()
+                    .rev()
+                    .find(|t| t._type != TokenType::Whitespace)
+                    .map(|t| t._type);
+                let empty = matches!(
+                    previous,
+                    Some(TokenType::LeftBracket) | Some(TokenType::LeftSquareBracket)
+                );
+                if !empty {
+                    f.depth = f
+                        .depth
+                        .checked_sub(1)
+                        .ok_or("unexpected closing bracket.")?;
+                    f.pending = Sep::Newline;
+                    f.blank = false;
+                }
+                f.write(text(token));
+            }
+            TokenType::Comma => {
+                f.write(",");
+                f.pending = Sep::Newline;
+            }
+            TokenType::Colon => {
+                f.write(":");
+                f.pending = Sep::Space;
+            }
+            _ => f.write(text(token)),
+        }
+    }
+    if f.depth > 0 {
+        return Err("unexpected end of input.");
+    }
+    f.out.push('\n');
+    Ok(f.out)
+}
+
+// the number of line breaks in the whitespace right before token `i`.
+fn newlines_before(tokens: &[Token], i: usize) -> usize {
+    match i.checked_sub(1).map(|p| &tokens[p]) {
+        Some(t) if t._type == TokenType::Whitespace => t.s.iter().filter(|&&c| c == b'\n').count(),
+        _ => 0,
+    }
+}
+
+// the type of the first token after `i` that is not whitespace.
+fn next_significant(tokens: &[Token], i: usize) -> Option<TokenType> {
+    tokens[i + 1..]
+        .iter()
+        .find(|t| t._type != TokenType::Whitespace)
+        .map(|t| t._type)
+}
+
+fn is_close(t: Option<TokenType>) -> bool {
+    matches!(
+        t,
+        Some(TokenType::RightBracket) | Some(TokenType::RightSquareBracket)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_document() {
+        let src = r#"// settings
+{"name":"app", "ports": [ 80,443 ], // defaults
+
+  /* limits */ "limits": {"max": 1.50e3, "empty": {}, "none": [ ]},
+  "z": null}"#;
+        let exp = r#"// settings
+{
+  "name": "app",
+  "ports": [
+    80,
+    443
+  ], // defaults
+
+  /* limits */
+  "limits": {
+    "max": 1.50e3,
+    "empty": {},
+    "none": []
+  },
+  "z": null
+}
+"#;
+        let options = FormatOptions::default();
+        let formatted = format_document(src, &options).unwrap();
+        assert_eq!(formatted, exp);
+        assert_eq!(format_document(exp, &options).unwrap(), exp);
+        assert_eq!(
+            format_document("[1 // one\n, 2]", &FormatOptions { indent: 4 }).unwrap(),
+            "[\n    1 // one\n    ,\n    2\n]\n"
+        );
+        assert_eq!(
+            format_document(r#"{"a": -1, "b": [-0.5e3]}"#, &options).unwrap(),
+            "{\n  \"a\": -1,\n  \"b\": [\n    -0.5e3\n  ]\n}\n"
+        );
+        assert_eq!(format_document("[1abc]", &options), Err("invalid number."));
+        assert!(format_document("[1", &options).is_err());
+        assert!(format_document("1]", &options).is_err());
+    }
+}
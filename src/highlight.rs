@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+/// Map the tokens of a document to semantic classes for syntax highlighting.
+use crate::lexer::{generate_tokens_with_trivia_into, Token, TokenType};
+use crate::serializer::{self, Pretty, SerializerOptions};
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// A string used as an object key.
+    Key,
+    /// A string used as a value.
+    String,
+    Number,
+    /// `true`, `false` or `null`.
+    Literal,
+    /// Brackets, colons and commas.
+    Punctuation,
+    Comment,
+    /// The rest of the input from where lexing failed.
+    Invalid,
+}
+
+/// Classify the byte ranges of `s`, skipping whitespace. Input that cannot be
+/// lexed ends in a single `Invalid` range instead of an error, so documents
+/// being edited can still be highlighted.
+pub fn highlight(s: &str) -> Vec<(Range<usize>, Class)> {
+    let mut tokens = vec![];
+    let failed = generate_tokens_with_trivia_into(s, &mut tokens).is_err();
+    let mut spans = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let class = match token._type {
+            TokenType::Whitespace => continue,
+            TokenType::Comment => Class::Comment,
+            TokenType::String if is_key(&tokens[i + 1..]) => Class::Key,
+            TokenType::String => Class::String,
+            TokenType::Number => Class::Number,
+            TokenType::Null | TokenType::Boolean => Class::Literal,
+            _ => Class::Punctuation,
+        };
+        spans.push((token.start..token.start + token.s.len(), class));
+    }
+    if failed {
+        let end = tokens.last().map_or(0, |t| t.start + t.s.len());
+        spans.push((end..s.len(), Class::Invalid));
+    }
+    spans
+}
+
+// a string is a key when the next significant token is a colon.
+fn is_key(rest: &[Token]) -> bool {
+    rest.iter()
+        .find(|t| !t.is_trivia())
+        .is_some_and(|t| t._type == TokenType::Colon)
+}
+
+/// The CSS colors `to_html` gives each class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub background: String,
+    /// The color of whitespace and anything else without a class.
+    pub foreground: String,
+    pub key: String,
+    pub string: String,
+    pub number: String,
+    pub literal: String,
+    pub punctuation: String,
+    pub comment: String,
+    pub invalid: String,
+}
+
+impl Theme {
+    pub fn light() -> Theme {
+        Theme::from_colors([
+            "#ffffff", "#24292e", "#005cc5", "#032f62", "#e36209", "#d73a49", "#24292e", "#6a737d",
+            "#b31d28",
+        ])
+    }
+
+    pub fn dark() -> Theme {
+        Theme::from_colors([
+            "#1e1e1e", "#d4d4d4", "#9cdcfe", "#ce9178", "#b5cea8", "#569cd6", "#d4d4d4", "#6a9955",
+            "#f44747",
+        ])
+    }
+
+    fn from_colors(colors: [&str; 9]) -> Theme {
+        let [background, foreground, key, string, number, literal, punctuation, comment, invalid] =
+            colors.map(str::to_owned);
+        Theme {
+            background,
+            foreground,
+            key,
+            string,
+            number,
+            literal,
+            punctuation,
+            comment,
+            invalid,
+        }
+    }
+
+    fn color(&self, class: Class) -> &str {
+        match class {
+            Class::Key => &self.key,
+            Class::String => &self.string,
+            Class::Number => &self.number,
+            Class::Literal => &self.literal,
+            Class::Punctuation => &self.punctuation,
+            Class::Comment => &self.comment,
+            Class::Invalid => &self.invalid,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::light()
+    }
+}
+
+/// How `to_html` renders a document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HtmlOptions {
+    pub theme: Theme,
+    /// Make every array and object collapsible by clicking its opening
+    /// bracket, which is all a collapsed one shows.
+    pub collapsible: bool,
+}
+
+/// Render `s` as a highlighted `<pre>` element, styled inline so it can be
+/// embedded in any page. The text is kept as it is, comments and invalid
+/// input included.
+pub fn to_html(s: &str, options: &HtmlOptions) -> String {
+    let theme = &options.theme;
+    let mut out = format!(
+        "<pre style=\"background:{};color:{}\">",
+        theme.background, theme.foreground
+    );
+    let mut open = 0;
+    let mut end = 0;
+    for (range, class) in highlight(s) {
+        escape_html(&mut out, &s[end..range.start]);
+        let text = &s[range.clone()];
+        let opens =
+            options.collapsible && class == Class::Punctuation && (text == "{" || text == "[");
+        if opens {
+            out.push_str("<details open style=\"display:inline\">");
+            out.push_str("<summary style=\"display:inline;cursor:pointer\">");
+            open += 1;
+        }
+        out.push_str(&format!("<span style=\"color:{}\">", theme.color(class)));
+        escape_html(&mut out, text);
+        out.push_str("</span>");
+        if opens {
+            out.push_str("</summary>");
+        }
+        let closes = class == Class::Punctuation && (text == "}" || text == "]");
+        if options.collapsible && closes && open > 0 {
+            out.push_str("</details>");
+            open -= 1;
+        }
+        end = range.end;
+    }
+    escape_html(&mut out, &s[end..]);
+    out.push_str(&"</details>".repeat(open));
+    out.push_str("</pre>");
+    out
+}
+
+impl Value {
+    /// Pretty-print the value and render it with `to_html`.
+    pub fn to_html(&self, options: &HtmlOptions) -> String {
+        let pretty = SerializerOptions {
+            pretty: Some(Pretty::default()),
+            ..SerializerOptions::default()
+        };
+        to_html(&serializer::to_string_with(self, &pretty), options)
+    }
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let s = r#"{"a" /* c */ : ["b", 1, null]}"#;
+        let classes: Vec<(&str, Class)> =
+            highlight(s).into_iter().map(|(r, c)| (&s[r], c)).collect();
+        assert_eq!(
+            classes,
+            vec![
+                ("{", Class::Punctuation),
+                (r#""a""#, Class::Key),
+                ("/* c */", Class::Comment),
+                (":", Class::Punctuation),
+                ("[", Class::Punctuation),
+                (r#""b""#, Class::String),
+                (",", Class::Punctuation),
+                ("1", Class::Number),
+                (",", Class::Punctuation),
+                ("null", Class::Literal),
+                ("]", Class::Punctuation),
+                ("}", Class::Punctuation),
+            ]
+        );
+        assert_eq!(
+            highlight(r#"[1, "open"#),
+            vec![
+                (0..1, Class::Punctuation),
+                (1..2, Class::Number),
+                (2..3, Class::Punctuation),
+                (4..9, Class::Invalid),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_html() {
+        let theme = Theme::dark();
+        let options = HtmlOptions {
+            theme: theme.clone(),
+            collapsible: false,
+        };
+        let html = to_html(r#"{"a<": [1]}"#, &options);
+        assert!(html.starts_with(r#"<pre style="background:#1e1e1e;color:#d4d4d4">"#));
+        assert!(html.contains(r#"<span style="color:#9cdcfe">&quot;a&lt;&quot;</span>"#));
+        assert!(html.contains(r#"<span style="color:#d4d4d4">:</span> <span"#));
+        assert!(!html.contains("details"));
+        let options = HtmlOptions {
+            theme,
+            collapsible: true,
+        };
+        let html = Value::Array(vec![Value::Array(vec![])]).to_html(&options);
+        assert_eq!(html.matches("<details open").count(), 2);
+        assert_eq!(html.matches("</details>").count(), 2);
+        // unbalanced input still yields balanced markup.
+        let html = to_html("[[1", &options);
+        assert_eq!(html.matches("</details>").count(), 2);
+    }
+}
@@ -0,0 +1,110 @@
+use std::pin::Pin;
+
+/// Parse HTTP bodies as they arrive, for any `http_body::Body` such as
+/// `hyper::body::Incoming` or `reqwest::Body`. Enabled by the `http` feature.
+///
+/// Blocking bodies like `reqwest::blocking::Response` implement `Read` and
+/// are parsed with `sink::parse_into`.
+use crate::chunked::ChunkedParser;
+use crate::parser::ParseLimits;
+use crate::sink::{ValueBuilder, ValueSink};
+use crate::value::Value;
+use bytes::Buf;
+use http_body::Body;
+
+/// Parse `body` into a `Value`, feeding each frame to the parser as it comes
+/// rather than buffering the whole body, and failing once the document
+/// exceeds `limits`.
+pub async fn parse_body<B>(body: B, limits: &ParseLimits) -> Result<Value, &'static str>
+where
+    B: Body + Unpin,
+{
+    parse_body_into(body, ValueBuilder::new(), limits).await
+}
+
+/// Parse `body` into `sink` like `parse_body`.
+pub async fn parse_body_into<B, S>(
+    mut body: B,
+    sink: S,
+    limits: &ParseLimits,
+) -> Result<S::Output, &'static str>
+where
+    B: Body + Unpin,
+    S: ValueSink,
+{
+    let mut parser = ChunkedParser::with_sink(sink);
+    parser.set_limits(*limits);
+    while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+        let frame = frame.map_err(|_| "failed to read input.")?;
+        // trailers carry no part of the document.
+        if let Ok(mut data) = frame.into_data() {
+            while data.has_remaining() {
+                let chunk = data.chunk();
+                let len = chunk.len();
+                parser.feed(chunk)?;
+                data.advance(len);
+            }
+        }
+    }
+    parser.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse, MAX_DEPTH};
+    use bytes::Bytes;
+    use http_body::Frame;
+    use std::collections::VecDeque;
+    use std::task::{Context, Poll};
+
+    // a body yielding its chunks one frame at a time.
+    struct Chunks(VecDeque<Result<Bytes, ()>>);
+
+    impl Chunks {
+        fn new(chunks: &[&[u8]]) -> Chunks {
+            Chunks(
+                chunks
+                    .iter()
+                    .map(|c| Ok(Bytes::copy_from_slice(c)))
+                    .collect(),
+            )
+        }
+    }
+
+    impl Body for Chunks {
+        type Data = Bytes;
+        type Error = ();
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            Poll::Ready(self.0.pop_front().map(|chunk| chunk.map(Frame::data)))
+        }
+    }
+
+    #[test]
+    fn test_parse_body() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let body = Chunks::new(&[b"{\"a\": [1, tr", b"ue], \"b\": \"x", b"y\"}"]);
+        let limits = ParseLimits::default();
+        assert_eq!(
+            rt.block_on(parse_body(body, &limits)),
+            Ok(parse(r#"{"a": [1, true], "b": "xy"}"#).unwrap())
+        );
+        let mut body = Chunks::new(&[b"[1, "]);
+        body.0.push_back(Err(()));
+        assert_eq!(
+            rt.block_on(parse_body(body, &limits)),
+            Err("failed to read input.")
+        );
+        let body = Chunks::new(&["[".repeat(MAX_DEPTH + 1).as_bytes()]);
+        assert_eq!(
+            rt.block_on(parse_body(body, &limits)),
+            Err("json nested too deeply.")
+        );
+    }
+}
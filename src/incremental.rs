@@ -0,0 +1,148 @@
+use std::ops::Range;
+
+/// Update the tokens of a document after an edit by lexing only the tokens
+/// the edit can have changed.
+use crate::lexer::{next_trivia_token, Token};
+
+/// The replacement of `range` of the old text by `len` bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub len: usize,
+}
+
+/// The tokens at `old` in the old token list were replaced by the ones at
+/// `new` in the new list; tokens outside those ranges are the same, shifted by
+/// the length change of the edit when they follow it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenChange {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// Update `old`, the tokens from `generate_tokens_with_trivia` on the text
+/// before `edit`, to the tokens of `text`, the text after it.
+///
+/// Lexing restarts at the first token that can see the edit and stops as soon
+/// as it reaches the start of an old token past the edit, from where the old
+/// tokens are reused.
+pub fn relex<'a>(
+    old: &[Token<'_>],
+    text: &'a str,
+    edit: &TextEdit,
+) -> Result<(Vec<Token<'a>>, TokenChange), &'static str> {
+    let bytes = text.as_bytes();
+    let end = |t: &Token| t.start + t.s.len();
+    // a token ending before the edit was lexed without looking at it; one
+    // ending right at it may be extended by the new text.
+    let first = old.partition_point(|t| end(t) < edit.range.start);
+    let mut i = first.checked_sub(1).map_or(0, |p| end(&old[p]));
+    let edit_end = edit.range.start + edit.len;
+    let old_position = |i: usize| i + edit.range.len() - edit.len;
+    let mut fresh = vec![];
+    let mut last = first;
+    while i < bytes.len() {
+        if i >= edit_end {
+            let position = old_position(i);
+            last += old[last..].partition_point(|t| t.start < position);
+            if old.get(last).is_some_and(|t| t.start == position) {
+                break;
+            }
+        }
+        i = next_trivia_token(bytes, i, &mut fresh)?;
+    }
+    if i >= bytes.len() {
+        last = old.len();
+    }
+    // the token touching the edit may have come out unchanged.
+    let mut first = first;
+    if fresh
+        .first()
+        .is_some_and(|t| first < last && same(t, &old[first]))
+    {
+        fresh.remove(0);
+        first += 1;
+    }
+    let reslice = |t: &Token, start: usize| Token {
+        s: &bytes[start..start + t.s.len()],
+        start,
+        _type: t._type,
+    };
+    let change = TokenChange {
+        old: first..last,
+        new: first..first + fresh.len(),
+    };
+    let mut tokens = Vec::with_capacity(first + fresh.len() + old.len() - last);
+    tokens.extend(old[..first].iter().map(|t| reslice(t, t.start)));
+    tokens.append(&mut fresh);
+    tokens.extend(
+        old[last..]
+            .iter()
+            .map(|t| reslice(t, t.start + edit.len - edit.range.len())),
+    );
+    Ok((tokens, change))
+}
+
+fn same(new: &Token, old: &Token) -> bool {
+    new.start == old.start && new._type == old._type && new.s == old.s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::generate_tokens_with_trivia;
+
+    // apply the edit to `before` and check `relex` agrees with lexing anew.
+    fn check(before: &str, range: Range<usize>, insert: &str) -> TokenChange {
+        let after = format!(
+            "{}{}{}",
+            &before[..range.start],
+            insert,
+            &before[range.end..]
+        );
+        let old = generate_tokens_with_trivia(before).unwrap();
+        let edit = TextEdit {
+            range,
+            len: insert.len(),
+        };
+        let (tokens, change) = relex(&old, &after, &edit).unwrap();
+        assert_eq!(tokens, generate_tokens_with_trivia(&after).unwrap());
+        change
+    }
+
+    #[test]
+    fn test_relex() {
+        let s = r#"{"a": [1, 2], "b": true}"#;
+        // `2` becomes `25`: only the number is lexed again.
+        let change = check(s, 11..11, "5");
+        assert_eq!(
+            change,
+            TokenChange {
+                old: 8..9,
+                new: 8..9
+            }
+        );
+        // deleting `, 2` removes three tokens.
+        let change = check(s, 8..11, "");
+        assert_eq!(
+            change,
+            TokenChange {
+                old: 6..9,
+                new: 6..6
+            }
+        );
+        // an unbalanced quote is reported as when lexing the whole text.
+        assert!(relex(
+            &generate_tokens_with_trivia(s).unwrap(),
+            r#"{"a": "[1, 2], "b": true}"#,
+            &TextEdit {
+                range: 6..6,
+                len: 1
+            }
+        )
+        .is_err());
+        check(s, 1..4, r#""x": "y", "c""#);
+        check(s, 0..s.len(), "[]");
+        check("", 0..0, "null");
+    }
+}
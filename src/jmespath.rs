@@ -0,0 +1,1007 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A subset of JMESPath evaluated against the parsed `Value`s.
+///
+/// Supported: identifiers, sub-expressions, index and slice expressions,
+/// list/object projections, flatten, filters, pipes, multi-selects, literals,
+/// `@` and a set of the built-in functions (`length`, `keys`, `sort`, ...).
+/// Expressions nested deeper than `MAX_DEPTH` are rejected.
+use crate::parser::{parse, MAX_DEPTH};
+use crate::query::Segment;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Comparator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Star,
+    Flatten,
+    Filter,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    LeftParen,
+    RightParen,
+    Comma,
+    Colon,
+    Pipe,
+    Or,
+    And,
+    Not,
+    Current,
+    Comparator(Comparator),
+    Number(i64),
+    Identifier(String),
+    QuotedIdentifier(String),
+    Literal(Value),
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    Current,
+    Field(String),
+    Subexpression(Box<Node>, Box<Node>),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Projection(Box<Node>, Box<Node>),
+    ValueProjection(Box<Node>, Box<Node>),
+    FilterProjection(Box<Node>, Box<Node>, Box<Node>),
+    Flatten(Box<Node>),
+    Pipe(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Comparison(Comparator, Box<Node>, Box<Node>),
+    Literal(Value),
+    MultiSelectList(Vec<Node>),
+    MultiSelectHash(Vec<(String, Node)>),
+    Function(String, Vec<Node>),
+}
+
+/// Evaluate the JMESPath expression `expr` against `value`.
+pub fn search(expr: &str, value: &Value) -> Result<Value, &'static str> {
+    evaluate(&compile(expr)?, value)
+}
+
+// parse the expression into the AST that `evaluate` walks.
+pub(crate) fn compile(expr: &str) -> Result<Node, &'static str> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        pos: 0,
+        depth: 0,
+    };
+    let node = parser.expression(0)?;
+    if *parser.peek() != Token::Eof {
+        return Err("unexpected token after expression.");
+    }
+    Ok(node)
+}
+
+// the expression as path segments, if it is made only of fields, non-negative
+// indices and `[*]`/`*` projections.
+pub(crate) fn path(node: &Node) -> Option<Vec<Segment>> {
+    let mut path = vec![];
+    match node {
+        Node::Current => {}
+        Node::Field(name) => path.push(Segment::Key(name.clone())),
+        Node::Index(i) if *i >= 0 => path.push(Segment::Index(*i as usize)),
+        Node::Subexpression(lhs, rhs) => {
+            path.extend(self::path(lhs)?);
+            path.extend(self::path(rhs)?);
+        }
+        // a pipe stops projections, so only a plain left-hand side keeps it a path.
+        Node::Pipe(lhs, rhs) => {
+            let left = self::path(lhs)?;
+            if left
+                .iter()
+                .any(|s| matches!(s, Segment::Elements | Segment::Values))
+            {
+                return None;
+            }
+            path.extend(left);
+            path.extend(self::path(rhs)?);
+        }
+        Node::Projection(lhs, rhs) => {
+            path.extend(self::path(lhs)?);
+            path.push(Segment::Elements);
+            path.extend(self::path(rhs)?);
+        }
+        Node::ValueProjection(lhs, rhs) => {
+            path.extend(self::path(lhs)?);
+            path.push(Segment::Values);
+            path.extend(self::path(rhs)?);
+        }
+        _ => return None,
+    }
+    Some(path)
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, &'static str> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '.' => (Token::Dot, 1),
+            '*' => (Token::Star, 1),
+            ',' => (Token::Comma, 1),
+            ':' => (Token::Colon, 1),
+            '@' => (Token::Current, 1),
+            '{' => (Token::LeftBrace, 1),
+            '}' => (Token::RightBrace, 1),
+            '(' => (Token::LeftParen, 1),
+            ')' => (Token::RightParen, 1),
+            ']' => (Token::RightBracket, 1),
+            '[' => match next {
+                Some(']') => (Token::Flatten, 2),
+                Some('?') => (Token::Filter, 2),
+                _ => (Token::LeftBracket, 1),
+            },
+            '|' => match next {
+                Some('|') => (Token::Or, 2),
+                _ => (Token::Pipe, 1),
+            },
+            '&' => match next {
+                Some('&') => (Token::And, 2),
+                _ => return Err("expression references are not supported."),
+            },
+            '!' => match next {
+                Some('=') => (Token::Comparator(Comparator::Ne), 2),
+                _ => (Token::Not, 1),
+            },
+            '=' => match next {
+                Some('=') => (Token::Comparator(Comparator::Eq), 2),
+                _ => return Err("unexpected '=' in expression."),
+            },
+            '<' => match next {
+                Some('=') => (Token::Comparator(Comparator::Le), 2),
+                _ => (Token::Comparator(Comparator::Lt), 1),
+            },
+            '>' => match next {
+                Some('=') => (Token::Comparator(Comparator::Ge), 2),
+                _ => (Token::Comparator(Comparator::Gt), 1),
+            },
+            c if c == '-' || c.is_ascii_digit() => {
+                let end = scan(&chars, i + 1, |c| c.is_ascii_digit());
+                let s: String = chars[i..end].iter().collect();
+                let n = s
+                    .parse::<i64>()
+                    .map_err(|_| "invalid number in expression.")?;
+                (Token::Number(n), end - i)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let end = scan(&chars, i, |c| c.is_ascii_alphanumeric() || c == '_');
+                (Token::Identifier(chars[i..end].iter().collect()), end - i)
+            }
+            '"' => {
+                let (s, end) = delimited(&chars, i, '"')?;
+                (Token::QuotedIdentifier(unescape(&s)?), end - i)
+            }
+            '\'' => {
+                let (s, end) = delimited(&chars, i, '\'')?;
+                (
+                    Token::Literal(Value::String(s.replace("\\'", "'"))),
+                    end - i,
+                )
+            }
+            '`' => {
+                let (s, end) = delimited(&chars, i, '`')?;
+                let v = parse(s.replace("\\`", "`").trim()).map_err(|_| "invalid literal.")?;
+                (Token::Literal(v), end - i)
+            }
+            _ => return Err("unexpected character in expression."),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    tokens.push(Token::Eof);
+    Ok(tokens)
+}
+
+// return the index of the first character from `start` not matching `f`.
+fn scan(chars: &[char], start: usize, f: impl Fn(char) -> bool) -> usize {
+    let mut end = start;
+    while end < chars.len() && f(chars[end]) {
+        end += 1;
+    }
+    end
+}
+
+// return the raw content between the delimiter at `start` and its unescaped
+// closing delimiter, and the index after the closing delimiter.
+fn delimited(
+    chars: &[char],
+    start: usize,
+    delimiter: char,
+) -> Result<(String, usize), &'static str> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i] == delimiter {
+            return Ok((chars[start + 1..i].iter().collect(), i + 1));
+        }
+        i += 1;
+    }
+    Err("unterminated quote in expression.")
+}
+
+fn unescape(s: &str) -> Result<String, &'static str> {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => out.push(c),
+            _ => return Err("invalid escape in quoted identifier."),
+        }
+    }
+    Ok(out)
+}
+
+fn binding_power(token: &Token) -> u8 {
+    match token {
+        Token::Pipe => 1,
+        Token::Or => 2,
+        Token::And => 3,
+        Token::Comparator(_) => 5,
+        Token::Flatten => 9,
+        Token::Star => 20,
+        Token::Filter => 21,
+        Token::Dot => 40,
+        Token::Not => 45,
+        Token::LeftBrace => 50,
+        Token::LeftBracket => 55,
+        Token::LeftParen => 60,
+        _ => 0,
+    }
+}
+
+// top-down operator precedence parser following the reference implementation.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // the depth of the tree being built, see `nested_expression`.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn peek_next(&self) -> &Token {
+        self.tokens.get(self.pos + 1).unwrap_or(&Token::Eof)
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, token: Token, err: &'static str) -> Result<(), &'static str> {
+        if self.advance() != token {
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, rbp: u8) -> Result<Node, &'static str> {
+        let depth = self.depth;
+        let node = self.nested_expression(rbp);
+        self.depth = depth;
+        node
+    }
+
+    // an expression `depth` levels down, each operand and operator counting
+    // as a level so that neither nesting nor long chains can exhaust the
+    // stack when the tree is evaluated.
+    fn nested_expression(&mut self, rbp: u8) -> Result<Node, &'static str> {
+        self.enter()?;
+        let token = self.advance();
+        let mut left = self.nud(token)?;
+        while rbp < binding_power(self.peek()) {
+            self.enter()?;
+            let token = self.advance();
+            left = self.led(token, left)?;
+        }
+        Ok(left)
+    }
+
+    fn enter(&mut self) -> Result<(), &'static str> {
+        if self.depth == MAX_DEPTH {
+            return Err("expression nested too deeply.");
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn nud(&mut self, token: Token) -> Result<Node, &'static str> {
+        match token {
+            Token::Literal(v) => Ok(Node::Literal(v)),
+            Token::Identifier(name) => Ok(Node::Field(name)),
+            Token::QuotedIdentifier(name) => {
+                if *self.peek() == Token::LeftParen {
+                    return Err("quoted identifier cannot be a function name.");
+                }
+                Ok(Node::Field(name))
+            }
+            Token::Star => {
+                let rhs = self.projection_rhs(binding_power(&Token::Star))?;
+                Ok(Node::ValueProjection(
+                    Box::new(Node::Current),
+                    Box::new(rhs),
+                ))
+            }
+            Token::Filter => self.filter(Node::Current),
+            Token::LeftBrace => self.multi_select_hash(),
+            Token::Flatten => {
+                let rhs = self.projection_rhs(binding_power(&Token::Flatten))?;
+                Ok(Node::Projection(
+                    Box::new(Node::Flatten(Box::new(Node::Current))),
+                    Box::new(rhs),
+                ))
+            }
+            Token::LeftBracket => match self.peek() {
+                Token::Number(_) | Token::Colon => {
+                    let index = self.index_expression()?;
+                    self.project_if_slice(Node::Current, index)
+                }
+                Token::Star if *self.peek_next() == Token::RightBracket => {
+                    self.advance();
+                    self.advance();
+                    let rhs = self.projection_rhs(binding_power(&Token::Star))?;
+                    Ok(Node::Projection(Box::new(Node::Current), Box::new(rhs)))
+                }
+                _ => self.multi_select_list(),
+            },
+            Token::Current => Ok(Node::Current),
+            Token::Not => Ok(Node::Not(Box::new(
+                self.expression(binding_power(&Token::Not))?,
+            ))),
+            Token::LeftParen => {
+                let node = self.expression(0)?;
+                self.expect(Token::RightParen, "right parenthesis expected.")?;
+                Ok(node)
+            }
+            _ => Err("unexpected token in expression."),
+        }
+    }
+
+    fn led(&mut self, token: Token, left: Node) -> Result<Node, &'static str> {
+        match token {
+            Token::Dot => {
+                if *self.peek() == Token::Star {
+                    self.advance();
+                    let rhs = self.projection_rhs(binding_power(&Token::Dot))?;
+                    return Ok(Node::ValueProjection(Box::new(left), Box::new(rhs)));
+                }
+                let rhs = self.dot_rhs(binding_power(&Token::Dot))?;
+                Ok(Node::Subexpression(Box::new(left), Box::new(rhs)))
+            }
+            Token::Pipe => {
+                let rhs = self.expression(binding_power(&Token::Pipe))?;
+                Ok(Node::Pipe(Box::new(left), Box::new(rhs)))
+            }
+            Token::Or => {
+                let rhs = self.expression(binding_power(&Token::Or))?;
+                Ok(Node::Or(Box::new(left), Box::new(rhs)))
+            }
+            Token::And => {
+                let rhs = self.expression(binding_power(&Token::And))?;
+                Ok(Node::And(Box::new(left), Box::new(rhs)))
+            }
+            Token::Comparator(op) => {
+                let rhs = self.expression(binding_power(&Token::Comparator(op)))?;
+                Ok(Node::Comparison(op, Box::new(left), Box::new(rhs)))
+            }
+            Token::LeftParen => {
+                let name = match left {
+                    Node::Field(name) => name,
+                    _ => return Err("function name expected."),
+                };
+                let mut args = vec![];
+                if *self.peek() == Token::RightParen {
+                    self.advance();
+                    return Ok(Node::Function(name, args));
+                }
+                loop {
+                    args.push(self.expression(0)?);
+                    match self.advance() {
+                        Token::Comma => continue,
+                        Token::RightParen => break,
+                        _ => return Err("comma or right parenthesis expected."),
+                    }
+                }
+                Ok(Node::Function(name, args))
+            }
+            Token::Filter => self.filter(left),
+            Token::Flatten => {
+                let rhs = self.projection_rhs(binding_power(&Token::Flatten))?;
+                Ok(Node::Projection(
+                    Box::new(Node::Flatten(Box::new(left))),
+                    Box::new(rhs),
+                ))
+            }
+            Token::LeftBracket => match self.peek() {
+                Token::Number(_) | Token::Colon => {
+                    let index = self.index_expression()?;
+                    self.project_if_slice(left, index)
+                }
+                _ => {
+                    self.expect(Token::Star, "star expected.")?;
+                    self.expect(Token::RightBracket, "right bracket expected.")?;
+                    let rhs = self.projection_rhs(binding_power(&Token::Star))?;
+                    Ok(Node::Projection(Box::new(left), Box::new(rhs)))
+                }
+            },
+            _ => Err("unexpected token in expression."),
+        }
+    }
+
+    fn filter(&mut self, left: Node) -> Result<Node, &'static str> {
+        let condition = self.expression(0)?;
+        self.expect(Token::RightBracket, "right bracket expected.")?;
+        let rhs = self.projection_rhs(binding_power(&Token::Filter))?;
+        Ok(Node::FilterProjection(
+            Box::new(left),
+            Box::new(condition),
+            Box::new(rhs),
+        ))
+    }
+
+    // the left bracket is already consumed.
+    fn index_expression(&mut self) -> Result<Node, &'static str> {
+        if *self.peek() != Token::Colon && *self.peek_next() != Token::Colon {
+            let index = match self.advance() {
+                Token::Number(n) => n,
+                _ => return Err("index expected."),
+            };
+            self.expect(Token::RightBracket, "right bracket expected.")?;
+            return Ok(Node::Index(index));
+        }
+        let mut parts = [None, None, None];
+        let mut current = 0;
+        loop {
+            match self.advance() {
+                Token::RightBracket => break,
+                Token::Colon if current < 2 => current += 1,
+                // a number only right after `[` or a colon.
+                Token::Number(n) if parts[current].is_none() => parts[current] = Some(n),
+                _ => return Err("invalid slice expression."),
+            }
+        }
+        let step = parts[2].unwrap_or(1);
+        if step == 0 {
+            return Err("slice step cannot be 0.");
+        }
+        Ok(Node::Slice(parts[0], parts[1], step))
+    }
+
+    fn project_if_slice(&mut self, left: Node, index: Node) -> Result<Node, &'static str> {
+        let is_slice = matches!(index, Node::Slice(..));
+        let node = Node::Subexpression(Box::new(left), Box::new(index));
+        if !is_slice {
+            return Ok(node);
+        }
+        let rhs = self.projection_rhs(binding_power(&Token::Star))?;
+        Ok(Node::Projection(Box::new(node), Box::new(rhs)))
+    }
+
+    fn projection_rhs(&mut self, rbp: u8) -> Result<Node, &'static str> {
+        match self.peek() {
+            t if binding_power(t) < 10 => Ok(Node::Current),
+            Token::LeftBracket | Token::Filter => self.expression(rbp),
+            Token::Dot => {
+                self.advance();
+                self.dot_rhs(rbp)
+            }
+            _ => Err("unexpected token after projection."),
+        }
+    }
+
+    fn dot_rhs(&mut self, rbp: u8) -> Result<Node, &'static str> {
+        match self.peek() {
+            Token::Identifier(_) | Token::QuotedIdentifier(_) | Token::Star => self.expression(rbp),
+            Token::LeftBracket => {
+                self.advance();
+                self.multi_select_list()
+            }
+            Token::LeftBrace => {
+                self.advance();
+                self.multi_select_hash()
+            }
+            _ => Err("identifier expected after dot."),
+        }
+    }
+
+    // the left bracket is already consumed.
+    fn multi_select_list(&mut self) -> Result<Node, &'static str> {
+        let mut nodes = vec![];
+        loop {
+            nodes.push(self.expression(0)?);
+            match self.advance() {
+                Token::Comma => continue,
+                Token::RightBracket => break,
+                _ => return Err("comma or right bracket expected."),
+            }
+        }
+        Ok(Node::MultiSelectList(nodes))
+    }
+
+    // the left brace is already consumed.
+    fn multi_select_hash(&mut self) -> Result<Node, &'static str> {
+        let mut pairs = vec![];
+        loop {
+            let key = match self.advance() {
+                Token::Identifier(key) | Token::QuotedIdentifier(key) => key,
+                _ => return Err("key expected in multi-select hash."),
+            };
+            self.expect(Token::Colon, "colon expected.")?;
+            pairs.push((key, self.expression(0)?));
+            match self.advance() {
+                Token::Comma => continue,
+                Token::RightBrace => break,
+                _ => return Err("comma or right brace expected."),
+            }
+        }
+        Ok(Node::MultiSelectHash(pairs))
+    }
+}
+
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(v) => !v.is_empty(),
+        Value::Object(m) => !m.is_empty(),
+        _ => true,
+    }
+}
+
+pub(crate) fn evaluate(node: &Node, value: &Value) -> Result<Value, &'static str> {
+    match node {
+        Node::Current => Ok(value.clone()),
+        Node::Field(name) => match value {
+            Value::Object(m) => Ok(m.get(name).cloned().unwrap_or(Value::Null)),
+            _ => Ok(Value::Null),
+        },
+        Node::Subexpression(lhs, rhs) | Node::Pipe(lhs, rhs) => {
+            evaluate(rhs, &evaluate(lhs, value)?)
+        }
+        Node::Index(i) => match value {
+            Value::Array(v) => {
+                let i = if *i < 0 { v.len() as i64 + i } else { *i };
+                if i < 0 {
+                    return Ok(Value::Null);
+                }
+                Ok(v.get(i as usize).cloned().unwrap_or(Value::Null))
+            }
+            _ => Ok(Value::Null),
+        },
+        Node::Slice(start, stop, step) => match value {
+            Value::Array(v) => Ok(Value::Array(slice(v, *start, *stop, *step))),
+            _ => Ok(Value::Null),
+        },
+        Node::Projection(lhs, rhs) => match evaluate(lhs, value)? {
+            Value::Array(v) => project(v.iter(), rhs),
+            _ => Ok(Value::Null),
+        },
+        Node::ValueProjection(lhs, rhs) => match evaluate(lhs, value)? {
+            Value::Object(m) => project(m.values(), rhs),
+            _ => Ok(Value::Null),
+        },
+        Node::FilterProjection(lhs, condition, rhs) => match evaluate(lhs, value)? {
+            Value::Array(v) => {
+                let mut matched = vec![];
+                for item in v {
+                    if is_truthy(&evaluate(condition, &item)?) {
+                        matched.push(item);
+                    }
+                }
+                project(matched.iter(), rhs)
+            }
+            _ => Ok(Value::Null),
+        },
+        Node::Flatten(node) => match evaluate(node, value)? {
+            Value::Array(v) => {
+                let mut flattened = vec![];
+                for item in v {
+                    match item {
+                        Value::Array(inner) => flattened.extend(inner),
+                        other => flattened.push(other),
+                    }
+                }
+                Ok(Value::Array(flattened))
+            }
+            _ => Ok(Value::Null),
+        },
+        Node::Or(lhs, rhs) => {
+            let left = evaluate(lhs, value)?;
+            if is_truthy(&left) {
+                return Ok(left);
+            }
+            evaluate(rhs, value)
+        }
+        Node::And(lhs, rhs) => {
+            let left = evaluate(lhs, value)?;
+            if !is_truthy(&left) {
+                return Ok(left);
+            }
+            evaluate(rhs, value)
+        }
+        Node::Not(node) => Ok(Value::Bool(!is_truthy(&evaluate(node, value)?))),
+        Node::Comparison(op, lhs, rhs) => {
+            let left = evaluate(lhs, value)?;
+            let right = evaluate(rhs, value)?;
+            Ok(compare(*op, &left, &right))
+        }
+        Node::Literal(v) => Ok(v.clone()),
+        Node::MultiSelectList(nodes) => {
+            if *value == Value::Null {
+                return Ok(Value::Null);
+            }
+            let mut v = vec![];
+            for node in nodes {
+                v.push(evaluate(node, value)?);
+            }
+            Ok(Value::Array(v))
+        }
+        Node::MultiSelectHash(pairs) => {
+            if *value == Value::Null {
+                return Ok(Value::Null);
+            }
+            let mut m = HashMap::new();
+            for (key, node) in pairs {
+                m.insert(key.clone(), evaluate(node, value)?);
+            }
+            Ok(Value::Object(m))
+        }
+        Node::Function(name, args) => {
+            let mut evaluated = vec![];
+            for arg in args {
+                evaluated.push(evaluate(arg, value)?);
+            }
+            call_function(name, evaluated)
+        }
+    }
+}
+
+// evaluate `rhs` against every item, dropping the null results.
+fn project<'a>(items: impl Iterator<Item = &'a Value>, rhs: &Node) -> Result<Value, &'static str> {
+    let mut v = vec![];
+    for item in items {
+        let r = evaluate(rhs, item)?;
+        if r != Value::Null {
+            v.push(r);
+        }
+    }
+    Ok(Value::Array(v))
+}
+
+fn slice(items: &[Value], start: Option<i64>, stop: Option<i64>, step: i64) -> Vec<Value> {
+    let len = items.len() as i64;
+    let adjust = |endpoint: i64| {
+        if endpoint < 0 {
+            let endpoint = endpoint + len;
+            if endpoint >= 0 {
+                endpoint
+            } else if step < 0 {
+                -1
+            } else {
+                0
+            }
+        } else if endpoint >= len {
+            if step < 0 {
+                len - 1
+            } else {
+                len
+            }
+        } else {
+            endpoint
+        }
+    };
+    let start = match start {
+        Some(s) => adjust(s),
+        None if step < 0 => len - 1,
+        None => 0,
+    };
+    let stop = match stop {
+        Some(s) => adjust(s),
+        None if step < 0 => -1,
+        None => len,
+    };
+    let mut v = vec![];
+    let mut i = start;
+    while (step > 0 && i < stop) || (step < 0 && i > stop) {
+        v.push(items[i as usize].clone());
+        i += step;
+    }
+    v
+}
+
+fn compare(op: Comparator, left: &Value, right: &Value) -> Value {
+    match op {
+        Comparator::Eq => Value::Bool(left == right),
+        Comparator::Ne => Value::Bool(left != right),
+        _ => match (left, right) {
+            (Value::Number(l), Value::Number(r)) => Value::Bool(match op {
+                Comparator::Lt => l < r,
+                Comparator::Le => l <= r,
+                Comparator::Gt => l > r,
+                _ => l >= r,
+            }),
+            _ => Value::Null,
+        },
+    }
+}
+
+fn numbers(value: &Value) -> Result<Vec<f64>, &'static str> {
+    match value {
+        Value::Array(v) => v
+            .iter()
+            .map(|item| match item {
+                Value::Number(n) => Ok(*n),
+                _ => Err("invalid type for function."),
+            })
+            .collect(),
+        _ => Err("invalid type for function."),
+    }
+}
+
+// sort an array made entirely of numbers or entirely of strings.
+fn sorted(value: &Value) -> Result<Vec<Value>, &'static str> {
+    let mut v = match value {
+        Value::Array(v) => v.clone(),
+        _ => return Err("invalid type for function."),
+    };
+    if v.iter().all(|item| matches!(item, Value::Number(_))) {
+        v.sort_by(|a, b| match (a, b) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            _ => unreachable!(),
+        });
+    } else if v.iter().all(|item| matches!(item, Value::String(_))) {
+        v.sort_by(|a, b| match (a, b) {
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            _ => unreachable!(),
+        });
+    } else {
+        return Err("invalid type for function.");
+    }
+    Ok(v)
+}
+
+fn call_function(name: &str, args: Vec<Value>) -> Result<Value, &'static str> {
+    let arity = match name {
+        "not_null" => args.len().max(1),
+        "contains" | "starts_with" | "ends_with" | "join" => 2,
+        _ => 1,
+    };
+    if args.len() != arity {
+        return Err("invalid number of arguments for function.");
+    }
+    let invalid = Err("invalid type for function.");
+    match (name, &args[0]) {
+        ("length", Value::String(s)) => Ok(Value::Number(s.chars().count() as f64)),
+        ("length", Value::Array(v)) => Ok(Value::Number(v.len() as f64)),
+        ("length", Value::Object(m)) => Ok(Value::Number(m.len() as f64)),
+        ("keys", Value::Object(m)) => Ok(Value::Array(
+            m.keys().map(|k| Value::String(k.clone())).collect(),
+        )),
+        ("values", Value::Object(m)) => Ok(Value::Array(m.values().cloned().collect())),
+        ("type", v) => Ok(Value::String(v.kind().name().to_owned())),
+        ("contains", Value::Array(v)) => Ok(Value::Bool(v.contains(&args[1]))),
+        ("contains", Value::String(s)) => match &args[1] {
+            Value::String(search) => Ok(Value::Bool(s.contains(search.as_str()))),
+            _ => invalid,
+        },
+        ("starts_with", Value::String(s)) | ("ends_with", Value::String(s)) => match &args[1] {
+            Value::String(affix) if name == "starts_with" => {
+                Ok(Value::Bool(s.starts_with(affix.as_str())))
+            }
+            Value::String(affix) => Ok(Value::Bool(s.ends_with(affix.as_str()))),
+            _ => invalid,
+        },
+        ("sum", v) => Ok(Value::Number(numbers(v)?.iter().sum())),
+        ("avg", v) => {
+            let n = numbers(v)?;
+            if n.is_empty() {
+                return Ok(Value::Null);
+            }
+            Ok(Value::Number(n.iter().sum::<f64>() / n.len() as f64))
+        }
+        ("min", v) => Ok(sorted(v)?.into_iter().next().unwrap_or(Value::Null)),
+        ("max", v) => Ok(sorted(v)?.pop().unwrap_or(Value::Null)),
+        ("sort", v) => Ok(Value::Array(sorted(v)?)),
+        ("not_null", _) => Ok(args
+            .into_iter()
+            .find(|v| *v != Value::Null)
+            .unwrap_or(Value::Null)),
+        ("abs", Value::Number(n)) => Ok(Value::Number(n.abs())),
+        ("ceil", Value::Number(n)) => Ok(Value::Number(n.ceil())),
+        ("floor", Value::Number(n)) => Ok(Value::Number(n.floor())),
+        ("reverse", Value::String(s)) => Ok(Value::String(s.chars().rev().collect())),
+        ("reverse", Value::Array(v)) => Ok(Value::Array(v.iter().rev().cloned().collect())),
+        ("join", Value::String(glue)) => match &args[1] {
+            Value::Array(v) => {
+                let mut parts = vec![];
+                for item in v {
+                    match item {
+                        Value::String(s) => parts.push(s.as_str()),
+                        _ => return invalid,
+                    }
+                }
+                Ok(Value::String(parts.join(glue)))
+            }
+            _ => invalid,
+        },
+        ("to_number", Value::Number(n)) => Ok(Value::Number(*n)),
+        ("to_number", Value::String(s)) => {
+            Ok(s.parse::<f64>().map(Value::Number).unwrap_or(Value::Null))
+        }
+        ("to_number", _) => Ok(Value::Null),
+        ("to_array", Value::Array(v)) => Ok(Value::Array(v.clone())),
+        ("to_array", v) => Ok(Value::Array(vec![v.clone()])),
+        (
+            "length" | "keys" | "values" | "contains" | "starts_with" | "ends_with" | "abs"
+            | "ceil" | "floor" | "reverse" | "join",
+            _,
+        ) => invalid,
+        _ => Err("unknown function."),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_owned())
+    }
+
+    #[test]
+    fn test_search() {
+        let doc = parse(
+            r#"{"people": [
+                {"name": "a", "age": 20, "active": true, "tags": ["x", "y"]},
+                {"name": "b", "age": 35, "active": false, "tags": ["z"]},
+                {"name": "c", "age": 50, "active": true, "tags": []}
+            ], "meta": {"count": 3}}"#,
+        )
+        .unwrap();
+        assert_eq!(search("meta.count", &doc).unwrap(), Value::Number(3.0));
+        assert_eq!(search("meta.missing", &doc).unwrap(), Value::Null);
+        assert_eq!(search("people[0].name", &doc).unwrap(), s("a"));
+        assert_eq!(search("people[-1].name", &doc).unwrap(), s("c"));
+        assert_eq!(
+            search("people[*].name", &doc).unwrap(),
+            Value::Array(vec![s("a"), s("b"), s("c")])
+        );
+        assert_eq!(
+            search("people[1:].name", &doc).unwrap(),
+            Value::Array(vec![s("b"), s("c")])
+        );
+        assert_eq!(
+            search("people[::-2].name", &doc).unwrap(),
+            Value::Array(vec![s("c"), s("a")])
+        );
+        assert_eq!(
+            search("people[].tags[]", &doc).unwrap(),
+            Value::Array(vec![s("x"), s("y"), s("z")])
+        );
+        assert_eq!(
+            search("people[?active].name", &doc).unwrap(),
+            Value::Array(vec![s("a"), s("c")])
+        );
+        assert_eq!(
+            search("people[?age > `30` && active].name", &doc).unwrap(),
+            Value::Array(vec![s("c")])
+        );
+        assert_eq!(
+            search("people[?name == 'b'].age | [0]", &doc).unwrap(),
+            Value::Number(35.0)
+        );
+        assert_eq!(search("people[*].name | [0]", &doc).unwrap(), s("a"));
+        assert_eq!(
+            search("people[0].[name, age]", &doc).unwrap(),
+            Value::Array(vec![s("a"), Value::Number(20.0)])
+        );
+        let mut m = HashMap::new();
+        m.insert("n".to_owned(), s("a"));
+        assert_eq!(
+            search("people[0].{n: name}", &doc).unwrap(),
+            Value::Object(m)
+        );
+        assert_eq!(
+            search("meta.*", &doc).unwrap(),
+            Value::Array(vec![Value::Number(3.0)])
+        );
+        assert_eq!(search("!meta", &doc).unwrap(), Value::Bool(false));
+        assert_eq!(search("meta.missing || 'x'", &doc).unwrap(), s("x"));
+    }
+
+    #[test]
+    fn test_functions() {
+        let doc = parse(r#"{"a": [3, 1, 2], "s": "hello", "o": {"k": null}}"#).unwrap();
+        assert_eq!(search("length(a)", &doc).unwrap(), Value::Number(3.0));
+        assert_eq!(search("length(s)", &doc).unwrap(), Value::Number(5.0));
+        assert_eq!(search("length(@)", &doc).unwrap(), Value::Number(3.0));
+        assert_eq!(
+            search("sort(a)", &doc).unwrap(),
+            Value::Array(vec![
+                Value::Number(1.0),
+                Value::Number(2.0),
+                Value::Number(3.0)
+            ])
+        );
+        assert_eq!(search("max(a)", &doc).unwrap(), Value::Number(3.0));
+        assert_eq!(search("sum(a)", &doc).unwrap(), Value::Number(6.0));
+        assert_eq!(search("avg(a)", &doc).unwrap(), Value::Number(2.0));
+        assert_eq!(search("keys(o)", &doc).unwrap(), Value::Array(vec![s("k")]));
+        assert_eq!(search("type(o.k)", &doc).unwrap(), s("null"));
+        assert_eq!(search("contains(a, `2`)", &doc).unwrap(), Value::Bool(true));
+        assert_eq!(
+            search("starts_with(s, 'he')", &doc).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(search("not_null(o.k, s)", &doc).unwrap(), s("hello"));
+        assert_eq!(
+            search("length(`[1, 2]`)", &doc).unwrap(),
+            Value::Number(2.0)
+        );
+        assert!(search("length(o.k)", &doc).is_err());
+        assert!(search("nope(a)", &doc).is_err());
+        // the average of infinities is NaN.
+        let doc = parse(r#"{"a": [1e400, -1e400], "b": [1, 2]}"#).unwrap();
+        assert!(search("sort([avg(a), avg(b)])", &doc).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        let doc = Value::Null;
+        assert!(search("a.", &doc).is_err());
+        assert!(search("a[", &doc).is_err());
+        assert!(search("a b", &doc).is_err());
+        assert!(search("[0:1:0]", &doc).is_err());
+        assert!(search("[:1 2]", &doc).is_err());
+        assert!(search("[1 2:]", &doc).is_err());
+        assert!(search("'unterminated", &doc).is_err());
+        let nested = |n: usize| "(".repeat(n) + "@" + &")".repeat(n);
+        assert_eq!(search(&nested(MAX_DEPTH - 1), &doc), Ok(Value::Null));
+        assert_eq!(
+            search(&nested(100_000), &doc),
+            Err("expression nested too deeply.")
+        );
+        for expr in &["a.".repeat(100_000) + "a", "!".repeat(100_000) + "a"] {
+            assert_eq!(search(expr, &doc), Err("expression nested too deeply."));
+        }
+    }
+}
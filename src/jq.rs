@@ -0,0 +1,1086 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::parser::MAX_DEPTH;
+use crate::query::Segment;
+/// A jq-like filter language over the parsed `Value`s.
+///
+/// A filter maps one input value to a stream of output values. Supported:
+/// `.`, `..`, `.foo`, `."foo"`, `.[f]`, `.[n:m]`, `.[]`, `?`, `|`, `,`, `//`,
+/// `and`/`or`, comparisons, arithmetic, array and object construction,
+/// literals and builtins such as `select`, `map`, `length`, `keys` and `has`.
+/// Filters nested deeper than `MAX_DEPTH` are rejected.
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Recurse,
+    Field(String),
+    Identifier(String),
+    Str(String),
+    Number(f64),
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    LeftParen,
+    RightParen,
+    Pipe,
+    Comma,
+    Colon,
+    Semicolon,
+    Question,
+    Alternative,
+    Op(Op),
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Ast {
+    Identity,
+    Recurse,
+    Field(Box<Ast>, String),
+    Index(Box<Ast>, Box<Ast>),
+    Slice(Box<Ast>, Option<Box<Ast>>, Option<Box<Ast>>),
+    Iterate(Box<Ast>),
+    Try(Box<Ast>),
+    Literal(Value),
+    Array(Option<Box<Ast>>),
+    Object(Vec<(Ast, Ast)>),
+    Pipe(Box<Ast>, Box<Ast>),
+    Comma(Box<Ast>, Box<Ast>),
+    Alternative(Box<Ast>, Box<Ast>),
+    Or(Box<Ast>, Box<Ast>),
+    And(Box<Ast>, Box<Ast>),
+    Binary(Op, Box<Ast>, Box<Ast>),
+    Negate(Box<Ast>),
+    Call(String, Vec<Ast>),
+}
+
+// builtins and the number of arguments they take.
+const BUILTINS: [(&str, usize); 24] = [
+    ("empty", 0),
+    ("not", 0),
+    ("length", 0),
+    ("keys", 0),
+    ("values", 0),
+    ("has", 1),
+    ("select", 1),
+    ("map", 1),
+    ("type", 0),
+    ("add", 0),
+    ("first", 0),
+    ("last", 0),
+    ("first", 1),
+    ("reverse", 0),
+    ("sort", 0),
+    ("unique", 0),
+    ("min", 0),
+    ("max", 0),
+    ("any", 0),
+    ("all", 0),
+    ("tonumber", 0),
+    ("ascii_downcase", 0),
+    ("ascii_upcase", 0),
+    ("to_entries", 0),
+];
+
+/// A compiled filter, reusable across any number of inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(Ast);
+
+impl Filter {
+    /// Run the filter against `input` and collect every value it outputs.
+    pub fn apply(&self, input: &Value) -> Result<Vec<Value>, &'static str> {
+        evaluate(&self.0, input)
+    }
+
+    // the filter as path segments, if it is made only of `.foo`, `.[n]` and `.[]` steps.
+    pub(crate) fn path(&self) -> Option<Vec<Segment>> {
+        fn walk(ast: &Ast, path: &mut Vec<Segment>) -> Option<()> {
+            match ast {
+                Ast::Identity => {}
+                Ast::Field(term, name) => {
+                    walk(term, path)?;
+                    path.push(Segment::Key(name.clone()));
+                }
+                Ast::Index(term, index) => {
+                    walk(term, path)?;
+                    match &**index {
+                        Ast::Literal(Value::String(k)) => path.push(Segment::Key(k.clone())),
+                        Ast::Literal(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => {
+                            path.push(Segment::Index(*n as usize))
+                        }
+                        _ => return None,
+                    }
+                }
+                Ast::Iterate(term) => {
+                    walk(term, path)?;
+                    path.push(Segment::Children);
+                }
+                Ast::Pipe(left, right) => {
+                    walk(left, path)?;
+                    walk(right, path)?;
+                }
+                _ => return None,
+            }
+            Some(())
+        }
+        let mut path = vec![];
+        walk(&self.0, &mut path)?;
+        Some(path)
+    }
+}
+
+/// Compile a jq-like filter expression such as `.items[] | select(.active) | .name`.
+pub fn compile_filter(s: &str) -> Result<Filter, &'static str> {
+    let mut parser = Parser {
+        tokens: tokenize(s)?,
+        pos: 0,
+        depth: 0,
+    };
+    let ast = parser.pipe()?;
+    if *parser.peek() != Token::Eof {
+        return Err("unexpected token after filter.");
+    }
+    Ok(Filter(ast))
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, &'static str> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '.' => match next {
+                Some('.') => (Token::Recurse, 2),
+                Some(c) if is_identifier_start(c) => {
+                    let end = scan(&chars, i + 1, is_identifier);
+                    (Token::Field(chars[i + 1..end].iter().collect()), end - i)
+                }
+                Some('"') => {
+                    let (s, end) = string(&chars, i + 1)?;
+                    (Token::Field(s), end - i)
+                }
+                _ => (Token::Dot, 1),
+            },
+            '[' => (Token::LeftBracket, 1),
+            ']' => (Token::RightBracket, 1),
+            '{' => (Token::LeftBrace, 1),
+            '}' => (Token::RightBrace, 1),
+            '(' => (Token::LeftParen, 1),
+            ')' => (Token::RightParen, 1),
+            '|' => (Token::Pipe, 1),
+            ',' => (Token::Comma, 1),
+            ':' => (Token::Colon, 1),
+            ';' => (Token::Semicolon, 1),
+            '?' => (Token::Question, 1),
+            '+' => (Token::Op(Op::Add), 1),
+            '-' => (Token::Op(Op::Sub), 1),
+            '*' => (Token::Op(Op::Mul), 1),
+            '%' => (Token::Op(Op::Rem), 1),
+            '/' => match next {
+                Some('/') => (Token::Alternative, 2),
+                _ => (Token::Op(Op::Div), 1),
+            },
+            '=' => match next {
+                Some('=') => (Token::Op(Op::Eq), 2),
+                _ => return Err("assignment is not supported."),
+            },
+            '!' => match next {
+                Some('=') => (Token::Op(Op::Ne), 2),
+                _ => return Err("unexpected '!' in filter."),
+            },
+            '<' => match next {
+                Some('=') => (Token::Op(Op::Le), 2),
+                _ => (Token::Op(Op::Lt), 1),
+            },
+            '>' => match next {
+                Some('=') => (Token::Op(Op::Ge), 2),
+                _ => (Token::Op(Op::Gt), 1),
+            },
+            '"' => {
+                let (s, end) = string(&chars, i)?;
+                (Token::Str(s), end - i)
+            }
+            c if c.is_ascii_digit() => {
+                let end = scan(&chars, i, |c| {
+                    c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E'
+                });
+                let s: String = chars[i..end].iter().collect();
+                let n = s.parse::<f64>().map_err(|_| "invalid number in filter.")?;
+                (Token::Number(n), end - i)
+            }
+            c if is_identifier_start(c) => {
+                let end = scan(&chars, i, is_identifier);
+                (Token::Identifier(chars[i..end].iter().collect()), end - i)
+            }
+            _ => return Err("unexpected character in filter."),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    tokens.push(Token::Eof);
+    Ok(tokens)
+}
+
+// return the index of the first character from `start` not matching `f`.
+fn scan(chars: &[char], start: usize, f: impl Fn(char) -> bool) -> usize {
+    let mut end = start;
+    while end < chars.len() && f(chars[end]) {
+        end += 1;
+    }
+    end
+}
+
+// read the string literal whose opening quote is at `start`, return the
+// unescaped content and the index after the closing quote.
+fn string(chars: &[char], start: usize) -> Result<(String, usize), &'static str> {
+    let mut out = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((out, i + 1)),
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some(&c) if c == '"' || c == '\\' || c == '/' => out.push(c),
+                    _ => return Err("invalid escape in string."),
+                }
+                i += 2;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated string in filter.")
+}
+
+// recursive descent parser, one method per precedence level from `|` down to postfix terms.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // the depth of the tree being built: every term and operator counts as a
+    // level, so that neither nesting nor long chains can exhaust the stack
+    // when the tree is evaluated.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, token: Token, err: &'static str) -> Result<(), &'static str> {
+        if self.advance() != token {
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Identifier(s) if s == keyword)
+    }
+
+    fn enter(&mut self) -> Result<(), &'static str> {
+        if self.depth == MAX_DEPTH {
+            return Err("filter nested too deeply.");
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // parse with `parse`, starting over at the current depth afterwards.
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Parser) -> Result<Ast, &'static str>,
+    ) -> Result<Ast, &'static str> {
+        let depth = self.depth;
+        let ast = parse(self);
+        self.depth = depth;
+        ast
+    }
+
+    fn pipe(&mut self) -> Result<Ast, &'static str> {
+        self.nested(|p| {
+            let left = p.comma()?;
+            if *p.peek() == Token::Pipe {
+                p.advance();
+                return Ok(Ast::Pipe(Box::new(left), Box::new(p.pipe()?)));
+            }
+            Ok(left)
+        })
+    }
+
+    fn comma(&mut self) -> Result<Ast, &'static str> {
+        let mut left = self.alternative()?;
+        while *self.peek() == Token::Comma {
+            self.enter()?;
+            self.advance();
+            left = Ast::Comma(Box::new(left), Box::new(self.alternative()?));
+        }
+        Ok(left)
+    }
+
+    fn alternative(&mut self) -> Result<Ast, &'static str> {
+        let left = self.or()?;
+        if *self.peek() == Token::Alternative {
+            self.enter()?;
+            self.advance();
+            return Ok(Ast::Alternative(
+                Box::new(left),
+                Box::new(self.alternative()?),
+            ));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Ast, &'static str> {
+        let mut left = self.and()?;
+        while self.is_keyword("or") {
+            self.enter()?;
+            self.advance();
+            left = Ast::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Ast, &'static str> {
+        let mut left = self.comparison()?;
+        while self.is_keyword("and") {
+            self.enter()?;
+            self.advance();
+            left = Ast::And(Box::new(left), Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Ast, &'static str> {
+        let left = self.additive()?;
+        match *self.peek() {
+            Token::Op(op @ (Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge)) => {
+                self.enter()?;
+                self.advance();
+                let right = self.additive()?;
+                Ok(Ast::Binary(op, Box::new(left), Box::new(right)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn additive(&mut self) -> Result<Ast, &'static str> {
+        let mut left = self.multiplicative()?;
+        while let Token::Op(op @ (Op::Add | Op::Sub)) = *self.peek() {
+            self.enter()?;
+            self.advance();
+            left = Ast::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Ast, &'static str> {
+        let mut left = self.postfix()?;
+        while let Token::Op(op @ (Op::Mul | Op::Div | Op::Rem)) = *self.peek() {
+            self.enter()?;
+            self.advance();
+            left = Ast::Binary(op, Box::new(left), Box::new(self.postfix()?));
+        }
+        Ok(left)
+    }
+
+    fn postfix(&mut self) -> Result<Ast, &'static str> {
+        self.enter()?;
+        let mut term = self.primary()?;
+        loop {
+            term = match self.peek() {
+                Token::Field(name) => {
+                    let name = name.clone();
+                    self.enter()?;
+                    self.advance();
+                    Ast::Field(Box::new(term), name)
+                }
+                Token::Dot if self.tokens.get(self.pos + 1) == Some(&Token::LeftBracket) => {
+                    self.advance();
+                    continue;
+                }
+                Token::LeftBracket => {
+                    self.enter()?;
+                    self.advance();
+                    self.bracket_suffix(term)?
+                }
+                Token::Question => {
+                    self.enter()?;
+                    self.advance();
+                    Ast::Try(Box::new(term))
+                }
+                _ => return Ok(term),
+            };
+        }
+    }
+
+    // the left bracket is already consumed.
+    fn bracket_suffix(&mut self, term: Ast) -> Result<Ast, &'static str> {
+        if *self.peek() == Token::RightBracket {
+            self.advance();
+            return Ok(Ast::Iterate(Box::new(term)));
+        }
+        let start = if *self.peek() == Token::Colon {
+            None
+        } else {
+            Some(Box::new(self.pipe()?))
+        };
+        if *self.peek() != Token::Colon {
+            self.expect(Token::RightBracket, "right bracket expected.")?;
+            return match start {
+                Some(index) => Ok(Ast::Index(Box::new(term), index)),
+                None => Err("index expected."),
+            };
+        }
+        self.advance();
+        let end = if *self.peek() == Token::RightBracket {
+            None
+        } else {
+            Some(Box::new(self.pipe()?))
+        };
+        self.expect(Token::RightBracket, "right bracket expected.")?;
+        Ok(Ast::Slice(Box::new(term), start, end))
+    }
+
+    fn primary(&mut self) -> Result<Ast, &'static str> {
+        match self.advance() {
+            Token::Dot => Ok(Ast::Identity),
+            Token::Recurse => Ok(Ast::Recurse),
+            Token::Field(name) => Ok(Ast::Field(Box::new(Ast::Identity), name)),
+            Token::Number(n) => Ok(Ast::Literal(Value::Number(n))),
+            Token::Str(s) => Ok(Ast::Literal(Value::String(s))),
+            Token::Op(Op::Sub) => Ok(Ast::Negate(Box::new(self.postfix()?))),
+            Token::LeftParen => {
+                let ast = self.pipe()?;
+                self.expect(Token::RightParen, "right parenthesis expected.")?;
+                Ok(ast)
+            }
+            Token::LeftBracket => {
+                if *self.peek() == Token::RightBracket {
+                    self.advance();
+                    return Ok(Ast::Array(None));
+                }
+                let ast = self.pipe()?;
+                self.expect(Token::RightBracket, "right bracket expected.")?;
+                Ok(Ast::Array(Some(Box::new(ast))))
+            }
+            Token::LeftBrace => self.object(),
+            Token::Identifier(name) => match name.as_str() {
+                "true" => Ok(Ast::Literal(Value::Bool(true))),
+                "false" => Ok(Ast::Literal(Value::Bool(false))),
+                "null" => Ok(Ast::Literal(Value::Null)),
+                _ => self.call(name),
+            },
+            _ => Err("unexpected token in filter."),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Ast, &'static str> {
+        let mut args = vec![];
+        if *self.peek() == Token::LeftParen {
+            self.advance();
+            loop {
+                args.push(self.pipe()?);
+                match self.advance() {
+                    Token::Semicolon => continue,
+                    Token::RightParen => break,
+                    _ => return Err("semicolon or right parenthesis expected."),
+                }
+            }
+        }
+        if !BUILTINS
+            .iter()
+            .any(|&(n, arity)| n == name && arity == args.len())
+        {
+            return Err("unknown function.");
+        }
+        Ok(Ast::Call(name, args))
+    }
+
+    // the left brace is already consumed.
+    fn object(&mut self) -> Result<Ast, &'static str> {
+        let mut entries = vec![];
+        if *self.peek() == Token::RightBrace {
+            self.advance();
+            return Ok(Ast::Object(entries));
+        }
+        loop {
+            let key = match self.advance() {
+                Token::Identifier(key) | Token::Str(key) => key,
+                _ => return Err("object key expected."),
+            };
+            let value = if *self.peek() == Token::Colon {
+                self.advance();
+                self.nested(Parser::alternative)?
+            } else {
+                // `{foo}` is shorthand for `{foo: .foo}`.
+                Ast::Field(Box::new(Ast::Identity), key.clone())
+            };
+            entries.push((Ast::Literal(Value::String(key)), value));
+            match self.advance() {
+                Token::Comma => continue,
+                Token::RightBrace => break,
+                _ => return Err("comma or right brace expected."),
+            }
+        }
+        Ok(Ast::Object(entries))
+    }
+}
+
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+// jq's total order: null < false < true < numbers < strings < arrays < objects.
+fn compare(left: &Value, right: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.partial_cmp(r).unwrap_or(Ordering::Equal),
+        (Value::String(l), Value::String(r)) => l.cmp(r),
+        (Value::Array(l), Value::Array(r)) => {
+            for (a, b) in l.iter().zip(r.iter()) {
+                let ord = compare(a, b);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            l.len().cmp(&r.len())
+        }
+        (Value::Object(l), Value::Object(r)) => {
+            let mut lk: Vec<&String> = l.keys().collect();
+            let mut rk: Vec<&String> = r.keys().collect();
+            lk.sort();
+            rk.sort();
+            let ord = lk.cmp(&rk);
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            for k in lk {
+                let ord = compare(&l[k], &r[k]);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            Ordering::Equal
+        }
+        _ => rank(left).cmp(&rank(right)),
+    }
+}
+
+fn arithmetic(op: Op, left: &Value, right: &Value) -> Result<Value, &'static str> {
+    match (op, left, right) {
+        (Op::Eq, l, r) => Ok(Value::Bool(compare(l, r) == Ordering::Equal)),
+        (Op::Ne, l, r) => Ok(Value::Bool(compare(l, r) != Ordering::Equal)),
+        (Op::Lt, l, r) => Ok(Value::Bool(compare(l, r) == Ordering::Less)),
+        (Op::Le, l, r) => Ok(Value::Bool(compare(l, r) != Ordering::Greater)),
+        (Op::Gt, l, r) => Ok(Value::Bool(compare(l, r) == Ordering::Greater)),
+        (Op::Ge, l, r) => Ok(Value::Bool(compare(l, r) != Ordering::Less)),
+        (Op::Add, Value::Null, v) | (Op::Add, v, Value::Null) => Ok(v.clone()),
+        (Op::Add, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+        (Op::Add, Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
+        (Op::Add, Value::Array(l), Value::Array(r)) => {
+            Ok(Value::Array(l.iter().chain(r.iter()).cloned().collect()))
+        }
+        (Op::Add, Value::Object(l), Value::Object(r)) => {
+            let mut m = l.clone();
+            m.extend(r.iter().map(|(k, v)| (k.clone(), v.clone())));
+            Ok(Value::Object(m))
+        }
+        (Op::Sub, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
+        (Op::Sub, Value::Array(l), Value::Array(r)) => Ok(Value::Array(
+            l.iter().filter(|v| !r.contains(v)).cloned().collect(),
+        )),
+        (Op::Mul, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
+        (Op::Div, Value::Number(_), Value::Number(r)) if *r == 0.0 => Err("cannot divide by zero."),
+        (Op::Div, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l / r)),
+        (Op::Rem, Value::Number(l), Value::Number(r)) => {
+            let r = *r as i64;
+            if r == 0 {
+                return Err("cannot divide by zero.");
+            }
+            // `i64::MIN % -1` overflows.
+            let rem = (*l as i64).checked_rem(r).ok_or("remainder overflowed.")?;
+            Ok(Value::Number(rem as f64))
+        }
+        _ => Err("unsupported operand types."),
+    }
+}
+
+fn recurse(value: &Value, out: &mut Vec<Value>) {
+    out.push(value.clone());
+    match value {
+        Value::Array(v) => v.iter().for_each(|item| recurse(item, out)),
+        Value::Object(m) => m.values().for_each(|item| recurse(item, out)),
+        _ => {}
+    }
+}
+
+fn index(value: &Value, index: &Value) -> Result<Value, &'static str> {
+    match (value, index) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(m), Value::String(k)) => Ok(m.get(k).cloned().unwrap_or(Value::Null)),
+        (Value::Array(v), Value::Number(n)) => {
+            let i = *n as i64;
+            let i = if i < 0 { v.len() as i64 + i } else { i };
+            if i < 0 {
+                return Ok(Value::Null);
+            }
+            Ok(v.get(i as usize).cloned().unwrap_or(Value::Null))
+        }
+        _ => Err("cannot index value."),
+    }
+}
+
+fn slice(value: &Value, start: Option<&Value>, end: Option<&Value>) -> Result<Value, &'static str> {
+    let v = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::Array(v) => v,
+        _ => return Err("cannot slice value."),
+    };
+    let len = v.len() as i64;
+    let bound = |b: Option<&Value>, default: i64| match b {
+        None | Some(Value::Null) => Ok(default),
+        Some(Value::Number(n)) => {
+            let n = *n as i64;
+            let n = if n < 0 { len + n } else { n };
+            Ok(n.max(0).min(len))
+        }
+        _ => Err("slice bounds must be numbers."),
+    };
+    let start = bound(start, 0)?;
+    let end = bound(end, len)?.max(start);
+    Ok(Value::Array(v[start as usize..end as usize].to_vec()))
+}
+
+// run `f` for every combination of outputs of `left` and `right`.
+fn cartesian(
+    left: &Ast,
+    right: &Ast,
+    input: &Value,
+    f: impl Fn(&Value, &Value) -> Result<Value, &'static str>,
+) -> Result<Vec<Value>, &'static str> {
+    let rights = evaluate(right, input)?;
+    let mut out = vec![];
+    for l in evaluate(left, input)? {
+        for r in &rights {
+            out.push(f(&l, r)?);
+        }
+    }
+    Ok(out)
+}
+
+fn evaluate(ast: &Ast, input: &Value) -> Result<Vec<Value>, &'static str> {
+    match ast {
+        Ast::Identity => Ok(vec![input.clone()]),
+        Ast::Recurse => {
+            let mut out = vec![];
+            recurse(input, &mut out);
+            Ok(out)
+        }
+        Ast::Field(term, name) => {
+            let key = Value::String(name.clone());
+            let mut out = vec![];
+            for v in evaluate(term, input)? {
+                out.push(index(&v, &key)?);
+            }
+            Ok(out)
+        }
+        Ast::Index(term, idx) => {
+            let indices = evaluate(idx, input)?;
+            let mut out = vec![];
+            for v in evaluate(term, input)? {
+                for i in &indices {
+                    out.push(index(&v, i)?);
+                }
+            }
+            Ok(out)
+        }
+        Ast::Slice(term, start, end) => {
+            let starts = match start {
+                Some(s) => evaluate(s, input)?.into_iter().map(Some).collect(),
+                None => vec![None],
+            };
+            let ends = match end {
+                Some(e) => evaluate(e, input)?.into_iter().map(Some).collect(),
+                None => vec![None],
+            };
+            let mut out = vec![];
+            for v in evaluate(term, input)? {
+                for s in &starts {
+                    for e in &ends {
+                        out.push(slice(&v, s.as_ref(), e.as_ref())?);
+                    }
+                }
+            }
+            Ok(out)
+        }
+        Ast::Iterate(term) => {
+            let mut out = vec![];
+            for v in evaluate(term, input)? {
+                match v {
+                    Value::Array(items) => out.extend(items),
+                    Value::Object(m) => out.extend(m.into_values()),
+                    _ => return Err("cannot iterate over value."),
+                }
+            }
+            Ok(out)
+        }
+        Ast::Try(term) => Ok(evaluate(term, input).unwrap_or_default()),
+        Ast::Literal(v) => Ok(vec![v.clone()]),
+        Ast::Array(None) => Ok(vec![Value::Array(vec![])]),
+        Ast::Array(Some(inner)) => Ok(vec![Value::Array(evaluate(inner, input)?)]),
+        Ast::Object(entries) => {
+            let mut objects = vec![HashMap::new()];
+            for (key, value) in entries {
+                let keys = evaluate(key, input)?;
+                let values = evaluate(value, input)?;
+                let mut next = vec![];
+                for m in &objects {
+                    for k in &keys {
+                        let k = match k {
+                            Value::String(k) => k,
+                            _ => return Err("object keys must be strings."),
+                        };
+                        for v in &values {
+                            let mut m = m.clone();
+                            m.insert(k.clone(), v.clone());
+                            next.push(m);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            Ok(objects.into_iter().map(Value::Object).collect())
+        }
+        Ast::Pipe(left, right) => {
+            let mut out = vec![];
+            for v in evaluate(left, input)? {
+                out.extend(evaluate(right, &v)?);
+            }
+            Ok(out)
+        }
+        Ast::Comma(left, right) => {
+            let mut out = evaluate(left, input)?;
+            out.extend(evaluate(right, input)?);
+            Ok(out)
+        }
+        Ast::Alternative(left, right) => {
+            let out: Vec<Value> = evaluate(left, input)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(is_truthy)
+                .collect();
+            if out.is_empty() {
+                return evaluate(right, input);
+            }
+            Ok(out)
+        }
+        Ast::Or(left, right) | Ast::And(left, right) => {
+            let is_or = matches!(ast, Ast::Or(..));
+            let mut out = vec![];
+            for l in evaluate(left, input)? {
+                if is_truthy(&l) == is_or {
+                    out.push(Value::Bool(is_or));
+                    continue;
+                }
+                for r in evaluate(right, input)? {
+                    out.push(Value::Bool(is_truthy(&r)));
+                }
+            }
+            Ok(out)
+        }
+        Ast::Binary(op, left, right) => cartesian(left, right, input, |l, r| arithmetic(*op, l, r)),
+        Ast::Negate(term) => {
+            let mut out = vec![];
+            for v in evaluate(term, input)? {
+                match v {
+                    Value::Number(n) => out.push(Value::Number(-n)),
+                    _ => return Err("cannot negate value."),
+                }
+            }
+            Ok(out)
+        }
+        Ast::Call(name, args) => call(name, args, input),
+    }
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>, &'static str> {
+    match value {
+        Value::Array(v) => Ok(v),
+        _ => Err("array expected."),
+    }
+}
+
+fn call(name: &str, args: &[Ast], input: &Value) -> Result<Vec<Value>, &'static str> {
+    let one = |v: Value| Ok(vec![v]);
+    match (name, args) {
+        ("empty", _) => Ok(vec![]),
+        ("not", _) => one(Value::Bool(!is_truthy(input))),
+        ("length", _) => match input {
+            Value::Null => one(Value::Number(0.0)),
+            Value::Number(n) => one(Value::Number(n.abs())),
+            Value::String(s) => one(Value::Number(s.chars().count() as f64)),
+            Value::Array(v) => one(Value::Number(v.len() as f64)),
+            Value::Object(m) => one(Value::Number(m.len() as f64)),
+            Value::Bool(_) => Err("boolean has no length."),
+        },
+        ("keys", _) => match input {
+            Value::Object(m) => {
+                let mut keys: Vec<&String> = m.keys().collect();
+                keys.sort();
+                one(Value::Array(
+                    keys.into_iter().map(|k| Value::String(k.clone())).collect(),
+                ))
+            }
+            Value::Array(v) => one(Value::Array(
+                (0..v.len()).map(|i| Value::Number(i as f64)).collect(),
+            )),
+            _ => Err("value has no keys."),
+        },
+        ("values", _) => Ok(if *input == Value::Null {
+            vec![]
+        } else {
+            vec![input.clone()]
+        }),
+        ("has", [key]) => {
+            let mut out = vec![];
+            for k in evaluate(key, input)? {
+                let has = match (input, &k) {
+                    (Value::Object(m), Value::String(k)) => m.contains_key(k),
+                    (Value::Array(v), Value::Number(n)) => *n >= 0.0 && (*n as usize) < v.len(),
+                    _ => return Err("cannot check whether value has a key."),
+                };
+                out.push(Value::Bool(has));
+            }
+            Ok(out)
+        }
+        ("select", [condition]) => {
+            let mut out = vec![];
+            for c in evaluate(condition, input)? {
+                if is_truthy(&c) {
+                    out.push(input.clone());
+                }
+            }
+            Ok(out)
+        }
+        ("map", [f]) => {
+            let mut out = vec![];
+            for item in array(input)? {
+                out.extend(evaluate(f, item)?);
+            }
+            one(Value::Array(out))
+        }
+        ("type", _) => one(Value::String(input.kind().name().to_owned())),
+        ("add", _) => {
+            let mut acc = Value::Null;
+            for item in array(input)? {
+                acc = arithmetic(Op::Add, &acc, item)?;
+            }
+            one(acc)
+        }
+        ("first", [f]) => Ok(evaluate(f, input)?.into_iter().take(1).collect()),
+        ("first", _) => one(array(input)?.first().cloned().unwrap_or(Value::Null)),
+        ("last", _) => one(array(input)?.last().cloned().unwrap_or(Value::Null)),
+        ("reverse", _) => match input {
+            Value::String(s) => one(Value::String(s.chars().rev().collect())),
+            Value::Null => one(Value::Array(vec![])),
+            v => one(Value::Array(array(v)?.iter().rev().cloned().collect())),
+        },
+        ("sort", _) | ("unique", _) | ("min", _) | ("max", _) => {
+            let mut v = array(input)?.clone();
+            v.sort_by(compare);
+            match name {
+                "sort" => one(Value::Array(v)),
+                "unique" => {
+                    v.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+                    one(Value::Array(v))
+                }
+                "min" => one(v.into_iter().next().unwrap_or(Value::Null)),
+                _ => one(v.pop().unwrap_or(Value::Null)),
+            }
+        }
+        ("any", _) => one(Value::Bool(array(input)?.iter().any(is_truthy))),
+        ("all", _) => one(Value::Bool(array(input)?.iter().all(is_truthy))),
+        ("tonumber", _) => match input {
+            Value::Number(n) => one(Value::Number(*n)),
+            Value::String(s) => s
+                .parse::<f64>()
+                .map(|n| vec![Value::Number(n)])
+                .map_err(|_| "cannot parse string as number."),
+            _ => Err("cannot convert value to number."),
+        },
+        ("ascii_downcase", _) | ("ascii_upcase", _) => match input {
+            Value::String(s) if name == "ascii_downcase" => {
+                one(Value::String(s.to_ascii_lowercase()))
+            }
+            Value::String(s) => one(Value::String(s.to_ascii_uppercase())),
+            _ => Err("string expected."),
+        },
+        ("to_entries", _) => match input {
+            Value::Object(m) => {
+                let mut keys: Vec<&String> = m.keys().collect();
+                keys.sort();
+                let entries = keys
+                    .into_iter()
+                    .map(|k| {
+                        let mut entry = HashMap::new();
+                        entry.insert("key".to_owned(), Value::String(k.clone()));
+                        entry.insert("value".to_owned(), m[k].clone());
+                        Value::Object(entry)
+                    })
+                    .collect();
+                one(Value::Array(entries))
+            }
+            _ => Err("object expected."),
+        },
+        _ => Err("unknown function."),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    fn run(filter: &str, input: &str) -> Vec<Value> {
+        compile_filter(filter)
+            .unwrap()
+            .apply(&parse(input).unwrap())
+            .unwrap()
+    }
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_owned())
+    }
+
+    #[test]
+    fn test_filter() {
+        let doc = r#"{"items": [
+            {"name": "a", "active": true, "n": 1},
+            {"name": "b", "active": false, "n": 2},
+            {"name": "c", "active": true, "n": 3}
+        ]}"#;
+        assert_eq!(
+            run(".items[] | select(.active) | .name", doc),
+            vec![s("a"), s("c")]
+        );
+        assert_eq!(run(".items[0].name", doc), vec![s("a")]);
+        assert_eq!(run(".items[-1].name", doc), vec![s("c")]);
+        assert_eq!(run(".items | length", doc), vec![Value::Number(3.0)]);
+        assert_eq!(run("[.items[].n] | add", doc), vec![Value::Number(6.0)]);
+        assert_eq!(
+            run(".items | map(.n * 2)", doc),
+            vec![Value::Array(vec![
+                Value::Number(2.0),
+                Value::Number(4.0),
+                Value::Number(6.0)
+            ])]
+        );
+        assert_eq!(run(".items[1:][].name", doc), vec![s("b"), s("c")]);
+        assert_eq!(
+            run(".items[] | select(.n >= 2 and .active) | .name", doc),
+            vec![s("c")]
+        );
+        assert_eq!(run(".missing // \"default\"", doc), vec![s("default")]);
+        assert_eq!(
+            run(".items[0] | .name, .n", doc),
+            vec![s("a"), Value::Number(1.0)]
+        );
+        let mut m = HashMap::new();
+        m.insert("name".to_owned(), s("a"));
+        m.insert("x".to_owned(), Value::Number(1.0));
+        assert_eq!(
+            run(".items[0] | {name, x: .n}", doc),
+            vec![Value::Object(m)]
+        );
+        assert_eq!(
+            run(".items[0] | has(\"name\")", doc),
+            vec![Value::Bool(true)]
+        );
+        assert_eq!(
+            run("[..] | length", r#"{"a": [1]}"#),
+            vec![Value::Number(3.0)]
+        );
+        assert_eq!(run(".[\"a\"]", r#"{"a": 1}"#), vec![Value::Number(1.0)]);
+        assert_eq!(run(".a.b?", r#"{"a": 1}"#), vec![]);
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        assert!(compile_filter(".items[").is_err());
+        assert!(compile_filter("nope").is_err());
+        assert!(compile_filter("select").is_err());
+        assert!(compile_filter(". .").is_err());
+        let nested = |n: usize| "(".repeat(n) + "." + &")".repeat(n);
+        assert!(compile_filter(&nested(MAX_DEPTH / 2)).is_ok());
+        for s in &[
+            nested(100_000),
+            ".a".repeat(100_000),
+            ". | ".repeat(100_000) + ".",
+        ] {
+            assert_eq!(compile_filter(s).err(), Some("filter nested too deeply."));
+        }
+        let f = compile_filter(".a.b").unwrap();
+        assert!(f.apply(&parse(r#"{"a": 1}"#).unwrap()).is_err());
+        let f = compile_filter(".[]").unwrap();
+        assert!(f.apply(&Value::Bool(true)).is_err());
+        let f = compile_filter(". % -1").unwrap();
+        assert_eq!(f.apply(&Value::Number(7.0)), Ok(vec![Value::Number(0.0)]));
+        assert_eq!(f.apply(&Value::Number(-1e19)), Err("remainder overflowed."));
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
 use std::vec::Vec;
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -6,7 +6,6 @@ pub enum TokenType {
     Null,
     Number,
     String,
-    Quote,
     Boolean,
     LeftBracket,
     RightBracket,
@@ -14,6 +13,10 @@ pub enum TokenType {
     RightSquareBracket,
     Colon,
     Comma,
+    /// A run of whitespace, only emitted by `generate_tokens_with_trivia`.
+    Whitespace,
+    /// A `//` or `/* */` comment, only emitted by `generate_tokens_with_trivia`.
+    Comment,
 }
 
 #[derive(PartialEq, Debug)]
@@ -23,106 +26,348 @@ pub struct Token<'a> {
     pub _type: TokenType,
 }
 
-const DELIMITERS: [u8; 6] = [b'{', b'}', b'[', b']', b':', b','];
+impl Token<'_> {
+    /// Whether the token carries no meaning for the parser, i.e. whitespace or
+    /// a comment.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self._type, TokenType::Whitespace | TokenType::Comment)
+    }
+}
+
+// what the lexer does on top of producing the significant tokens.
+#[derive(Clone, Copy, Default)]
+struct Mode {
+    // check string tokens are UTF-8 and reject non-ASCII bytes elsewhere.
+    validate: bool,
+    // emit whitespace and comments as trivia tokens.
+    trivia: bool,
+}
+
+// how the lexer treats each byte value.
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Class {
+    Whitespace,
+    Quote,
+    Delimiter,
+    Other,
+}
 
+// the class of every byte, built at compile time so the scanning loop does a
+// single lookup per byte.
+const CLASSES: [Class; 256] = {
+    let mut classes = [Class::Other; 256];
+    let whitespace = [b' ', b'\t', b'\n', b'\r', 0x0c];
+    let mut i = 0;
+    while i < whitespace.len() {
+        classes[whitespace[i] as usize] = Class::Whitespace;
+        i += 1;
+    }
+    let delimiters = [b'{', b'}', b'[', b']', b':', b','];
+    let mut i = 0;
+    while i < delimiters.len() {
+        classes[delimiters[i] as usize] = Class::Delimiter;
+        i += 1;
+    }
+    classes[b'"' as usize] = Class::Quote;
+    classes
+};
+
+fn class(b: u8) -> Class {
+    CLASSES[b as usize]
+}
+
+// only called for delimiter bytes, so anything left over is a comma.
 fn get_token_type(b: u8) -> TokenType {
-    let mut delimiter_map = HashMap::new();
-    delimiter_map.insert(b'{', TokenType::LeftBracket);
-    delimiter_map.insert(b'}', TokenType::RightBracket);
-    delimiter_map.insert(b'[', TokenType::LeftSquareBracket);
-    delimiter_map.insert(b']', TokenType::RightSquareBracket);
-    delimiter_map.insert(b':', TokenType::Colon);
-    delimiter_map.insert(b',', TokenType::Comma);
-    *delimiter_map.get(&b).unwrap()
+    match b {
+        b'{' => TokenType::LeftBracket,
+        b'}' => TokenType::RightBracket,
+        b'[' => TokenType::LeftSquareBracket,
+        b']' => TokenType::RightSquareBracket,
+        b':' => TokenType::Colon,
+        _ => TokenType::Comma,
+    }
 }
 
 /// use DFA to produce the tokens from the string s.
-///
-pub fn generate_tokens(s: &str) -> Vec<Token<'_>> {
-    if s.is_empty() {
-        return vec![];
-    }
+/// Unsupported keywords are reported as errors rather than panics.
+pub fn generate_tokens(s: &str) -> Result<Vec<Token<'_>>, &'static str> {
+    tokenize(s.as_bytes(), Mode::default())
+}
+
+/// Like `generate_tokens`, also emitting whitespace and comments as trivia
+/// tokens so that joining every token gives back `s` exactly.
+pub fn generate_tokens_with_trivia(s: &str) -> Result<Vec<Token<'_>>, &'static str> {
+    let mut tokens = vec![];
+    generate_tokens_with_trivia_into(s, &mut tokens)?;
+    Ok(tokens)
+}
+
+// on error `tokens` keeps what was lexed before the failure.
+pub(crate) fn generate_tokens_with_trivia_into<'a>(
+    s: &'a str,
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<(), &'static str> {
+    let mode = Mode {
+        trivia: true,
+        ..Mode::default()
+    };
+    tokenize_into(s.as_bytes(), mode, tokens)
+}
 
+/// Like `generate_tokens` for bytes that may not be UTF-8. Only string
+/// contents can hold non-ASCII bytes outside of invalid keywords, so they are
+/// validated as each string is scanned instead of in a separate pass.
+pub fn generate_tokens_from_bytes(bytes: &[u8]) -> Result<Vec<Token<'_>>, &'static str> {
+    let mode = Mode {
+        validate: true,
+        ..Mode::default()
+    };
+    tokenize(bytes, mode)
+}
+
+fn tokenize(bytes: &[u8], mode: Mode) -> Result<Vec<Token<'_>>, &'static str> {
+    let mut tokens = vec![];
+    tokenize_into(bytes, mode, &mut tokens)?;
+    Ok(tokens)
+}
+
+// like `generate_tokens`, calling `check` on every token as soon as it is
+// lexed and stopping at the first error it returns.
+pub(crate) fn generate_tokens_checked<'a>(
+    s: &'a str,
+    mut check: impl FnMut(&Token<'a>) -> Result<(), &'static str>,
+) -> Result<Vec<Token<'a>>, &'static str> {
     let bytes = s.as_bytes();
     let mut tokens = vec![];
     let mut i = 0;
-    loop {
-        if i >= bytes.len() {
-            break;
+    while i < bytes.len() {
+        let lexed = tokens.len();
+        i = next_token(bytes, i, Mode::default(), &mut tokens)?;
+        if let Some(token) = tokens.get(lexed) {
+            check(token)?;
         }
-        match bytes[i] {
-            b'"' => {
-                i = add_quoted_string(bytes, i, &mut tokens);
-            }
-            c if is_delimiters(c) => {
-                i = add_delimiter_token(bytes, i, &mut tokens);
+    }
+    Ok(tokens)
+}
+
+/// Like `generate_tokens`, appending to `tokens` so its storage can be reused.
+pub fn generate_tokens_into<'a>(
+    s: &'a str,
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<(), &'static str> {
+    tokenize_into(s.as_bytes(), Mode::default(), tokens)
+}
+
+fn tokenize_into<'a>(
+    bytes: &'a [u8],
+    mode: Mode,
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<(), &'static str> {
+    let mut i = 0;
+    while i < bytes.len() {
+        i = next_token(bytes, i, mode, tokens)?;
+    }
+    Ok(())
+}
+
+/// Lex the token starting at `i` as `generate_tokens_with_trivia` would and
+/// return the index after it.
+pub(crate) fn next_trivia_token<'a>(
+    bytes: &'a [u8],
+    i: usize,
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<usize, &'static str> {
+    let mode = Mode {
+        trivia: true,
+        ..Mode::default()
+    };
+    next_token(bytes, i, mode, tokens)
+}
+
+// lex the token starting at `i`, which must be in `bytes`, and return the
+// index of the next character to process.
+fn next_token<'a>(
+    bytes: &'a [u8],
+    mut i: usize,
+    mode: Mode,
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<usize, &'static str> {
+    match class(bytes[i]) {
+        Class::Quote => {
+            i = add_string_token(bytes, i, tokens)?;
+            if mode.validate && !is_utf8(tokens[tokens.len() - 1].s) {
+                return Err("invalid utf-8 in string.");
             }
-            c if c.is_ascii_whitespace() => {
-                i += 1;
+        }
+        Class::Delimiter => {
+            i = add_delimiter_token(bytes, i, tokens);
+        }
+        Class::Whitespace => {
+            // skip the whole run of whitespace at once.
+            let start = i;
+            i = bytes[i..]
+                .iter()
+                .position(|&c| class(c) != Class::Whitespace)
+                .map_or(bytes.len(), |p| i + p);
+            if mode.trivia {
+                tokens.push(Token {
+                    s: &bytes[start..i],
+                    start,
+                    _type: TokenType::Whitespace,
+                });
             }
-            _ => {
-                i = add_keyword_or_number(bytes, i, &mut tokens);
+        }
+        Class::Other if mode.trivia && bytes[i] == b'/' => {
+            i = add_comment_token(bytes, i, tokens)?;
+        }
+        Class::Other => {
+            if mode.validate && !bytes[i..keyword_end(bytes, i)].is_ascii() {
+                return Err("invalid utf-8 in input.");
             }
+            i = add_keyword_or_number(bytes, i, tokens)?;
         }
     }
+    Ok(i)
+}
 
-    tokens
+// check `b` is UTF-8, skipping the ASCII prefix a word at a time.
+fn is_utf8(b: &[u8]) -> bool {
+    let ascii = b
+        .chunks_exact(8)
+        .take_while(|c| {
+            let mut word = [0; 8];
+            word.copy_from_slice(c);
+            u64::from_ne_bytes(word) & 0x8080_8080_8080_8080 == 0
+        })
+        .count()
+        * 8;
+    std::str::from_utf8(&b[ascii..]).is_ok()
 }
 
-// input `start` is the next character to process.
-// return the index of the next character to process.
-fn add_quoted_string<'a, 'b>(
+// add the string starting at the quote at `start` as one token, quotes
+// included, and return the index of the next character to process.
+fn add_string_token<'a>(
     bytes: &'a [u8],
     start: usize,
-    tokens: &'b mut Vec<Token<'a>>,
-) -> usize {
-    let mut start = add_quote_token(bytes, start, tokens);
-    start = get_string_in_quote(bytes, start, tokens);
-    add_quote_token(bytes, start, tokens)
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<usize, &'static str> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i..].iter().position(|&c| c == b'"' || c == b'\\') {
+            // skip the escaped character, which may be a quote.
+            Some(p) if bytes[i + p] == b'\\' => i += p + 2,
+            Some(p) => {
+                let end = i + p + 1;
+                tokens.push(Token {
+                    s: &bytes[start..end],
+                    start,
+                    _type: TokenType::String,
+                });
+                return Ok(end);
+            }
+            None => break,
+        }
+    }
+    Err("unterminated string.")
 }
 
-fn add_quote_token<'a, 'b>(bytes: &'a [u8], start: usize, tokens: &'b mut Vec<Token<'a>>) -> usize {
-    if start < bytes.len() && bytes[start] == b'"' {
-        let token = Token {
-            s: &bytes[start..start + 1],
-            start,
-            _type: TokenType::Quote,
-        };
-        tokens.push(token);
-        return start + 1;
+/// Decode the escapes of the string token `s`, whose quotes are included,
+/// borrowing its content when there are none.
+pub fn decode_string(s: &[u8]) -> Result<Cow<'_, str>, &'static str> {
+    let raw = &s[1..s.len() - 1];
+    if !raw.contains(&b'\\') {
+        return std::str::from_utf8(raw)
+            .map(Cow::Borrowed)
+            .map_err(|_| "invalid utf-8 in string.");
+    }
+    let mut out = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter().copied();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b @ (b'"' | b'\\' | b'/')) => out.push(b),
+            Some(b'b') => out.push(0x08),
+            Some(b'f') => out.push(0x0c),
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'u') => {
+                let c = unicode_escape(&mut bytes)?;
+                out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            _ => return Err("invalid escape in string."),
+        }
     }
-    start
+    String::from_utf8(out)
+        .map(Cow::Owned)
+        .map_err(|_| "invalid utf-8 in string.")
 }
 
-fn get_string_in_quote<'a, 'b>(
+// decode the `XXXX` after `\u`, combining surrogate pairs.
+pub(crate) fn unicode_escape(bytes: &mut impl Iterator<Item = u8>) -> Result<char, &'static str> {
+    let hex4 = |bytes: &mut dyn Iterator<Item = u8>| -> Result<u32, &'static str> {
+        let mut n = 0;
+        for _ in 0..4 {
+            let digit = bytes
+                .next()
+                .and_then(|b| (b as char).to_digit(16))
+                .ok_or("invalid unicode escape.")?;
+            n = n * 16 + digit;
+        }
+        Ok(n)
+    };
+    let high = hex4(bytes)?;
+    let code = match high {
+        0xD800..=0xDBFF => {
+            if bytes.next() != Some(b'\\') || bytes.next() != Some(b'u') {
+                return Err("unpaired surrogate in string.");
+            }
+            let low = hex4(bytes)?;
+            if !(0xDC00..=0xDFFF).contains(&low) {
+                return Err("unpaired surrogate in string.");
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        }
+        0xDC00..=0xDFFF => return Err("unpaired surrogate in string."),
+        _ => high,
+    };
+    char::from_u32(code).ok_or("invalid unicode escape.")
+}
+
+// add the `//` comment running to the end of the line, or the `/* */`
+// comment, starting at `start`.
+fn add_comment_token<'a>(
     bytes: &'a [u8],
     start: usize,
-    tokens: &'b mut Vec<Token<'a>>,
-) -> usize {
-    if start >= bytes.len() {
-        return start;
-    }
-    let mut iter = bytes[start..].split_inclusive(|&c| c == b'"');
-    let end = start + iter.next().unwrap().len() - 1;
-    let token = Token {
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<usize, &'static str> {
+    let rest = &bytes[start..];
+    let end = if rest.starts_with(b"//") {
+        rest.iter()
+            .position(|&c| c == b'\n')
+            .map_or(bytes.len(), |p| start + p)
+    } else if rest.starts_with(b"/*") {
+        rest[2..]
+            .windows(2)
+            .position(|w| w == b"*/")
+            .map(|p| start + 2 + p + 2)
+            .ok_or("unterminated comment.")?
+    } else {
+        return Err("unsupported keyword or number.");
+    };
+    tokens.push(Token {
         s: &bytes[start..end],
         start,
-        _type: TokenType::String,
-    };
-    tokens.push(token);
-    end
-}
-
-fn is_delimiters(c: u8) -> bool {
-    DELIMITERS.iter().any(|&d| d == c)
+        _type: TokenType::Comment,
+    });
+    Ok(end)
 }
 
 // add delimiter token
-fn add_delimiter_token<'a, 'b>(
-    bytes: &'a [u8],
-    start: usize,
-    tokens: &'b mut Vec<Token<'a>>,
-) -> usize {
+fn add_delimiter_token<'a>(bytes: &'a [u8], start: usize, tokens: &mut Vec<Token<'a>>) -> usize {
     if start >= bytes.len() {
         return start;
     }
@@ -135,19 +380,30 @@ fn add_delimiter_token<'a, 'b>(
     start + 1
 }
 
-fn add_keyword_or_number<'a, 'b>(
+// index just past the keyword or number starting at `start`. It may run
+// until the end of input, e.g. a top-level `42`.
+fn keyword_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&c| matches!(class(c), Class::Whitespace | Class::Delimiter))
+        .map_or(bytes.len(), |p| start + p)
+}
+
+fn add_keyword_or_number<'a>(
     bytes: &'a [u8],
     start: usize,
-    tokens: &'b mut Vec<Token<'a>>,
-) -> usize {
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<usize, &'static str> {
     if start >= bytes.len() {
-        return start;
+        return Ok(start);
     }
-    let mut iter = bytes[start..].split_inclusive(|&c| c.is_ascii_whitespace() || is_delimiters(c));
-    let end = start + iter.next().unwrap().len() - 1;
+    let end = keyword_end(bytes, start);
     let b = &bytes[start..end];
 
-    if b[0].is_ascii_digit() {
+    if b[0] == b'-' || b[0].is_ascii_digit() {
+        if !is_number(b) {
+            return Err("invalid number.");
+        }
         let token = Token {
             s: &bytes[start..end],
             start,
@@ -155,30 +411,57 @@ fn add_keyword_or_number<'a, 'b>(
         };
         tokens.push(token);
     } else {
-        let s = std::str::from_utf8(b).unwrap(); // let's panic if unsupported character is met.
-        match s {
-            "null" => {
+        match b {
+            b"null" => {
                 add_null_token(bytes, start, "null".len(), tokens);
             }
-            "false" => {
+            b"false" => {
                 add_boolean_token(bytes, start, "false".len(), tokens);
             }
-            "true" => {
+            b"true" => {
                 add_boolean_token(bytes, start, "true".len(), tokens);
             }
             _ => {
-                panic!("Unsupported keyword or number.");
+                return Err("unsupported keyword or number.");
             }
         }
     }
-    end
+    Ok(end)
 }
-fn add_null_token<'a, 'b>(
-    bytes: &'a [u8],
-    start: usize,
-    length: usize,
-    tokens: &'b mut Vec<Token<'a>>,
-) {
+// whether `b` has the shape of a JSON number. Leading zeros are let through
+// so that tools working on tokens can point them out; `parse_number` rejects
+// them.
+fn is_number(b: &[u8]) -> bool {
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while b.get(*i).is_some_and(u8::is_ascii_digit) {
+            *i += 1;
+        }
+        *i > start
+    };
+    let mut i = (b[0] == b'-') as usize;
+    if !digits(&mut i) {
+        return false;
+    }
+    if b.get(i) == Some(&b'.') {
+        i += 1;
+        if !digits(&mut i) {
+            return false;
+        }
+    }
+    if matches!(b.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(b.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if !digits(&mut i) {
+            return false;
+        }
+    }
+    i == b.len()
+}
+
+fn add_null_token<'a>(bytes: &'a [u8], start: usize, length: usize, tokens: &mut Vec<Token<'a>>) {
     let token = Token {
         s: &bytes[start..start + length],
         start,
@@ -186,11 +469,11 @@ fn add_null_token<'a, 'b>(
     };
     tokens.push(token);
 }
-fn add_boolean_token<'a, 'b>(
+fn add_boolean_token<'a>(
     bytes: &'a [u8],
     start: usize,
     length: usize,
-    tokens: &'b mut Vec<Token<'a>>,
+    tokens: &mut Vec<Token<'a>>,
 ) {
     let token = Token {
         s: &bytes[start..start + length],
@@ -203,6 +486,7 @@ fn add_boolean_token<'a, 'b>(
 #[cfg(test)]
 mod test {
     use super::*;
+    #[allow(clippy::ptr_arg)]
     fn compare_tokens(left: &Vec<Token<'_>>, right: &Vec<Token<'_>>) {
         assert_eq!(left.len(), right.len());
         for i in 0..left.len() {
@@ -210,12 +494,13 @@ mod test {
         }
     }
     #[test]
+    #[allow(clippy::byte_char_slices)]
     fn test_tokenize() {
         println!("Testing.");
         for &t in &[b'{', b'}', b'[', b']', b':', b','] {
             let bytes = &[t];
             let s = std::str::from_utf8(bytes).unwrap();
-            let res = generate_tokens(s);
+            let res = generate_tokens(s).unwrap();
             let exp = vec![Token {
                 s: bytes,
                 start: 0,
@@ -224,7 +509,7 @@ mod test {
             compare_tokens(&res, &exp);
         }
         {
-            let res = generate_tokens("{}");
+            let res = generate_tokens("{}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -240,7 +525,7 @@ mod test {
             compare_tokens(&res, &exp);
         }
         {
-            let res = generate_tokens("{     }");
+            let res = generate_tokens("{     }").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -257,7 +542,7 @@ mod test {
         }
 
         {
-            let res = generate_tokens("       {     }");
+            let res = generate_tokens("       {     }").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -274,7 +559,7 @@ mod test {
         }
 
         {
-            let res = generate_tokens("{[]}");
+            let res = generate_tokens("{[]}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -301,7 +586,7 @@ mod test {
         }
 
         {
-            let res = generate_tokens("{  []}");
+            let res = generate_tokens("{  []}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -327,7 +612,7 @@ mod test {
             compare_tokens(&res, &exp);
         }
         {
-            let res = generate_tokens("{  [    ]}");
+            let res = generate_tokens("{  [    ]}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -354,7 +639,7 @@ mod test {
         }
 
         {
-            let res = generate_tokens("{[true]}");
+            let res = generate_tokens("{[true]}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -385,7 +670,7 @@ mod test {
             compare_tokens(&res, &exp);
         }
         {
-            let res = generate_tokens("{[true, false]}");
+            let res = generate_tokens("{[true, false]}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -426,7 +711,7 @@ mod test {
             compare_tokens(&res, &exp);
         }
         {
-            let res = generate_tokens("{[\"k1\":true]}");
+            let res = generate_tokens("{[\"k1\":true]}").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -439,20 +724,10 @@ mod test {
                     _type: TokenType::LeftSquareBracket,
                 },
                 Token {
-                    s: &[b'"'],
+                    s: &[b'"', b'k', b'1', b'"'],
                     start: 2,
-                    _type: TokenType::Quote,
-                },
-                Token {
-                    s: &[b'k', b'1'],
-                    start: 3,
                     _type: TokenType::String,
                 },
-                Token {
-                    s: &[b'"'],
-                    start: 5,
-                    _type: TokenType::Quote,
-                },
                 Token {
                     s: &[b':'],
                     start: 6,
@@ -479,18 +754,14 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::byte_char_slices)]
     fn test_tokenize_split_inclusive() {
         {
-            let res = generate_tokens(r#"""#);
-            let exp = vec![Token {
-                s: &[b'"'],
-                start: 0,
-                _type: TokenType::Quote,
-            }];
-            compare_tokens(&res, &exp);
+            assert_eq!(generate_tokens(r#"""#), Err("unterminated string."));
+            assert_eq!(generate_tokens(r#""a\""#), Err("unterminated string."));
         }
         {
-            let res = generate_tokens("{     }");
+            let res = generate_tokens("{     }").unwrap();
             let exp = vec![
                 Token {
                     s: &[b'{'],
@@ -506,4 +777,96 @@ mod test {
             compare_tokens(&res, &exp);
         }
     }
+
+    #[test]
+    fn test_classes() {
+        let res = generate_tokens("\t\x0c{\r\n ,}").unwrap();
+        let types: Vec<(TokenType, usize)> = res.iter().map(|t| (t._type, t.start)).collect();
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::LeftBracket, 2),
+                (TokenType::Comma, 6),
+                (TokenType::RightBracket, 7)
+            ]
+        );
+        assert_eq!(class(b'"'), Class::Quote);
+        assert_eq!(class(0xff), Class::Other);
+    }
+
+    #[test]
+    fn test_tokenize_bytes() {
+        let res = generate_tokens_from_bytes("[\"0123456789abcdé\"]".as_bytes()).unwrap();
+        assert_eq!(res[1].s, "\"0123456789abcdé\"".as_bytes());
+        assert!(generate_tokens_from_bytes(b"[\"0123456789\xff\"]").is_err());
+        assert!(generate_tokens_from_bytes(b"[\"\xc3\"]").is_err());
+        assert_eq!(
+            generate_tokens_from_bytes(b"[nul\xff]"),
+            Err("invalid utf-8 in input.")
+        );
+        assert!(is_utf8(b"plain ascii text"));
+        assert!(!is_utf8(b"plain ascii text\x80"));
+    }
+
+    #[test]
+    fn test_number_tokens() {
+        for s in &["0", "-1", "-0.5e3", "1E+2", "007"] {
+            let res = generate_tokens(s).unwrap();
+            assert_eq!((res[0]._type, res[0].s), (TokenType::Number, s.as_bytes()));
+        }
+        for s in &["1abc", "-", "1.", "1e", "--1", "1.5.2", "-x"] {
+            assert_eq!(generate_tokens(s), Err("invalid number."), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_string_tokens() {
+        let res = generate_tokens(r#"["a\"b", "c\\"]"#).unwrap();
+        let strings: Vec<&[u8]> = res
+            .iter()
+            .filter(|t| t._type == TokenType::String)
+            .map(|t| t.s)
+            .collect();
+        assert_eq!(strings, vec![&br#""a\"b""#[..], &br#""c\\""#[..]]);
+        assert_eq!(decode_string(br#""plain""#).unwrap(), "plain");
+        assert!(matches!(decode_string(br#""plain""#), Ok(Cow::Borrowed(_))));
+        assert_eq!(
+            decode_string(br#""q\"\\\/\b\f\n\r\t\u00e9\ud83d\ude00""#).unwrap(),
+            "q\"\\/\x08\x0c\n\r\t\u{e9}\u{1f600}"
+        );
+        for s in &[
+            &br#""\x""#[..],
+            br#""\u12""#,
+            br#""\ud83d""#,
+            br#""\udc00""#,
+        ] {
+            assert!(decode_string(s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_trivia_tokens() {
+        let s = "{ // note\n  \"a\": /* x */ [1, true]\n}";
+        let res = generate_tokens_with_trivia(s).unwrap();
+        let joined: Vec<u8> = res.iter().flat_map(|t| t.s.iter().copied()).collect();
+        assert_eq!(joined, s.as_bytes());
+        let comments: Vec<&[u8]> = res
+            .iter()
+            .filter(|t| t._type == TokenType::Comment)
+            .map(|t| t.s)
+            .collect();
+        assert_eq!(comments, vec![&b"// note"[..], b"/* x */"]);
+        let significant: Vec<&[u8]> = res.iter().filter(|t| !t.is_trivia()).map(|t| t.s).collect();
+        let plain: Vec<&[u8]> = generate_tokens("{\"a\": [1, true]}")
+            .unwrap()
+            .iter()
+            .map(|t| t.s)
+            .collect();
+        assert_eq!(significant, plain);
+        assert_eq!(
+            generate_tokens_with_trivia("1 /* open"),
+            Err("unterminated comment.")
+        );
+        assert!(generate_tokens("// no comments").is_err());
+    }
 }
@@ -0,0 +1,69 @@
+pub mod access;
+pub mod annotate;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "async")]
+pub mod async_ndjson;
+#[cfg(feature = "axum")]
+pub mod axum;
+pub mod base64;
+pub mod cache;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod chunked;
+pub mod columns;
+pub mod config;
+pub mod conformance;
+pub mod csv;
+#[cfg(feature = "chrono")]
+pub mod datetime;
+pub mod defaults;
+mod digest;
+pub mod env;
+pub mod event;
+pub mod fixed;
+pub mod format;
+pub mod highlight;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod incremental;
+pub mod jmespath;
+pub mod jq;
+pub mod lexer;
+pub mod lint;
+mod literal;
+pub mod merge;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+pub mod multimap;
+pub mod ndjson;
+mod number;
+pub mod overlay;
+pub mod parser;
+pub mod patch;
+pub mod prelude;
+pub mod preview;
+#[cfg(feature = "prost")]
+pub mod protobuf;
+pub mod query;
+pub mod querystring;
+pub mod reformat;
+pub mod refs;
+pub mod roundtrip;
+pub mod scan;
+#[cfg(feature = "serde")]
+mod serde;
+pub mod serializer;
+pub mod shape;
+pub mod sink;
+pub mod smallstr;
+pub mod stream;
+pub mod tape;
+#[cfg(feature = "toml")]
+pub mod toml;
+pub mod value;
+pub mod xml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+
+/// Find likely mistakes in JSON documents, with the span of each.
+use crate::annotate::Span;
+use crate::lexer::{decode_string, generate_tokens_with_trivia_into, Token, TokenType};
+
+/// Nesting of arrays and objects deeper than this is reported.
+pub const DEEP_NESTING: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+    /// A key already used earlier in the same object.
+    DuplicateKey(String),
+    /// A comma right before a closing bracket.
+    TrailingComma,
+    /// A number starting with `0` followed by more digits, e.g. `007`.
+    LeadingZero,
+    /// A number that cannot be represented exactly by an `f64`: an integer
+    /// beyond 2^53 or more than 17 significant digits.
+    PrecisionLoss,
+    /// An array or object nested deeper than `DEEP_NESTING`.
+    DeepNesting,
+    /// An array holding values of different types, ignoring nulls.
+    MixedArray,
+    /// The document could not be lexed from here on.
+    Syntax(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub span: Span,
+}
+
+// an open array or object.
+struct Frame {
+    start: usize,
+    array: bool,
+    keys: HashSet<String>,
+    // the type of the first non-null element of an array, and whether a
+    // later one differed.
+    first: Option<TokenType>,
+    mixed: bool,
+}
+
+/// Lint the document `s`, which may hold comments, returning the lints in
+/// order of position. Only lexing errors are reported as `Syntax`; other
+/// structural errors are left to the parser.
+pub fn lint(s: &str) -> Vec<Lint> {
+    let mut tokens = vec![];
+    let lexed = generate_tokens_with_trivia_into(s, &mut tokens);
+    let tokens: Vec<&Token> = tokens.iter().filter(|t| !t.is_trivia()).collect();
+    let mut lints = vec![];
+    let mut frames: Vec<Frame> = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1).map(|t| t._type);
+        let is_key = token._type == TokenType::String && next == Some(TokenType::Colon);
+        if !is_key && token._type != TokenType::Colon && token._type != TokenType::Comma {
+            note_element(&mut frames, token._type);
+        }
+        match token._type {
+            TokenType::LeftBracket | TokenType::LeftSquareBracket => {
+                frames.push(Frame {
+                    start: token.start,
+                    array: token._type == TokenType::LeftSquareBracket,
+                    keys: HashSet::new(),
+                    first: None,
+                    mixed: false,
+                });
+                if frames.len() == DEEP_NESTING + 1 {
+                    lints.push(Lint {
+                        kind: LintKind::DeepNesting,
+                        span: span(token),
+                    });
+                }
+            }
+            TokenType::RightBracket | TokenType::RightSquareBracket => {
+                if i > 0 && tokens[i - 1]._type == TokenType::Comma {
+                    lints.push(Lint {
+                        kind: LintKind::TrailingComma,
+                        span: span(tokens[i - 1]),
+                    });
+                }
+                if let Some(frame) = frames.pop() {
+                    if frame.mixed {
+                        let span = Span {
+                            start: frame.start,
+                            end: token.start + 1,
+                        };
+                        lints.push(Lint {
+                            kind: LintKind::MixedArray,
+                            span,
+                        });
+                    }
+                }
+            }
+            TokenType::String if is_key => {
+                if let (Some(frame), Ok(key)) = (frames.last_mut(), decode_string(token.s)) {
+                    if !frame.keys.insert(key.to_string()) {
+                        lints.push(Lint {
+                            kind: LintKind::DuplicateKey(key.into_owned()),
+                            span: span(token),
+                        });
+                    }
+                }
+            }
+            TokenType::Number => {
+                let digits = token.s.strip_prefix(b"-").unwrap_or(token.s);
+                if digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit() {
+                    lints.push(Lint {
+                        kind: LintKind::LeadingZero,
+                        span: span(token),
+                    });
+                }
+                if loses_precision(digits) {
+                    lints.push(Lint {
+                        kind: LintKind::PrecisionLoss,
+                        span: span(token),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Err(e) = lexed {
+        let start = tokens.last().map_or(0, |t| t.start + t.s.len());
+        lints.push(Lint {
+            kind: LintKind::Syntax(e),
+            span: Span {
+                start,
+                end: s.len(),
+            },
+        });
+    }
+    lints.sort_by_key(|l| l.span.start);
+    lints
+}
+
+fn span(token: &Token) -> Span {
+    Span {
+        start: token.start,
+        end: token.start + token.s.len(),
+    }
+}
+
+// record an element of type `t` in the array being read, if any.
+fn note_element(frames: &mut [Frame], t: TokenType) {
+    let t = match t {
+        TokenType::Null | TokenType::RightBracket | TokenType::RightSquareBracket => return,
+        TokenType::LeftBracket => TokenType::RightBracket,
+        t => t,
+    };
+    if let Some(frame) = frames.last_mut().filter(|f| f.array) {
+        match frame.first {
+            None => frame.first = Some(t),
+            Some(first) => frame.mixed |= first != t,
+        }
+    }
+}
+
+// whether the number `s`, without its sign, cannot be held exactly.
+fn loses_precision(s: &[u8]) -> bool {
+    let mantissa_end = s
+        .iter()
+        .position(|&c| c == b'e' || c == b'E')
+        .unwrap_or(s.len());
+    let mantissa = &s[..mantissa_end];
+    if mantissa_end == s.len() && !mantissa.contains(&b'.') {
+        return std::str::from_utf8(s)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .is_none_or(|n| n > 1 << 53);
+    }
+    let digits: Vec<u8> = mantissa
+        .iter()
+        .copied()
+        .filter(u8::is_ascii_digit)
+        .skip_while(|&c| c == b'0')
+        .collect();
+    let significant = digits.iter().rposition(|&c| c != b'0').map_or(0, |p| p + 1);
+    significant > 17
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(s: &str) -> Vec<(LintKind, &str)> {
+        lint(s)
+            .into_iter()
+            .map(|l| (l.kind, &s[l.span.start..l.span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        assert_eq!(kinds(r#"{"a": [1, -2.5], "b": {"c": null}}"#), vec![]);
+        assert_eq!(kinds(r#"{"a": -1}"#), vec![]);
+        assert_eq!(
+            kinds(r#"{"a": 1, "a": [1, "x", null,], "n": -007, "big": 9007199254740993}"#),
+            vec![
+                (LintKind::DuplicateKey("a".to_owned()), r#""a""#),
+                (LintKind::MixedArray, r#"[1, "x", null,]"#),
+                (LintKind::TrailingComma, ","),
+                (LintKind::LeadingZero, "-007"),
+                (LintKind::PrecisionLoss, "9007199254740993"),
+            ]
+        );
+        assert_eq!(
+            kinds("[0.12345678901234567890, 1e400, 100000000000000000000]")
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec![LintKind::PrecisionLoss, LintKind::PrecisionLoss]
+        );
+        let deep = "[".repeat(DEEP_NESTING + 2) + &"]".repeat(DEEP_NESTING + 2);
+        assert_eq!(kinds(&deep), vec![(LintKind::DeepNesting, "[")]);
+        assert_eq!(
+            kinds("[1, tru]"),
+            vec![(LintKind::Syntax("unsupported keyword or number."), " tru]")]
+        );
+        assert_eq!(
+            kinds("[1abc]"),
+            vec![(LintKind::Syntax("invalid number."), "1abc]")]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+/// Rust source text for values, so payloads captured at runtime can be
+/// pasted into tests as fixtures.
+use crate::value::Value;
+
+// the columns a literal may take before it is broken over several lines.
+const WIDTH: usize = 80;
+const INDENT: usize = 4;
+
+impl Value {
+    /// The `serde_json::json!` invocation building this value, with members
+    /// sorted by key. Arrays and objects that do not fit on one line get one
+    /// entry per line.
+    ///
+    /// Integral numbers are written as integers, suffixed with `i64` outside
+    /// the range of `i32` that unsuffixed literals default to, up to 2^53;
+    /// other numbers are written as floats.
+    pub fn to_rust_literal(&self) -> String {
+        let mut out = "json!(".to_owned();
+        write_literal(&mut out, self, 0, "json!(".len() + 1);
+        out.push(')');
+        out
+    }
+}
+
+// write `value` at nesting `depth` into `out`, its line already holding
+// `column` characters plus whatever follows the value.
+fn write_literal(out: &mut String, value: &Value, depth: usize, column: usize) {
+    let flat = flat_literal(value);
+    let (open, close, entries) = match value {
+        Value::Array(v) if !v.is_empty() && column + flat.len() > WIDTH => (
+            '[',
+            ']',
+            v.iter().map(|item| (None, item)).collect::<Vec<_>>(),
+        ),
+        Value::Object(m) if !m.is_empty() && column + flat.len() > WIDTH => {
+            let mut members: Vec<_> = m.iter().map(|(k, v)| (Some(k), v)).collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+            ('{', '}', members)
+        }
+        _ => {
+            out.push_str(&flat);
+            return;
+        }
+    };
+    let indent = " ".repeat((depth + 1) * INDENT);
+    out.push(open);
+    for (key, item) in entries {
+        out.push('\n');
+        out.push_str(&indent);
+        let mut column = indent.len() + 1;
+        if let Some(key) = key {
+            let key = format!("{:?}: ", key);
+            column += key.len();
+            out.push_str(&key);
+        }
+        write_literal(out, item, depth + 1, column);
+        out.push(',');
+    }
+    out.push('\n');
+    out.push_str(&" ".repeat(depth * INDENT));
+    out.push(close);
+}
+
+// `value` on a single line.
+fn flat_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => {
+            format!("{}", *n as i64)
+        }
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= 2f64.powi(53) => {
+            format!("{}i64", *n as i64)
+        }
+        Value::Number(n) => format!("{:?}", n),
+        Value::String(s) => format!("{:?}", s),
+        Value::Array(v) => {
+            let items: Vec<String> = v.iter().map(flat_literal).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(m) => {
+            let mut members: Vec<String> = m
+                .iter()
+                .map(|(k, v)| format!("{:?}: {}", k, flat_literal(v)))
+                .collect();
+            members.sort();
+            format!("{{{}}}", members.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{parse_with, Backend};
+
+    #[test]
+    fn test_to_rust_literal() {
+        let v = parse_with(
+            r#"{"b": [1, -2.5, 1e20, 3000000000, true, null], "a": "q\"\u0001é"}"#,
+            Backend::Events,
+        )
+        .unwrap();
+        assert_eq!(
+            v.to_rust_literal(),
+            r#"json!({"a": "q\"\u{1}é", "b": [1, -2.5, 1e20, 3000000000i64, true, null]})"#
+        );
+        let v = parse_with(
+            r#"{"items": [{"name": "a long enough name", "tags": ["x", "y", "z"]},
+                          {"name": "another long name", "tags": []}], "n": 1}"#,
+            Backend::Events,
+        )
+        .unwrap();
+        let expected = r#"json!({
+    "items": [
+        {"name": "a long enough name", "tags": ["x", "y", "z"]},
+        {"name": "another long name", "tags": []},
+    ],
+    "n": 1,
+})"#;
+        assert_eq!(v.to_rust_literal(), expected);
+    }
+}
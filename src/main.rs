@@ -1,9 +1,707 @@
-#![allow(dead_code)]
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process::ExitCode;
 
-mod lexer;
-mod parser;
-fn main() {
-    println!("Hello, world!");
-    let _v = parser::parse(r#"{"key":"value"}"#);
+/// `pjson`, a command line front end to the library.
+use parsing_rs::csv::{to_csv, CsvOptions};
+use parsing_rs::event::{Event, EventReader};
+use parsing_rs::format::{format_document, FormatOptions};
+use parsing_rs::lexer::{generate_tokens_with_trivia, TokenType};
+use parsing_rs::merge::{deep_merge, merge_patch};
+use parsing_rs::parser::{parse_with, Backend};
+use parsing_rs::preview::preview_from;
+#[cfg(any(feature = "notify", feature = "repl"))]
+use parsing_rs::query::Query;
+use parsing_rs::serializer::{self, Pretty, SerializerOptions};
+use parsing_rs::value::{Value, ValueKind};
 
+const USAGE: &str = "\
+usage: pjson <command> [options] [arguments]
+
+commands:
+  convert [--from FORMAT] --to FORMAT [IN] [OUT]
+      Convert IN (default stdin) to OUT (default stdout). FORMAT is one of
+      json (the default input), msgpack, cbor, yaml, toml (input only) and
+      csv (output only); msgpack, cbor, yaml and toml need the crate
+      features of the same name.
+
+  fmt [--indent N] [--sort-keys] [--write | --check] [FILE...]
+      Reformat the files (default stdin) with N spaces of indentation
+      (default 2), keeping comments, and print them. --sort-keys orders
+      object members by key, which drops comments. --write rewrites the
+      files in place; --check lists the files that are not formatted and
+      fails if there are any.
+
+  paths [--types] [FILE]
+      Print the JSON Pointer of every value below the root of FILE
+      (default stdin) in document order, followed by its type with
+      --types. The document is streamed rather than loaded.
+
+  merge [--patch] BASE OVERLAY...
+      Merge each OVERLAY into BASE in turn and print the result. Objects
+      are merged member by member and anything else is replaced; with
+      --patch the overlays are JSON Merge Patches, whose null members
+      remove members instead.
+
+  head [--items N] [--string-len N] [--depth N] [FILE]
+      Print a preview of FILE (default stdin), streamed so that huge files
+      are never loaded: arrays and objects keep their first N entries
+      (default 10), strings their first N characters (default 80), and
+      containers nested deeper than --depth are elided.
+
+  watch [--expr QUERY] FILE
+      Print FILE, or the values QUERY selects from it, and print them again
+      whenever they change, until interrupted. Needs the `notify` feature.
+
+  repl FILE
+      Explore FILE interactively: each line is a query whose results are
+      previewed, or `:full QUERY` to print them whole. Tab completes the
+      keys of jq paths. Needs the `repl` feature.
+";
+
+// the options and operands of a command line.
+#[derive(Debug, PartialEq)]
+struct Args {
+    options: Vec<(String, Option<String>)>,
+    operands: Vec<String>,
+}
+
+impl Args {
+    // split `args` into options and operands. The options in `valued` take a
+    // value, as `--name value` or `--name=value`, the ones in `switches` do
+    // not. Everything after `--` is an operand.
+    fn parse<I>(args: I, switches: &[&str], valued: &[&str]) -> Result<Args, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Args {
+            options: vec![],
+            operands: vec![],
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.operands.extend(args.by_ref());
+                break;
+            }
+            if !arg.starts_with("--") {
+                parsed.operands.push(arg);
+                continue;
+            }
+            let (name, value) = match arg.find('=') {
+                Some(i) => (arg[2..i].to_owned(), Some(arg[i + 1..].to_owned())),
+                None => (arg[2..].to_owned(), None),
+            };
+            if switches.contains(&name.as_str()) && value.is_none() {
+                parsed.options.push((name, None));
+            } else if valued.contains(&name.as_str()) {
+                let value = match value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => return Err(format!("option --{} needs a value", name)),
+                };
+                parsed.options.push((name, Some(value)));
+            } else {
+                return Err(format!("unknown option {}", arg));
+            }
+        }
+        Ok(parsed)
+    }
+
+    // the value of the last occurrence of option `name`.
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| n == name)
+    }
+
+    // the operands, checking there are at most `max` of them.
+    fn operands(&self, max: usize) -> Result<&[String], String> {
+        if self.operands.len() > max {
+            return Err(format!("unexpected argument {}", self.operands[max]));
+        }
+        Ok(&self.operands)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "yaml")]
+    Yaml,
+    #[cfg(feature = "toml")]
+    Toml,
+    Csv,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Result<Format, String> {
+        Ok(match name {
+            "json" => Format::Json,
+            #[cfg(feature = "msgpack")]
+            "msgpack" => Format::Msgpack,
+            #[cfg(feature = "cbor")]
+            "cbor" => Format::Cbor,
+            #[cfg(feature = "yaml")]
+            "yaml" => Format::Yaml,
+            #[cfg(feature = "toml")]
+            "toml" => Format::Toml,
+            "csv" => Format::Csv,
+            _ if ["msgpack", "cbor", "yaml", "toml"].contains(&name) => {
+                return Err(format!("{} needs pjson built with the `{0}` feature", name))
+            }
+            _ => return Err(format!("unknown format {}", name)),
+        })
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<Value, &'static str> {
+        let text = || std::str::from_utf8(bytes).map_err(|_| "invalid utf-8 in input.");
+        match self {
+            Format::Json => parse_with(text()?, Backend::Events),
+            #[cfg(feature = "msgpack")]
+            Format::Msgpack => parsing_rs::msgpack::from_msgpack(bytes),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => parsing_rs::cbor::from_cbor(bytes),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => parsing_rs::yaml::from_yaml(text()?),
+            #[cfg(feature = "toml")]
+            Format::Toml => parsing_rs::toml::from_toml(text()?),
+            Format::Csv => Err("csv can only be written."),
+        }
+    }
+
+    fn encode(self, value: &Value) -> Result<Vec<u8>, &'static str> {
+        match self {
+            Format::Json => {
+                let mut out = serializer::to_string(value);
+                out.push('\n');
+                Ok(out.into_bytes())
+            }
+            #[cfg(feature = "msgpack")]
+            Format::Msgpack => Ok(parsing_rs::msgpack::to_msgpack(value)),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => Ok(parsing_rs::cbor::to_cbor(value)),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => parsing_rs::yaml::to_yaml(value).map(String::into_bytes),
+            #[cfg(feature = "toml")]
+            Format::Toml => Err("toml can only be read."),
+            Format::Csv => to_csv(value, &CsvOptions::default()).map(String::into_bytes),
+        }
+    }
+}
+
+// `path` opened for reading, with `-` standing for stdin.
+fn open(path: Option<&str>) -> Result<Box<dyn Read>, String> {
+    match path {
+        None | Some("-") => Ok(Box::new(io::stdin())),
+        Some(path) => File::open(path)
+            .map(|f| Box::new(BufReader::new(f)) as Box<dyn Read>)
+            .map_err(|e| format!("cannot open {}: {}", path, e)),
+    }
+}
+
+// `path` created for writing, or `None` for stdout.
+fn create(path: Option<&str>) -> Result<Option<BufWriter<File>>, String> {
+    match path {
+        None | Some("-") => Ok(None),
+        Some(path) => File::create(path)
+            .map(|f| Some(BufWriter::new(f)))
+            .map_err(|e| format!("cannot create {}: {}", path, e)),
+    }
+}
+
+// the text of the file at `path`, with `-` standing for stdin.
+fn read_text(path: &str) -> Result<String, String> {
+    let bytes = read_all(open(Some(path))?)?;
+    String::from_utf8(bytes).map_err(|_| format!("{}: invalid utf-8 in input.", path))
+}
+
+fn read_all(mut reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("cannot read input: {}", e))?;
+    Ok(bytes)
+}
+
+fn write_all(writer: Option<BufWriter<File>>, bytes: &[u8]) -> Result<(), String> {
+    let written = match writer {
+        Some(mut w) => w.write_all(bytes).and_then(|_| w.flush()),
+        None => io::stdout().lock().write_all(bytes),
+    };
+    written.map_err(|e| format!("cannot write output: {}", e))
+}
+
+fn convert(args: Args) -> Result<ExitCode, String> {
+    let from = Format::from_name(args.value("from").unwrap_or("json"))?;
+    let to = Format::from_name(args.value("to").ok_or("convert needs --to FORMAT")?)?;
+    let operands = args.operands(2)?;
+    let input = open(operands.first().map(String::as_str))?;
+    let output = create(operands.get(1).map(String::as_str))?;
+    // JSON goes straight into the binary encoders without being built in
+    // memory; MessagePack can only be streamed into a seekable file.
+    match (from, to, output) {
+        #[cfg(feature = "cbor")]
+        (Format::Json, Format::Cbor, output) => {
+            match output {
+                Some(file) => parsing_rs::cbor::transcode(input, file)?,
+                None => parsing_rs::cbor::transcode(input, io::stdout().lock())?,
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        #[cfg(feature = "msgpack")]
+        (Format::Json, Format::Msgpack, Some(file)) => {
+            parsing_rs::msgpack::transcode(input, file)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        (from, to, output) => {
+            let value = from.decode(&read_all(input)?)?;
+            write_all(output, &to.encode(&value)?)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn fmt(args: Args) -> Result<ExitCode, String> {
+    let indent = match args.value("indent") {
+        Some(n) => n.parse().map_err(|_| format!("invalid indent {}", n))?,
+        None => 2,
+    };
+    let sort_keys = args.switch("sort-keys");
+    let (write, check) = (args.switch("write"), args.switch("check"));
+    if write && check {
+        return Err("--write and --check cannot be combined".to_owned());
+    }
+    if write && args.operands.is_empty() {
+        return Err("--write needs the files to rewrite".to_owned());
+    }
+    let format = |src: &str| {
+        if !sort_keys {
+            return format_document(src, &FormatOptions { indent });
+        }
+        // reordering members means building the document, so comments are
+        // blanked out rather than kept.
+        let mut plain = src.as_bytes().to_vec();
+        for token in generate_tokens_with_trivia(src)? {
+            if token._type == TokenType::Comment {
+                plain[token.start..token.start + token.s.len()].fill(b' ');
+            }
+        }
+        let plain = String::from_utf8(plain).expect("comments are replaced whole");
+        let options = SerializerOptions {
+            pretty: Some(Pretty { indent, width: 0 }),
+            sort_keys: true,
+            ..SerializerOptions::default()
+        };
+        let mut out = serializer::to_string_with(&parse_with(&plain, Backend::Events)?, &options);
+        out.push('\n');
+        Ok(out)
+    };
+    let stdin = ["-".to_owned()];
+    let paths = if args.operands.is_empty() {
+        &stdin[..]
+    } else {
+        &args.operands
+    };
+    let mut unformatted = false;
+    for path in paths {
+        let src = read_text(path)?;
+        let formatted = format(&src).map_err(|e| format!("{}: {}", path, e))?;
+        if check {
+            if formatted != src {
+                println!("{}", path);
+                unformatted = true;
+            }
+        } else if write {
+            if formatted != src {
+                fs::write(path, formatted).map_err(|e| format!("cannot write {}: {}", path, e))?;
+            }
+        } else {
+            write_all(None, formatted.as_bytes())?;
+        }
+    }
+    Ok(if unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn paths(args: Args) -> Result<ExitCode, String> {
+    let types = args.switch("types");
+    let operands = args.operands(1)?;
+    let mut events = EventReader::new(open(operands.first().map(String::as_str))?);
+    let mut out = BufWriter::new(io::stdout().lock());
+    // the pointer to the current value and, for each open container, the
+    // length of the pointer to it and the index of its next element.
+    let mut pointer = String::new();
+    let mut open: Vec<(usize, Option<usize>)> = vec![];
+    while let Some(event) = events.next_event()? {
+        let kind = match event {
+            Event::Key(key) => {
+                pointer.truncate(open.last().map_or(0, |o| o.0));
+                pointer.push('/');
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                continue;
+            }
+            Event::EndArray | Event::EndObject => {
+                open.pop();
+                continue;
+            }
+            Event::StartObject => ValueKind::Object,
+            Event::StartArray => ValueKind::Array,
+            Event::Null => ValueKind::Null,
+            Event::Bool(_) => ValueKind::Bool,
+            Event::Number(_) => ValueKind::Number,
+            Event::String(_) => ValueKind::String,
+        };
+        if let Some((len, Some(index))) = open.last_mut() {
+            pointer.truncate(*len);
+            pointer.push_str(&format!("/{}", index));
+            *index += 1;
+        }
+        let written = match (open.is_empty(), types) {
+            (true, _) => Ok(()),
+            (false, true) => writeln!(out, "{}\t{}", pointer, kind),
+            (false, false) => writeln!(out, "{}", pointer),
+        };
+        written.map_err(|e| format!("cannot write output: {}", e))?;
+        match kind {
+            ValueKind::Object => open.push((pointer.len(), None)),
+            ValueKind::Array => open.push((pointer.len(), Some(0))),
+            _ => {}
+        }
+    }
+    out.flush()
+        .map_err(|e| format!("cannot write output: {}", e))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn merge(args: Args) -> Result<ExitCode, String> {
+    let parse_file = |path: &String| {
+        parse_with(&read_text(path)?, Backend::Events).map_err(|e| format!("{}: {}", path, e))
+    };
+    let (base, overlays) = match args.operands.split_first() {
+        Some((base, overlays)) if !overlays.is_empty() => (base, overlays),
+        _ => return Err("merge needs a base and at least one overlay".to_owned()),
+    };
+    let mut value = parse_file(base)?;
+    for overlay in overlays {
+        let overlay = parse_file(overlay)?;
+        if args.switch("patch") {
+            merge_patch(&mut value, overlay);
+        } else {
+            deep_merge(&mut value, overlay);
+        }
+    }
+    write_all(None, Format::Json.encode(&value)?.as_slice())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn head(args: Args) -> Result<ExitCode, String> {
+    let number = |name: &str, default: usize| match args.value(name) {
+        Some(n) => n.parse().map_err(|_| format!("invalid --{} {}", name, n)),
+        None => Ok(default),
+    };
+    let items = number("items", 10)?;
+    let string_len = number("string-len", 80)?;
+    let depth = number("depth", usize::MAX)?;
+    let operands = args.operands(1)?;
+    let input = open(operands.first().map(String::as_str))?;
+    let value = preview_from(input, items, string_len, depth)?;
+    let options = SerializerOptions {
+        pretty: Some(Pretty::default()),
+        ..SerializerOptions::default()
+    };
+    let mut out = serializer::to_string_with(&value, &options);
+    out.push('\n');
+    write_all(None, out.as_bytes())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+// the values `query` selects from the document at `path`, pretty-printed.
+#[cfg(feature = "notify")]
+fn select(path: &str, query: &Query) -> Result<String, String> {
+    let value =
+        parse_with(&read_text(path)?, Backend::Events).map_err(|e| format!("{}: {}", path, e))?;
+    let options = SerializerOptions {
+        pretty: Some(Pretty::default()),
+        ..SerializerOptions::default()
+    };
+    let mut out = String::new();
+    for selected in query.evaluate(&value)? {
+        out.push_str(&serializer::to_string_with(&selected, &options));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "notify")]
+fn watch(args: Args) -> Result<ExitCode, String> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::path::Path;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let path = match args.operands(1)? {
+        [path] => path,
+        _ => return Err("watch needs the file to watch".to_owned()),
+    };
+    let query = Query::compile(args.value("expr").unwrap_or("."))?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("cannot watch {}: {}", path, e))?;
+    // watch the directory, as editors often replace files rather than write
+    // to them.
+    let file = Path::new(path);
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("cannot watch {}: {}", path, e))?;
+    let mut last = None;
+    loop {
+        // a file being written may not parse yet, so errors are reported
+        // and watching goes on.
+        let shown = select(path, &query);
+        if last.as_ref() != Some(&shown) {
+            match &shown {
+                Ok(out) => write_all(None, out.as_bytes())?,
+                Err(e) => eprintln!("pjson: {}", e),
+            }
+            last = Some(shown);
+        }
+        loop {
+            let event = rx
+                .recv()
+                .map_err(|_| "file watcher stopped".to_owned())?
+                .map_err(|e| format!("cannot watch {}: {}", path, e))?;
+            let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == file.file_name());
+            if changed {
+                break;
+            }
+        }
+        // let a burst of events from one save settle.
+        while rx.recv_timeout(Duration::from_millis(50)).is_ok() {}
+    }
+}
+
+// the start of the object key being typed at `pos` in a jq path, and the
+// keys of `root` that complete it, written as jq does.
+#[cfg(feature = "repl")]
+fn complete_path(root: &Value, line: &str, pos: usize) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before
+        .rfind(|c: char| c.is_whitespace() || "|,(".contains(c))
+        .map_or(0, |i| i + 1);
+    let path = &before[start..];
+    let dot = match path.rfind('.') {
+        Some(dot) if path.starts_with('.') => dot,
+        _ => return (pos, vec![]),
+    };
+    let (parent, partial) = (&path[..dot], &path[dot + 1..]);
+    let parents = Query::jq(if parent.is_empty() { "." } else { parent })
+        .and_then(|q| q.evaluate(root))
+        .unwrap_or_default();
+    let mut keys: Vec<String> = parents
+        .iter()
+        .filter_map(|v| match v {
+            Value::Object(m) => Some(m.keys()),
+            _ => None,
+        })
+        .flatten()
+        .filter(|k| k.starts_with(partial))
+        .map(|k| {
+            let mut chars = k.chars();
+            let plain = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if plain {
+                k.clone()
+            } else {
+                serializer::to_string(&Value::String(k.clone()))
+            }
+        })
+        .collect();
+    keys.sort();
+    keys.dedup();
+    (start + dot + 1, keys)
+}
+
+#[cfg(feature = "repl")]
+fn repl(args: Args) -> Result<ExitCode, String> {
+    use rustyline::completion::Completer;
+    use rustyline::error::ReadlineError;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::validate::Validator;
+    use rustyline::{Context, Editor, Helper};
+
+    struct PathHelper(Value);
+
+    impl Completer for PathHelper {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            line: &str,
+            pos: usize,
+            _: &Context<'_>,
+        ) -> rustyline::Result<(usize, Vec<String>)> {
+            Ok(complete_path(&self.0, line, pos))
+        }
+    }
+
+    impl Hinter for PathHelper {
+        type Hint = String;
+    }
+
+    impl Highlighter for PathHelper {}
+
+    impl Validator for PathHelper {}
+
+    impl Helper for PathHelper {}
+
+    let path = match args.operands(1)? {
+        [path] => path,
+        _ => return Err("repl needs the file to explore".to_owned()),
+    };
+    let root =
+        parse_with(&read_text(path)?, Backend::Events).map_err(|e| format!("{}: {}", path, e))?;
+    let mut editor = Editor::new().map_err(|e| format!("cannot start the prompt: {}", e))?;
+    editor.set_helper(Some(PathHelper(root)));
+    let options = SerializerOptions {
+        pretty: Some(Pretty::default()),
+        ..SerializerOptions::default()
+    };
+    loop {
+        let line = match editor.readline("pjson> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(ExitCode::SUCCESS),
+            Err(e) => return Err(format!("cannot read the prompt: {}", e)),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        let (expr, full) = match line.strip_prefix(":full") {
+            Some(expr) => (expr.trim(), true),
+            None if line == ":q" || line == ":quit" => return Ok(ExitCode::SUCCESS),
+            None => (line, false),
+        };
+        let root = &editor.helper().expect("the helper is set").0;
+        let results = match Query::compile(expr).and_then(|q| q.evaluate(root)) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                continue;
+            }
+        };
+        for value in results {
+            let value = if full {
+                value
+            } else {
+                value.preview(20, 120, 4)
+            };
+            println!("{}", serializer::to_string_with(&value, &options));
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    let result = match command.as_deref() {
+        Some("convert") => Args::parse(args, &[], &["from", "to"]).and_then(convert),
+        Some("fmt") => {
+            Args::parse(args, &["sort-keys", "write", "check"], &["indent"]).and_then(fmt)
+        }
+        Some("paths") => Args::parse(args, &["types"], &[]).and_then(paths),
+        Some("merge") => Args::parse(args, &["patch"], &[]).and_then(merge),
+        Some("head") => Args::parse(args, &[], &["items", "string-len", "depth"]).and_then(head),
+        #[cfg(feature = "notify")]
+        Some("watch") => Args::parse(args, &[], &["expr"]).and_then(watch),
+        #[cfg(not(feature = "notify"))]
+        Some("watch") => Err("watch needs pjson built with the `notify` feature".to_owned()),
+        #[cfg(feature = "repl")]
+        Some("repl") => Args::parse(args, &[], &[]).and_then(repl),
+        #[cfg(not(feature = "repl"))]
+        Some("repl") => Err("repl needs pjson built with the `repl` feature".to_owned()),
+        Some("help") | Some("--help") | Some("-h") => {
+            print!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(command) => Err(format!("unknown command {}\n\n{}", command, USAGE)),
+        None => Err(USAGE.to_owned()),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("pjson: {}", e.trim_end());
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Args, String> {
+        let args = args.iter().map(|a| a.to_string());
+        Args::parse(args, &["check"], &["to", "indent"])
+    }
+
+    #[test]
+    fn test_args() {
+        let args =
+            parse_args(&["--to", "cbor", "in", "--check", "--indent=4", "--", "--to"]).unwrap();
+        assert_eq!(args.value("to"), Some("cbor"));
+        assert_eq!(args.value("indent"), Some("4"));
+        assert!(args.switch("check"));
+        assert_eq!(args.operands, vec!["in", "--to"]);
+        assert!(args.operands(1).is_err());
+        assert!(parse_args(&["--to"]).is_err());
+        assert!(parse_args(&["--check=yes"]).is_err());
+        assert!(parse_args(&["--width", "80"]).is_err());
+        assert_eq!(Format::from_name("csv"), Ok(Format::Csv));
+        assert!(Format::from_name("xml").is_err());
+    }
+
+    #[cfg(feature = "repl")]
+    #[test]
+    fn test_complete_path() {
+        let root = parse_with(
+            r#"{"items": [{"name": "a", "id": 1}, {"note": 2}], "in": {"x y": 1}}"#,
+            Backend::Events,
+        )
+        .unwrap();
+        let complete = |line: &str| complete_path(&root, line, line.len());
+        assert_eq!(
+            complete(".i"),
+            (1, vec!["in".to_owned(), "items".to_owned()])
+        );
+        assert_eq!(
+            complete(".items[].n"),
+            (9, vec!["name".to_owned(), "note".to_owned()])
+        );
+        assert_eq!(complete("length, .in."), (12, vec!["\"x y\"".to_owned()]));
+        assert_eq!(complete("length"), (6, vec![]));
+        assert_eq!(complete(".nope.x"), (6, vec![]));
+    }
 }
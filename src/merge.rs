@@ -0,0 +1,90 @@
+/// Combine layered documents, such as a base configuration and the overlays
+/// adjusting it per environment.
+use crate::value::Value;
+
+/// Merge `overlay` into `value`: members of objects on both sides are merged
+/// recursively, and anything else in `overlay`, `null` and arrays included,
+/// replaces what it is merged into.
+pub fn deep_merge(value: &mut Value, overlay: Value) {
+    match (value, overlay) {
+        (Value::Object(m), Value::Object(o)) => {
+            for (key, v) in o {
+                match m.get_mut(&key) {
+                    Some(present) => deep_merge(present, v),
+                    None => {
+                        m.insert(key, v);
+                    }
+                }
+            }
+        }
+        (value, overlay) => *value = overlay,
+    }
+}
+
+/// Apply the JSON Merge Patch (RFC 7386) `patch` to `value`: like
+/// `deep_merge`, except that a `null` member of the patch removes the member
+/// of `value` rather than setting it to `null`.
+pub fn merge_patch(value: &mut Value, patch: Value) {
+    let patch = match patch {
+        Value::Object(p) => p,
+        patch => {
+            *value = patch;
+            return;
+        }
+    };
+    if !value.is_object() {
+        *value = Value::Object(Default::default());
+    }
+    if let Value::Object(m) = value {
+        for (key, v) in patch {
+            if v.is_null() {
+                m.remove(&key);
+            } else {
+                merge_patch(m.entry(key).or_insert(Value::Null), v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_with, Backend};
+
+    fn p(s: &str) -> Value {
+        parse_with(s, Backend::Events).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = p(r#"{"db": {"host": "db1", "port": 5432}, "tags": ["a"], "debug": true}"#);
+        let overlay =
+            p(r#"{"db": {"port": 6432, "pool": {"size": 4}}, "tags": ["b"], "debug": null}"#);
+        let mut merged = base.clone();
+        deep_merge(&mut merged, overlay.clone());
+        assert_eq!(
+            merged,
+            p(
+                r#"{"db": {"host": "db1", "port": 6432, "pool": {"size": 4}},
+                  "tags": ["b"], "debug": null}"#
+            )
+        );
+        let mut patched = base;
+        merge_patch(&mut patched, overlay);
+        assert_eq!(
+            patched,
+            p(r#"{"db": {"host": "db1", "port": 6432, "pool": {"size": 4}}, "tags": ["b"]}"#)
+        );
+
+        // examples from RFC 7386.
+        let mut v = p(r#"{"a": "b"}"#);
+        merge_patch(&mut v, p(r#"{"a": {"bb": {"ccc": null}}}"#));
+        assert_eq!(v, p(r#"{"a": {"bb": {}}}"#));
+        let mut v = p(r#"["a", "b"]"#);
+        merge_patch(&mut v, p(r#"{"a": "c"}"#));
+        assert_eq!(v, p(r#"{"a": "c"}"#));
+        let mut v = p(r#"{"e": null}"#);
+        merge_patch(&mut v, p(r#"{"a": 1}"#));
+        assert_eq!(v, p(r#"{"e": null, "a": 1}"#));
+    }
+}
@@ -0,0 +1,192 @@
+/// MessagePack encoding and decoding of `Value`s. Enabled by the `msgpack`
+/// feature.
+///
+/// Integral numbers are written as MessagePack integers and other numbers as
+/// 64-bit floats. On decoding, integers become numbers and binary data
+/// becomes an array of byte values; extension types and non-string map keys
+/// are rejected.
+use crate::event::{Event, EventReader};
+use crate::value::Value;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Encode `value` as MessagePack.
+pub fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut out = vec![];
+    rmpv::encode::write_value(&mut out, &to_rmpv(value)).expect("writing to a vec cannot fail");
+    out
+}
+
+/// Decode a single MessagePack value spanning all of `bytes`.
+pub fn from_msgpack(bytes: &[u8]) -> Result<Value, &'static str> {
+    let mut rest = bytes;
+    let value = rmpv::decode::read_value(&mut rest).map_err(|_| "cannot decode msgpack.")?;
+    if !rest.is_empty() {
+        return Err("trailing bytes after msgpack.");
+    }
+    from_rmpv(value)
+}
+
+/// Convert the JSON document in `reader` to MessagePack written to `writer`,
+/// one parse event at a time, so the document is never held in memory.
+///
+/// MessagePack puts the length of an array or map before its contents, so
+/// each container is written with a 32-bit length that is filled in by
+/// seeking back once the container ends; scalars are encoded as
+/// `to_msgpack` encodes them.
+pub fn transcode<R: Read, W: Write + Seek>(reader: R, mut writer: W) -> Result<(), &'static str> {
+    let mut events = EventReader::new(reader);
+    let mut pos = writer
+        .stream_position()
+        .map_err(|_| "failed to write output.")?;
+    // the offset of each open container's header, its length so far and
+    // whether it is a map.
+    let mut open: Vec<(u64, u64, bool)> = vec![];
+    let mut item = vec![];
+    while let Some(event) = events.next_event()? {
+        // a key starts a map entry, and anything but a key or an end starts
+        // an array element.
+        match (&event, open.last_mut()) {
+            (Event::Key(_), Some((_, len, true))) => *len += 1,
+            (Event::Key(_), _) | (Event::EndArray, _) | (Event::EndObject, _) => {}
+            (_, Some((_, len, false))) => *len += 1,
+            _ => {}
+        }
+        item.clear();
+        let scalar = match event {
+            Event::StartArray | Event::StartObject => {
+                let is_map = event == Event::StartObject;
+                open.push((pos, 0, is_map));
+                item.push(if is_map { 0xdf } else { 0xdd });
+                item.extend_from_slice(&[0; 4]);
+                None
+            }
+            Event::EndArray | Event::EndObject => {
+                let (start, len, _) = open.pop().expect("containers are balanced");
+                let len = u32::try_from(len).map_err(|_| "container too long for msgpack.")?;
+                writer
+                    .seek(SeekFrom::Start(start + 1))
+                    .and_then(|_| writer.write_all(&len.to_be_bytes()))
+                    .and_then(|_| writer.seek(SeekFrom::Start(pos)))
+                    .map_err(|_| "failed to write output.")?;
+                None
+            }
+            Event::Key(s) | Event::String(s) => Some(rmpv::Value::from(s)),
+            Event::Null => Some(rmpv::Value::Nil),
+            Event::Bool(b) => Some(rmpv::Value::Boolean(b)),
+            Event::Number(n) => Some(to_rmpv(&Value::Number(n))),
+        };
+        if let Some(scalar) = scalar {
+            rmpv::encode::write_value(&mut item, &scalar).expect("writing to a vec cannot fail");
+        }
+        writer
+            .write_all(&item)
+            .map_err(|_| "failed to write output.")?;
+        pos += item.len() as u64;
+    }
+    writer.flush().map_err(|_| "failed to write output.")
+}
+
+fn to_rmpv(value: &Value) -> rmpv::Value {
+    match value {
+        Value::Null => rmpv::Value::Nil,
+        Value::Bool(b) => rmpv::Value::Boolean(*b),
+        Value::Number(n) if n.fract() == 0.0 && *n >= -(2f64.powi(63)) && *n < 2f64.powi(64) => {
+            if *n < 0.0 {
+                rmpv::Value::from(*n as i64)
+            } else {
+                rmpv::Value::from(*n as u64)
+            }
+        }
+        Value::Number(n) => rmpv::Value::F64(*n),
+        Value::String(s) => rmpv::Value::from(s.as_str()),
+        Value::Array(v) => rmpv::Value::Array(v.iter().map(to_rmpv).collect()),
+        Value::Object(m) => rmpv::Value::Map(
+            m.iter()
+                .map(|(k, v)| (rmpv::Value::from(k.as_str()), to_rmpv(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn from_rmpv(value: rmpv::Value) -> Result<Value, &'static str> {
+    Ok(match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(b) => Value::Bool(b),
+        rmpv::Value::Integer(i) => Value::Number(i.as_f64().ok_or("invalid msgpack integer.")?),
+        rmpv::Value::F32(f) => Value::Number(f as f64),
+        rmpv::Value::F64(f) => Value::Number(f),
+        rmpv::Value::String(s) => {
+            Value::String(s.into_str().ok_or("invalid utf-8 in msgpack string.")?)
+        }
+        rmpv::Value::Binary(b) => {
+            Value::Array(b.into_iter().map(|b| Value::Number(b as f64)).collect())
+        }
+        rmpv::Value::Array(v) => Value::Array(
+            v.into_iter()
+                .map(from_rmpv)
+                .collect::<Result<Vec<Value>, &'static str>>()?,
+        ),
+        rmpv::Value::Map(entries) => {
+            let mut m = std::collections::HashMap::with_capacity(entries.len());
+            for (k, v) in entries {
+                let k = match k {
+                    rmpv::Value::String(s) => {
+                        s.into_str().ok_or("invalid utf-8 in msgpack string.")?
+                    }
+                    _ => return Err("msgpack map keys must be strings."),
+                };
+                m.insert(k, from_rmpv(v)?);
+            }
+            Value::Object(m)
+        }
+        rmpv::Value::Ext(..) => return Err("unsupported msgpack extension type."),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse, parse_with, Backend};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_msgpack() {
+        let v = parse(r#"{"a": [1, 0.5, true, null], "b": "text", "c": {}}"#).unwrap();
+        assert_eq!(from_msgpack(&to_msgpack(&v)).unwrap(), v);
+        assert_eq!(to_msgpack(&Value::Number(1.0)), vec![0x01]);
+        assert_eq!(to_msgpack(&Value::Number(-1.0)), vec![0xff]);
+        let big = Value::Number(-(2f64.powi(40)));
+        assert_eq!(from_msgpack(&to_msgpack(&big)).unwrap(), big);
+        assert_eq!(to_msgpack(&Value::Number(0.5))[0], 0xcb);
+
+        // bin 8 of two bytes.
+        assert_eq!(
+            from_msgpack(&[0xc4, 0x02, 0x00, 0xff]).unwrap(),
+            Value::Array(vec![Value::Number(0.0), Value::Number(255.0)])
+        );
+        assert!(from_msgpack(&[0x01, 0x02]).is_err());
+        assert!(from_msgpack(&[0x81, 0x01, 0x01]).is_err());
+        assert!(from_msgpack(&[0xd4, 0x01, 0x00]).is_err());
+        assert!(from_msgpack(&[]).is_err());
+    }
+
+    #[test]
+    fn test_transcode() {
+        let doc = r#"{"a": [1, -2, 0.5, true, null], "b": "text", "c": {"d": []}}"#;
+        let mut out = Cursor::new(vec![]);
+        transcode(doc.as_bytes(), &mut out).unwrap();
+        assert_eq!(
+            from_msgpack(out.get_ref()).unwrap(),
+            parse_with(doc, Backend::Events).unwrap()
+        );
+        let mut out = Cursor::new(vec![]);
+        transcode("[1, [\"x\"]]".as_bytes(), &mut out).unwrap();
+        assert_eq!(
+            out.into_inner(),
+            vec![0xdd, 0, 0, 0, 2, 0x01, 0xdd, 0, 0, 0, 1, 0xa1, b'x']
+        );
+        assert!(transcode("[1, 2".as_bytes(), Cursor::new(vec![])).is_err());
+        assert!(transcode("[1] 2".as_bytes(), Cursor::new(vec![])).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+/// Objects that keep every member, duplicate keys included, for protocols
+/// that give repeated keys a meaning.
+use crate::sink::{parse_into, ValueSink};
+use crate::value::Value;
+
+/// Like `Value`, but objects are lists of members in document order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MultiValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<MultiValue>),
+    Object(Vec<(String, MultiValue)>),
+}
+
+impl MultiValue {
+    /// Every value of the member `key` of an object, in order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a MultiValue> {
+        let members = match self {
+            MultiValue::Object(members) => &members[..],
+            _ => &[],
+        };
+        members
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Collapse into a `Value`, the last of duplicate keys winning as it does
+    /// in `parse`.
+    pub fn into_value(self) -> Value {
+        match self {
+            MultiValue::Null => Value::Null,
+            MultiValue::Bool(b) => Value::Bool(b),
+            MultiValue::Number(n) => Value::Number(n),
+            MultiValue::String(s) => Value::String(s),
+            MultiValue::Array(v) => {
+                Value::Array(v.into_iter().map(MultiValue::into_value).collect())
+            }
+            MultiValue::Object(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(k, v)| (k, v.into_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Objects get their members in the map's iteration order.
+impl From<Value> for MultiValue {
+    fn from(value: Value) -> MultiValue {
+        match value {
+            Value::Null => MultiValue::Null,
+            Value::Bool(b) => MultiValue::Bool(b),
+            Value::Number(n) => MultiValue::Number(n),
+            Value::String(s) => MultiValue::String(s),
+            Value::Array(v) => MultiValue::Array(v.into_iter().map(MultiValue::from).collect()),
+            Value::Object(m) => {
+                MultiValue::Object(m.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+/// Parse `s` keeping every member of its objects.
+pub fn parse_multimap(s: &str) -> Result<MultiValue, &'static str> {
+    parse_into(s.as_bytes(), MultiValueBuilder::default())
+}
+
+/// The sink building `MultiValue`s.
+#[derive(Debug, Default)]
+pub struct MultiValueBuilder {
+    // open containers with the key of the value being read in each object.
+    stack: Vec<(MultiValue, Option<String>)>,
+    root: Option<MultiValue>,
+}
+
+impl MultiValueBuilder {
+    pub fn new() -> MultiValueBuilder {
+        MultiValueBuilder::default()
+    }
+
+    fn value(&mut self, value: MultiValue) -> Result<(), &'static str> {
+        match self.stack.last_mut() {
+            Some((MultiValue::Array(v), _)) => v.push(value),
+            Some((MultiValue::Object(members), key)) => {
+                members.push((key.take().ok_or("key expected.")?, value));
+            }
+            Some(_) => unreachable!("only containers are stacked"),
+            None if self.root.is_none() => self.root = Some(value),
+            None => return Err("trailing string after json."),
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), &'static str> {
+        let (value, _) = self.stack.pop().ok_or("unbalanced container.")?;
+        self.value(value)
+    }
+}
+
+impl ValueSink for MultiValueBuilder {
+    type Output = MultiValue;
+
+    fn start_object(&mut self) -> Result<(), &'static str> {
+        self.stack.push((MultiValue::Object(vec![]), None));
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), &'static str> {
+        self.end()
+    }
+
+    fn start_array(&mut self) -> Result<(), &'static str> {
+        self.stack.push((MultiValue::Array(vec![]), None));
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), &'static str> {
+        self.end()
+    }
+
+    fn key(&mut self, key: String) -> Result<(), &'static str> {
+        match self.stack.last_mut() {
+            Some((MultiValue::Object(_), k)) => {
+                *k = Some(key);
+                Ok(())
+            }
+            _ => Err("key outside of an object."),
+        }
+    }
+
+    fn null(&mut self) -> Result<(), &'static str> {
+        self.value(MultiValue::Null)
+    }
+
+    fn bool(&mut self, b: bool) -> Result<(), &'static str> {
+        self.value(MultiValue::Bool(b))
+    }
+
+    fn number(&mut self, n: f64) -> Result<(), &'static str> {
+        self.value(MultiValue::Number(n))
+    }
+
+    fn string(&mut self, s: String) -> Result<(), &'static str> {
+        self.value(MultiValue::String(s))
+    }
+
+    fn finish(self) -> Result<MultiValue, &'static str> {
+        if !self.stack.is_empty() {
+            return Err("unexpected end of input.");
+        }
+        self.root.ok_or("unexpected end of input.")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_parse_multimap() {
+        let s = r#"{"filter": "a", "sort": 1, "filter": {"x": [true]}, "filter": null}"#;
+        let v = parse_multimap(s).unwrap();
+        let filters: Vec<&MultiValue> = v.get_all("filter").collect();
+        assert_eq!(filters.len(), 3);
+        assert_eq!(filters[0], &MultiValue::String("a".to_owned()));
+        assert_eq!(filters[2], &MultiValue::Null);
+        if let MultiValue::Object(members) = &v {
+            let keys: Vec<&str> = members.iter().map(|(k, _)| k.as_str()).collect();
+            assert_eq!(keys, vec!["filter", "sort", "filter", "filter"]);
+        }
+        assert_eq!(v.into_value(), parse(s).unwrap());
+        assert_eq!(MultiValue::Null.get_all("a").count(), 0);
+        assert!(parse_multimap("[1,]").is_err());
+    }
+}
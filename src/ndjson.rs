@@ -0,0 +1,248 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Newline-delimited JSON utilities.
+use crate::event::{Event, EventReader};
+use crate::parser::{parse_with, Backend};
+use crate::serializer;
+use crate::value::Value;
+
+/// Stream the array at the root of the document in `reader` and write its
+/// elements as newline-delimited JSON, `chunk_size` elements per writer.
+///
+/// `create` is called with the chunk number (from 0) whenever a new chunk
+/// starts. Only one element is held in memory at a time. Return the number of
+/// chunks written.
+pub fn split_array<R, W, F>(
+    reader: R,
+    chunk_size: usize,
+    mut create: F,
+) -> Result<usize, &'static str>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(usize) -> std::io::Result<W>,
+{
+    if chunk_size == 0 {
+        return Err("chunk size must be positive.");
+    }
+    let mut events = EventReader::new(reader);
+    if events.next_event()? != Some(Event::StartArray) {
+        return Err("expect array");
+    }
+    let mut chunks = 0;
+    let mut count = 0;
+    let mut writer: Option<W> = None;
+    loop {
+        let event = match events.next_event()? {
+            Some(Event::EndArray) => break,
+            Some(event) => event,
+            None => return Err("unexpected end of input."),
+        };
+        let value = events.build_value(event)?;
+        if count % chunk_size == 0 {
+            if let Some(mut w) = writer.take() {
+                w.flush().map_err(|_| "failed to write output.")?;
+            }
+            writer = Some(create(chunks).map_err(|_| "failed to create output.")?);
+            chunks += 1;
+        }
+        let w = writer.as_mut().unwrap();
+        serializer::to_writer(w, &value)?;
+        w.write_all(b"\n").map_err(|_| "failed to write output.")?;
+        count += 1;
+    }
+    if let Some(mut w) = writer {
+        w.flush().map_err(|_| "failed to write output.")?;
+    }
+    // reject anything after the array.
+    events.next_event()?;
+    Ok(chunks)
+}
+
+/// Like `split_array`, writing chunk `i` to the file `{prefix}-{i:05}.ndjson`.
+/// Return the paths of the files written.
+pub fn split_array_to_files<R: Read>(
+    reader: R,
+    chunk_size: usize,
+    prefix: &Path,
+) -> Result<Vec<PathBuf>, &'static str> {
+    let mut paths = vec![];
+    split_array(reader, chunk_size, |i| {
+        let mut name = prefix.as_os_str().to_owned();
+        name.push(format!("-{:05}.ndjson", i));
+        let path = PathBuf::from(name);
+        let file = File::create(&path)?;
+        paths.push(path);
+        Ok(BufWriter::new(file))
+    })?;
+    Ok(paths)
+}
+
+/// A line `parse_log_lines` could not parse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    /// The line number, from 1.
+    pub line_no: usize,
+    /// The start of the line, at most `SNIPPET_LEN` characters.
+    pub snippet: String,
+    pub error: &'static str,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} ({})",
+            self.line_no, self.error, self.snippet
+        )
+    }
+}
+
+/// The most characters of a bad line kept in `LineError::snippet`.
+pub const SNIPPET_LEN: usize = 80;
+
+/// Iterator over the lines read by `parse_log_lines`.
+pub struct LogLines<R: Read> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+    line_no: usize,
+    parsed: usize,
+    skipped: usize,
+    blank: usize,
+    done: bool,
+}
+
+/// Parse one JSON document per line of `reader`, yielding an error for each
+/// line that is not one and carrying on with the next. Blank lines are
+/// passed over. A read error ends the iteration after yielding it.
+pub fn parse_log_lines<R: Read>(reader: R) -> LogLines<R> {
+    LogLines {
+        reader: BufReader::new(reader),
+        line: vec![],
+        line_no: 0,
+        parsed: 0,
+        skipped: 0,
+        blank: 0,
+        done: false,
+    }
+}
+
+impl<R: Read> LogLines<R> {
+    /// The number of lines parsed so far.
+    pub fn parsed(&self) -> usize {
+        self.parsed
+    }
+
+    /// The number of lines that could not be parsed so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// The number of blank lines passed over so far.
+    pub fn blank(&self) -> usize {
+        self.blank
+    }
+
+    fn error(&mut self, error: &'static str) -> LineError {
+        self.skipped += 1;
+        let line = String::from_utf8_lossy(&self.line);
+        LineError {
+            line_no: self.line_no,
+            snippet: line.trim_end().chars().take(SNIPPET_LEN).collect(),
+            error,
+        }
+    }
+}
+
+impl<R: Read> Iterator for LogLines<R> {
+    type Item = Result<Value, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            self.line_no += 1;
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(_) => {
+                    self.done = true;
+                    return Some(Err(self.error("failed to read input.")));
+                }
+            }
+            let line = match std::str::from_utf8(&self.line) {
+                Ok(line) => line.trim(),
+                Err(_) => return Some(Err(self.error("invalid utf-8 in line."))),
+            };
+            if line.is_empty() {
+                self.blank += 1;
+                continue;
+            }
+            return Some(match parse_with(line, Backend::Events) {
+                Ok(value) => {
+                    self.parsed += 1;
+                    Ok(value)
+                }
+                Err(e) => Err(self.error(e)),
+            });
+        }
+        self.done = true;
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_split_array() {
+        let prefix = std::env::temp_dir().join(format!("ndjson-test-{}", std::process::id()));
+        let doc = r#"[1, {"a": [true]}, "x", null, 2.5]"#;
+        let paths = split_array_to_files(doc.as_bytes(), 2, &prefix).unwrap();
+        let contents: Vec<String> = paths
+            .iter()
+            .map(|p| fs::read_to_string(p).unwrap())
+            .collect();
+        for p in &paths {
+            fs::remove_file(p).unwrap();
+        }
+        assert_eq!(
+            contents,
+            vec!["1\n{\"a\":[true]}\n", "\"x\"\nnull\n", "2.5\n"]
+        );
+        assert!(paths[0].to_str().unwrap().ends_with("-00000.ndjson"));
+    }
+
+    #[test]
+    fn test_split_array_errors() {
+        let sink = |_| Ok(std::io::sink());
+        assert_eq!(split_array("[]".as_bytes(), 3, sink), Ok(0));
+        assert!(split_array("[1]".as_bytes(), 0, sink).is_err());
+        assert!(split_array(r#"{"a": 1}"#.as_bytes(), 1, sink).is_err());
+        assert!(split_array("[1, 2".as_bytes(), 1, sink).is_err());
+        assert!(split_array("[1] [2]".as_bytes(), 1, sink).is_err());
+    }
+
+    #[test]
+    fn test_parse_log_lines() {
+        let log = b"{\"a\": 1}\r\n\nINFO started\n[true]\n\xff\n{\"b\":";
+        let mut lines = parse_log_lines(&log[..]);
+        let items: Vec<Result<Value, LineError>> = lines.by_ref().collect();
+        assert_eq!(items.len(), 5);
+        assert!(items[0].is_ok());
+        let bad: Vec<(usize, &str)> = items
+            .iter()
+            .filter_map(|item| item.as_ref().err())
+            .map(|e| (e.line_no, e.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            bad,
+            vec![(3, "INFO started"), (5, "\u{fffd}"), (6, "{\"b\":")]
+        );
+        assert_eq!((lines.parsed(), lines.skipped(), lines.blank()), (2, 3, 1));
+    }
+}
@@ -0,0 +1,135 @@
+// the powers of ten that are exact as f64.
+const POW10: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// Parse `b` if it follows the JSON number grammar
+/// `-?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?`.
+///
+/// Most numbers in real documents have at most 15 significant digits and a
+/// small exponent. Their mantissa and power of ten are then both exact as
+/// `f64`, so a single multiplication or division gives the correctly rounded
+/// result (Clinger's fast path). Other numbers fall back to the standard
+/// library's correctly rounding parser.
+pub(crate) fn parse_number(b: &[u8]) -> Option<f64> {
+    let mut i = 0;
+    let negative = b.first() == Some(&b'-');
+    if negative {
+        i += 1;
+    }
+    let mut mantissa: u64 = 0;
+    // set when digits beyond what the mantissa can hold were dropped.
+    let mut truncated = false;
+    let mut exponent: i64 = 0;
+    let mut digit = |d: u8, mantissa: &mut u64| {
+        if *mantissa < 1_000_000_000_000_000_000 {
+            *mantissa = *mantissa * 10 + (d - b'0') as u64;
+            true
+        } else {
+            truncated |= d != b'0';
+            false
+        }
+    };
+
+    match b.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            while let Some(&d) = b.get(i).filter(|d| d.is_ascii_digit()) {
+                if !digit(d, &mut mantissa) {
+                    exponent += 1;
+                }
+                i += 1;
+            }
+        }
+        _ => return None,
+    }
+    if b.get(i) == Some(&b'.') {
+        i += 1;
+        let start = i;
+        while let Some(&d) = b.get(i).filter(|d| d.is_ascii_digit()) {
+            if digit(d, &mut mantissa) {
+                exponent -= 1;
+            }
+            i += 1;
+        }
+        if i == start {
+            return None;
+        }
+    }
+    if matches!(b.get(i), Some(b'e') | Some(b'E')) {
+        i += 1;
+        let sign = match b.get(i) {
+            Some(b'-') => {
+                i += 1;
+                -1
+            }
+            Some(b'+') => {
+                i += 1;
+                1
+            }
+            _ => 1,
+        };
+        let start = i;
+        let mut e: i64 = 0;
+        while let Some(&d) = b.get(i).filter(|d| d.is_ascii_digit()) {
+            e = (e * 10 + (d - b'0') as i64).min(1_000_000);
+            i += 1;
+        }
+        if i == start {
+            return None;
+        }
+        exponent += sign * e;
+    }
+    if i != b.len() {
+        return None;
+    }
+
+    if !truncated && mantissa <= 1 << 53 && (-22..=22).contains(&exponent) {
+        let m = mantissa as f64;
+        let v = if exponent < 0 {
+            m / POW10[-exponent as usize]
+        } else {
+            m * POW10[exponent as usize]
+        };
+        return Some(if negative { -v } else { v });
+    }
+    // the grammar is checked above, so the text is ASCII and parses.
+    std::str::from_utf8(b).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        for s in &[
+            "0",
+            "-0",
+            "42",
+            "3.14159",
+            "-2.5e-3",
+            "1E22",
+            "1e23",
+            "123456789012345678901234567890",
+            "0.1",
+            "0.000000000000000000000000000001",
+            "9007199254740993",
+            "2.2250738585072014e-308",
+            "1.7976931348623157e308",
+            "1e400",
+            "1e-400",
+            "12345678901234567890e-10",
+        ] {
+            let expected: f64 = s.parse().unwrap();
+            let got = parse_number(s.as_bytes()).unwrap();
+            assert_eq!(got.to_bits(), expected.to_bits(), "{}", s);
+        }
+        for s in &[
+            "", "-", "01", "1.", ".5", "+1", "1e", "1e+", "0x1", "1.5.2", "--1", "1 ",
+        ] {
+            assert_eq!(parse_number(s.as_bytes()), None, "{}", s);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+
+/// Copy-on-write edits recorded on top of a shared, immutable document.
+use crate::access::{array_index, lookup, pointer_tokens, remove_at, set_at};
+use crate::value::Value;
+
+// one recorded edit; `value` is `None` for a removal.
+#[derive(Debug, Clone)]
+struct Change {
+    path: Vec<String>,
+    value: Option<Value>,
+    // whether the removal shifted the later elements of an array.
+    shifts: bool,
+}
+
+/// Edits addressed by JSON pointer over a borrowed `base`, which is never
+/// modified or copied as a whole. Reads of untouched parts borrow from the
+/// base; only the subtrees holding edits are copied, and only when read.
+#[derive(Debug, Clone)]
+pub struct Overlay<'a> {
+    base: &'a Value,
+    changes: Vec<Change>,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(base: &'a Value) -> Overlay<'a> {
+        Overlay {
+            base,
+            changes: vec![],
+        }
+    }
+
+    pub fn base(&self) -> &'a Value {
+        self.base
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Set the value at `pointer`, adding an object member, or appending
+    /// when an array index is `-` or one past the end. The parent must exist.
+    pub fn set(&mut self, pointer: &str, value: Value) -> Result<(), &'static str> {
+        let mut path = pointer_tokens(pointer).ok_or("pointer not found.")?;
+        if let Some(last) = path.pop() {
+            let last = match self.len_at(&path)? {
+                None => last,
+                // record `-` as the index it stands for.
+                Some(len) => array_index(&last, len)
+                    .ok_or("pointer not found.")?
+                    .to_string(),
+            };
+            path.push(last);
+        }
+        self.changes.push(Change {
+            path,
+            value: Some(value),
+            shifts: false,
+        });
+        Ok(())
+    }
+
+    /// Remove the value at `pointer`, which must exist.
+    pub fn remove(&mut self, pointer: &str) -> Result<(), &'static str> {
+        let mut path = pointer_tokens(pointer).ok_or("pointer not found.")?;
+        let last = path.pop().ok_or("cannot remove the root.")?;
+        let shifts = match self.len_at(&path)? {
+            None => false,
+            Some(len) => {
+                last.parse::<usize>()
+                    .ok()
+                    .filter(|&i| i < len)
+                    .ok_or("pointer not found.")?;
+                true
+            }
+        };
+        path.push(last);
+        if !shifts && self.get_tokens(&path, true).is_none() {
+            return Err("pointer not found.");
+        }
+        self.changes.push(Change {
+            path,
+            value: None,
+            shifts,
+        });
+        Ok(())
+    }
+
+    /// The value at `pointer` with the edits applied, borrowed from the base
+    /// when no edit touches it.
+    pub fn get(&self, pointer: &str) -> Option<Cow<'a, Value>> {
+        self.get_tokens(&pointer_tokens(pointer)?, true)
+    }
+
+    /// A copy of the whole document with the edits applied.
+    pub fn to_value(&self) -> Value {
+        let mut value = self.base.clone();
+        for change in &self.changes {
+            apply(&mut value, &change.path, change);
+        }
+        value
+    }
+
+    // `None` for an object at `tokens`, its length for an array, and an error
+    // for anything else. Edits below an object cannot change its type, so
+    // objects are not copied to find it.
+    fn len_at(&self, tokens: &[String]) -> Result<Option<usize>, &'static str> {
+        match self.get_tokens(tokens, false).as_deref() {
+            Some(Value::Object(_)) => Ok(None),
+            Some(Value::Array(_)) => match self.get_tokens(tokens, true).as_deref() {
+                Some(Value::Array(a)) => Ok(Some(a.len())),
+                _ => unreachable!(),
+            },
+            _ => Err("pointer not found."),
+        }
+    }
+
+    // the value at `tokens`, leaving out the edits made below it unless
+    // `descendants`.
+    fn get_tokens(&self, tokens: &[String], descendants: bool) -> Option<Cow<'a, Value>> {
+        // the shallowest node on the way to `tokens` that an edit changed:
+        // an edited ancestor, `tokens` itself when a descendant was edited,
+        // or an array whose elements a removal shifted.
+        let depth = self
+            .changes
+            .iter()
+            .filter_map(|c| {
+                let parent = &c.path[..c.path.len().saturating_sub(1)];
+                if tokens.starts_with(&c.path) {
+                    Some(c.path.len())
+                } else if descendants && c.path.starts_with(tokens) {
+                    Some(tokens.len())
+                } else if c.shifts && tokens.starts_with(parent) {
+                    Some(parent.len())
+                } else {
+                    None
+                }
+            })
+            .min();
+        let depth = match depth {
+            Some(depth) => depth,
+            None => return lookup(self.base, tokens).map(Cow::Borrowed),
+        };
+        // copy that node from the last edit replacing it or from the base,
+        // then replay the edits made below it since.
+        let prefix = &tokens[..depth];
+        let last = self
+            .changes
+            .iter()
+            .rposition(|c| prefix.starts_with(&c.path));
+        let mut value = match last {
+            Some(i) => {
+                let change = &self.changes[i];
+                lookup(change.value.as_ref()?, &prefix[change.path.len()..])?.clone()
+            }
+            None => lookup(self.base, prefix)?.clone(),
+        };
+        for change in &self.changes[last.map_or(0, |i| i + 1)..] {
+            if change.path.starts_with(prefix)
+                && (descendants
+                    || tokens.starts_with(&change.path[..change.path.len().saturating_sub(1)]))
+            {
+                apply(&mut value, &change.path[depth..], change);
+            }
+        }
+        take(value, &tokens[depth..]).map(Cow::Owned)
+    }
+}
+
+// edits were checked against the merged view when recorded, so they apply.
+fn apply(value: &mut Value, path: &[String], change: &Change) {
+    let _ = match &change.value {
+        Some(new) => set_at(value, path, new.clone()).map(drop),
+        None => remove_at(value, path).map(drop),
+    };
+}
+
+// move the value at `tokens` out of `value`.
+fn take(value: Value, tokens: &[String]) -> Option<Value> {
+    tokens.iter().try_fold(value, |v, token| match v {
+        Value::Object(mut m) => m.remove(token),
+        Value::Array(mut a) => {
+            let i = token.parse::<usize>().ok().filter(|&i| i < a.len())?;
+            Some(a.swap_remove(i))
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_overlay() {
+        let base = parse(r#"{"a": {"b": 1, "c": [1, 2, 3]}, "d": "x"}"#).unwrap();
+        let mut overlay = Overlay::new(&base);
+        overlay.set("/a/b", Value::Number(2.0)).unwrap();
+        overlay.remove("/a/c/0").unwrap();
+        overlay.set("/a/c/-", Value::Number(4.0)).unwrap();
+        overlay.set("/e", Value::Bool(true)).unwrap();
+        assert_eq!(overlay.remove("/a/z"), Err("pointer not found."));
+        assert_eq!(overlay.set("/d/0", Value::Null), Err("pointer not found."));
+
+        assert!(matches!(overlay.get("/d"), Some(Cow::Borrowed(_))));
+        assert_eq!(
+            overlay.get("/a/b").unwrap().into_owned(),
+            Value::Number(2.0)
+        );
+        assert_eq!(
+            overlay.get("/a/c").unwrap().into_owned(),
+            parse("[2, 3, 4]").unwrap()
+        );
+        assert_eq!(
+            overlay.get("/a/c/2").unwrap().into_owned(),
+            Value::Number(4.0)
+        );
+        assert_eq!(overlay.get("/a/c/3"), None);
+        assert_eq!(
+            overlay.to_value(),
+            parse(r#"{"a": {"b": 2, "c": [2, 3, 4]}, "d": "x", "e": true}"#).unwrap()
+        );
+        assert_eq!(base.pointer("/a/b"), Some(&Value::Number(1.0)));
+    }
+}
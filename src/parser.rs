@@ -1,43 +1,581 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::time::Instant;
 
 /// A parser to parse JSON from string written with top-down parsing method.
-use crate::lexer::{generate_tokens, Token, TokenType};
-#[derive(Debug, PartialEq)]
-pub enum Value {
-    Null,
-    Bool(bool),
-    Number(f64),
-    String(String),
-    Array(Vec<Value>),
-    Object(HashMap<String, Value>),
+use crate::event::EventReader;
+use crate::lexer::{
+    decode_string, generate_tokens, generate_tokens_checked, generate_tokens_from_bytes,
+    generate_tokens_into, Token, TokenType,
+};
+use crate::number::parse_number;
+use crate::smallstr::CompactValue;
+use crate::value::GenericValue;
+pub use crate::value::Value;
+
+/// The parser implementations behind `parse_with`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// The token-based parser of `parse`.
+    Tokens,
+    /// The streaming `EventReader`, which accepts all of JSON.
+    Events,
+}
+
+/// Parse `s` into a `Value` with the chosen backend.
+pub fn parse_with(s: &str, backend: Backend) -> Result<Value, &'static str> {
+    match backend {
+        Backend::Tokens => parse(s),
+        Backend::Events => {
+            let mut reader = EventReader::new(s.as_bytes());
+            let value = reader.next_value()?.ok_or("unexpected end of input.")?;
+            match reader.next_event()? {
+                None => Ok(value),
+                Some(_) => Err("trailing string after json."),
+            }
+        }
+    }
+}
+
+/// `s.parse::<Value>()` parses with the events backend, which accepts all of
+/// JSON.
+impl FromStr for Value {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Value, &'static str> {
+        parse_with(s, Backend::Events)
+    }
+}
+
+/// An error from `parse_located` or `parse_bytes_located`, with where in the
+/// input it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: &'static str,
+    /// The byte offset the error was found at.
+    pub offset: usize,
+    /// The line of `offset`, from 1.
+    pub line: usize,
+    /// The column of `offset` in characters, from 1.
+    pub column: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// With the `miette` feature, the error is a diagnostic labeling its offset;
+/// attach the input with `miette::Report::with_source_code` to show it.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for ParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("parsing::parse_error"))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let label = miette::LabeledSpan::at_offset(self.offset, self.message);
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+/// Parse `s` with the events backend, failing with a `ParseError` locating
+/// the error rather than a bare message. This is the located form of
+/// `parse_with` with `Backend::Events`; the token backend does not keep
+/// offsets, so its errors cannot be located.
+pub fn parse_located(s: &str) -> Result<Value, ParseError> {
+    parse_events_located(s.as_bytes())
 }
 
+/// Like `parse_bytes`, failing with a `ParseError` locating the error. Only
+/// UTF-8 is accepted, optionally after a byte order mark, which offsets
+/// count; invalid UTF-8 in a string is located just past the string.
+pub fn parse_bytes_located(b: &[u8]) -> Result<Value, ParseError> {
+    match b {
+        [0xef, 0xbb, 0xbf, rest @ ..] => parse_events_located(rest).map_err(|e| ParseError {
+            offset: e.offset + 3,
+            ..e
+        }),
+        b => parse_events_located(b),
+    }
+}
+
+fn parse_events_located(b: &[u8]) -> Result<Value, ParseError> {
+    let mut reader = EventReader::new(b);
+    let parsed = reader
+        .next_value()
+        .and_then(|v| v.ok_or("unexpected end of input."))
+        .and_then(|v| match reader.next_event()? {
+            None => Ok(v),
+            Some(_) => Err("trailing string after json."),
+        });
+    parsed.map_err(|message| {
+        // back up to the start of a character, counting characters by
+        // their first bytes so invalid UTF-8 still has a column.
+        let is_first = |c: &u8| c & 0xc0 != 0x80;
+        let mut offset = reader.offset().min(b.len());
+        while offset < b.len() && offset > 0 && !is_first(&b[offset]) {
+            offset -= 1;
+        }
+        let before = &b[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map_or(0, |i| i + 1);
+        ParseError {
+            message,
+            offset,
+            line: before.iter().filter(|&&c| c == b'\n').count() + 1,
+            column: before[line_start..].iter().filter(|c| is_first(c)).count() + 1,
+        }
+    })
+}
+
+/// Parse `s` with the events backend, failing with "parse timed out." if
+/// parsing is still running at `deadline`.
+pub fn parse_with_deadline(s: &str, deadline: Instant) -> Result<Value, &'static str> {
+    let mut reader = EventReader::new(s.as_bytes());
+    reader.set_deadline(deadline);
+    let value = reader.next_value()?.ok_or("unexpected end of input.")?;
+    match reader.next_event()? {
+        None => Ok(value),
+        Some(_) => Err("trailing string after json."),
+    }
+}
+
+/// Parse the JSON text `s` into a `Value`.
 pub fn parse(s: &str) -> Result<Value, &'static str> {
     // first tokenize the string into tokens
-    let tokens = generate_tokens(s);
+    let tokens = generate_tokens(s)?;
     // then construct the Json value from the tokens.
-    let (value, tokens) = parse_value(&tokens)?;
+    parse_tokens(&tokens)
+}
+
+/// Choices for `parse_with_options` beyond the grammar itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    /// The parser to use, `Backend::Tokens` as in `parse` by default.
+    pub backend: Backend,
+    /// Skip what `skip_junk_prefix` does before parsing.
+    pub skip_prefix: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            backend: Backend::Tokens,
+            skip_prefix: false,
+        }
+    }
+}
+
+/// Parse `s` into a `Value` as `options` choose.
+pub fn parse_with_options(s: &str, options: &ParseOptions) -> Result<Value, &'static str> {
+    let s = if options.skip_prefix {
+        skip_junk_prefix(s)
+    } else {
+        s
+    };
+    parse_with(s, options.backend)
+}
+
+// prefixes APIs put before JSON responses so they cannot be loaded as
+// scripts from other sites, longest first.
+const XSSI_PREFIXES: [&str; 4] = [")]}',", ")]}'", "while(1);", "for(;;);"];
+
+/// The rest of `s` after an anti-XSSI prefix such as `)]}'` or `while(1);`,
+/// and after any other text before the first `{` or `[` when the rest does
+/// not already start with a JSON value.
+pub fn skip_junk_prefix(s: &str) -> &str {
+    let s = s.trim_start();
+    let s = XSSI_PREFIXES
+        .iter()
+        .find_map(|prefix| s.strip_prefix(prefix))
+        .unwrap_or(s)
+        .trim_start();
+    let starts_value = match s.chars().next() {
+        Some('{' | '[' | '"' | '-' | '0'..='9') => true,
+        _ => ["true", "false", "null"].iter().any(|l| s.starts_with(l)),
+    };
+    match s.find(['{', '[']) {
+        Some(start) if !starts_value => &s[start..],
+        _ => s,
+    }
+}
+
+/// Parse JSON text given as bytes, failing on invalid UTF-8 instead of
+/// requiring a separate validation scan up front.
+///
+/// A leading byte order mark selects the encoding: UTF-8 text after one is
+/// parsed as without it, and UTF-16 text goes to `parse_utf16le` or
+/// `parse_utf16be`.
+pub fn parse_bytes(b: &[u8]) -> Result<Value, &'static str> {
+    let b = match b {
+        [0xef, 0xbb, 0xbf, rest @ ..] => rest,
+        [0xff, 0xfe, rest @ ..] => return parse_utf16le(rest),
+        [0xfe, 0xff, rest @ ..] => return parse_utf16be(rest),
+        b => b,
+    };
+    let tokens = generate_tokens_from_bytes(b)?;
+    parse_tokens(&tokens)
+}
+
+/// Parse UTF-16LE text, without a byte order mark, with the events backend.
+/// The text is transcoded to UTF-8 as it is parsed rather than up front.
+pub fn parse_utf16le(b: &[u8]) -> Result<Value, &'static str> {
+    parse_utf16(b, u16::from_le_bytes)
+}
+
+/// Like `parse_utf16le`, for UTF-16BE text.
+pub fn parse_utf16be(b: &[u8]) -> Result<Value, &'static str> {
+    parse_utf16(b, u16::from_be_bytes)
+}
+
+fn parse_utf16(b: &[u8], unit: fn([u8; 2]) -> u16) -> Result<Value, &'static str> {
+    let mut input = Utf16Reader {
+        bytes: b,
+        unit,
+        carry: vec![],
+        invalid: false,
+    };
+    let mut reader = EventReader::new(&mut input);
+    let parsed = reader
+        .next_value()
+        .and_then(|v| v.ok_or("unexpected end of input."))
+        .and_then(|v| match reader.next_event()? {
+            None => Ok(v),
+            Some(_) => Err("trailing string after json."),
+        });
+    match parsed {
+        Err(_) if input.invalid => Err("invalid utf-16 in input."),
+        parsed => parsed,
+    }
+}
+
+// UTF-16 text read as UTF-8, noting whether it was invalid.
+struct Utf16Reader<'a> {
+    bytes: &'a [u8],
+    unit: fn([u8; 2]) -> u16,
+    // the rest of a character that did not fit in the last read.
+    carry: Vec<u8>,
+    invalid: bool,
+}
+
+impl Utf16Reader<'_> {
+    fn next_unit(&mut self) -> Option<u16> {
+        if self.bytes.len() < 2 {
+            return None;
+        }
+        let unit = (self.unit)([self.bytes[0], self.bytes[1]]);
+        self.bytes = &self.bytes[2..];
+        Some(unit)
+    }
+
+    fn next_char(&mut self) -> Option<Result<char, ()>> {
+        let unit = match self.next_unit() {
+            Some(unit) => unit,
+            // an odd byte is left over.
+            None if !self.bytes.is_empty() => return Some(Err(())),
+            None => return None,
+        };
+        let c = match unit {
+            0xd800..=0xdbff => match self.next_unit() {
+                Some(low @ 0xdc00..=0xdfff) => {
+                    0x10000 + ((unit as u32 - 0xd800) << 10) + (low as u32 - 0xdc00)
+                }
+                _ => return Some(Err(())),
+            },
+            unit => unit as u32,
+        };
+        Some(char::from_u32(c).ok_or(()))
+    }
+}
+
+impl Read for Utf16Reader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.carry.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.carry[..n]);
+        self.carry.drain(..n);
+        let mut n = n;
+        while n < buf.len() {
+            let c = match self.next_char() {
+                Some(Ok(c)) => c,
+                Some(Err(())) => {
+                    self.invalid = true;
+                    return Err(io::Error::other("invalid utf-16 in input."));
+                }
+                None => break,
+            };
+            let mut utf8 = [0; 4];
+            let utf8 = c.encode_utf8(&mut utf8).as_bytes();
+            let fits = utf8.len().min(buf.len() - n);
+            buf[n..n + fits].copy_from_slice(&utf8[..fits]);
+            self.carry.extend_from_slice(&utf8[fits..]);
+            n += fits;
+        }
+        Ok(n)
+    }
+}
+
+/// Parse like `parse` into a `CompactValue`, whose keys and strings of up to
+/// 22 bytes are stored inline instead of in their own allocations.
+pub fn parse_compact(s: &str) -> Result<CompactValue, &'static str> {
+    let tokens = generate_tokens(s)?;
+    parse_tokens(&tokens)
+}
+
+/// The deepest nesting of arrays and objects `parse_checked` accepts.
+pub const MAX_DEPTH: usize = 128;
+
+/// Parse like `parse`, but also reject documents nested deeper than
+/// `MAX_DEPTH`, so that no input can exhaust the stack: every input yields
+/// `Ok` or `Err` and none panics.
+pub fn parse_checked(s: &str) -> Result<Value, &'static str> {
+    parse_with_limits(s, &ParseLimits::default())
+}
+
+/// Bounds on untrusted input, checked by `parse_with_limits`,
+/// `EventReader::set_limits` and `ChunkedParser::set_limits` as the input is
+/// read, before any value exceeding them is built. Lengths of keys and
+/// strings are in bytes of the source text between the quotes, escapes
+/// included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseLimits {
+    /// The deepest nesting of arrays and objects, `MAX_DEPTH` by default.
+    pub max_depth: usize,
+    /// The longest object key.
+    pub max_key_len: usize,
+    /// The longest string value; keys are bounded by `max_key_len` only.
+    pub max_string_len: usize,
+    /// The most elements in one array.
+    pub max_array_len: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> ParseLimits {
+        ParseLimits {
+            max_depth: MAX_DEPTH,
+            max_key_len: usize::MAX,
+            max_string_len: usize::MAX,
+            max_array_len: usize::MAX,
+        }
+    }
+}
+
+// the open arrays and objects of a document being read, checked against
+// `ParseLimits` as each value starts.
+#[derive(Debug)]
+pub(crate) struct LimitCheck {
+    limits: ParseLimits,
+    // the element counts of the open arrays, `None` for objects.
+    open: Vec<Option<usize>>,
+}
+
+impl LimitCheck {
+    pub(crate) fn new(limits: ParseLimits) -> LimitCheck {
+        LimitCheck {
+            limits,
+            open: vec![],
+        }
+    }
+
+    // a value starts, counting it as an element of the innermost array.
+    pub(crate) fn value(&mut self) -> Result<(), &'static str> {
+        if let Some(Some(count)) = self.open.last_mut() {
+            *count += 1;
+            if *count > self.limits.max_array_len {
+                return Err("array too long.");
+            }
+        }
+        Ok(())
+    }
+
+    // the value that started opens an array or object.
+    pub(crate) fn open(&mut self, array: bool) -> Result<(), &'static str> {
+        if self.open.len() == self.limits.max_depth {
+            return Err("json nested too deeply.");
+        }
+        self.open.push(if array { Some(0) } else { None });
+        Ok(())
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.open.pop();
+    }
+
+    pub(crate) fn in_object(&self) -> bool {
+        self.open.last() == Some(&None)
+    }
+
+    // a key or string of `len` bytes has been read so far.
+    pub(crate) fn string(&self, len: usize, key: bool) -> Result<(), &'static str> {
+        match key {
+            true if len > self.limits.max_key_len => Err("key too long."),
+            false if len > self.limits.max_string_len => Err("string too long."),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse like `parse`, but fail as soon as the document exceeds one of
+/// `limits`, so payloads built to exhaust memory or the stack are rejected.
+/// Each token is checked as it is lexed, so the rest of an oversized
+/// document is never lexed.
+pub fn parse_with_limits(s: &str, limits: &ParseLimits) -> Result<Value, &'static str> {
+    let mut check = LimitCheck::new(*limits);
+    // whether the next string in the innermost object is a key.
+    let mut expect_key = false;
+    let tokens = generate_tokens_checked(s, |token| match token._type {
+        TokenType::LeftBracket | TokenType::LeftSquareBracket => {
+            check.value()?;
+            expect_key = token._type == TokenType::LeftBracket;
+            check.open(!expect_key)
+        }
+        TokenType::RightBracket | TokenType::RightSquareBracket => {
+            check.close();
+            Ok(())
+        }
+        TokenType::Comma => {
+            expect_key = check.in_object();
+            Ok(())
+        }
+        TokenType::String if expect_key => {
+            expect_key = false;
+            check.string(token.s.len() - 2, true)
+        }
+        TokenType::String => {
+            check.value()?;
+            check.string(token.s.len() - 2, false)
+        }
+        TokenType::Colon => Ok(()),
+        _ => check.value(),
+    })?;
+    parse_tokens(&tokens)
+}
+
+/// Counters describing one `parse_with_metrics` run. Allocations are not
+/// counted, as that needs a counting global allocator.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParseMetrics {
+    /// Bytes of input scanned.
+    pub bytes: usize,
+    /// Tokens produced by the lexer.
+    pub tokens: usize,
+    /// Values built, counting containers and everything in them but not keys.
+    pub nodes: usize,
+    /// The deepest nesting of arrays and objects.
+    pub peak_depth: usize,
+}
+
+/// Parse like `parse` and also return counters describing the run.
+pub fn parse_with_metrics(s: &str) -> Result<(Value, ParseMetrics), &'static str> {
+    let tokens = generate_tokens(s)?;
+    let value = parse_tokens(&tokens)?;
+    let mut metrics = ParseMetrics {
+        bytes: s.len(),
+        tokens: tokens.len(),
+        ..ParseMetrics::default()
+    };
+    let mut depth = 0;
+    for token in &tokens {
+        match token._type {
+            TokenType::LeftBracket | TokenType::LeftSquareBracket => {
+                depth += 1;
+                metrics.peak_depth = metrics.peak_depth.max(depth);
+                metrics.nodes += 1;
+            }
+            TokenType::RightBracket | TokenType::RightSquareBracket => depth -= 1,
+            // every key is followed by a colon, cancelling its string.
+            TokenType::Colon => metrics.nodes -= 1,
+            TokenType::Comma => {}
+            _ => metrics.nodes += 1,
+        }
+    }
+    Ok((value, metrics))
+}
+
+/// A parser that keeps its token storage between documents, so parsing many
+/// documents does not allocate and grow a new token buffer for each one.
+#[derive(Debug, Default)]
+pub struct Parser {
+    tokens: Vec<Token<'static>>,
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser::default()
+    }
+
+    /// Parse `s` like `parse`, reusing the token buffer.
+    pub fn parse(&mut self, s: &str) -> Result<Value, &'static str> {
+        // the empty buffer can hold tokens borrowing from `s`.
+        let mut tokens: Vec<Token<'_>> = std::mem::take(&mut self.tokens);
+        let value = generate_tokens_into(s, &mut tokens).and_then(|_| parse_tokens(&tokens));
+        tokens.clear();
+        let mut tokens = std::mem::ManuallyDrop::new(tokens);
+        // SAFETY: the allocation comes from a `Vec` of the same element
+        // layout, as lifetimes do not change layout, with the same capacity,
+        // and the length of 0 leaves no token borrowing from `s`.
+        self.tokens = unsafe {
+            Vec::from_raw_parts(
+                tokens.as_mut_ptr().cast::<Token<'static>>(),
+                0,
+                tokens.capacity(),
+            )
+        };
+        value
+    }
+}
+
+// build the value of a whole document from its tokens.
+fn parse_tokens<S: Str>(tokens: &[Token<'_>]) -> Result<GenericValue<f64, S>, &'static str> {
+    let (value, tokens) = parse_value(tokens)?;
     if !tokens.is_empty() {
         return Err("trailing string after json.");
     }
     Ok(value)
 }
 
+// the type of the first token, if any.
+fn peek(tokens: &[Token<'_>]) -> Option<TokenType> {
+    tokens.first().map(|t| t._type)
+}
+
+// the string types the token parser can build keys and strings of.
+trait Str: for<'x> From<&'x str> + Eq + Hash {}
+
+impl<T: for<'x> From<&'x str> + Eq + Hash> Str for T {}
+
 // construct a value from the tokens and return the value and any left tokens.
-fn parse_value<'a, 'b>(tokens: &'a [Token<'b>]) -> Result<(Value, &'a [Token<'b>]), &'static str> {
+fn parse_value<'a, 'b, S: Str>(
+    tokens: &'a [Token<'b>],
+) -> Result<(GenericValue<f64, S>, &'a [Token<'b>]), &'static str> {
     if tokens.is_empty() {
-        return Ok((Value::String("".to_owned()), tokens));
+        return Err("unexpected end of input.");
     }
     match tokens[0]._type {
         TokenType::LeftBracket => parse_object(tokens),
         TokenType::LeftSquareBracket => parse_array(tokens),
-        TokenType::Quote => parse_string(tokens),
-        TokenType::Null => Ok((Value::Null, &tokens[1..])),
-        TokenType::Boolean => Ok((Value::Bool(tokens[0].s == "true".as_bytes()), &tokens[1..])),
+        TokenType::String => parse_string(tokens),
+        TokenType::Null => Ok((GenericValue::Null, &tokens[1..])),
+        TokenType::Boolean => Ok((
+            GenericValue::Bool(tokens[0].s == "true".as_bytes()),
+            &tokens[1..],
+        )),
         // if it is number, for simplicity, we use f64 always
         TokenType::Number => {
-            if let Ok(num) = std::str::from_utf8(tokens[0].s).unwrap().parse::<f64>() {
-                Ok((Value::Number(num), &tokens[1..]))
+            if let Some(num) = parse_number(tokens[0].s) {
+                Ok((GenericValue::Number(num), &tokens[1..]))
             } else {
                 Err("cannot parse the string into the numbers.")
             }
@@ -46,84 +584,80 @@ fn parse_value<'a, 'b>(tokens: &'a [Token<'b>]) -> Result<(Value, &'a [Token<'b>
     }
 }
 
-fn parse_object<'a, 'b>(tokens: &'a [Token<'b>]) -> Result<(Value, &'a [Token<'b>]), &'static str> {
+fn parse_object<'a, 'b, S: Str>(
+    tokens: &'a [Token<'b>],
+) -> Result<(GenericValue<f64, S>, &'a [Token<'b>]), &'static str> {
     if tokens.len() < 2 || tokens[0]._type != TokenType::LeftBracket {
         return Err("Not a object.");
     }
     // empty object
     if tokens[1]._type == TokenType::RightBracket {
-        return Ok((Value::Object(HashMap::new()), &tokens[2..]));
+        return Ok((GenericValue::Object(HashMap::new()), &tokens[2..]));
     }
     let mut m = HashMap::new();
     let mut tokens = &tokens[1..];
     loop {
-        if let (Value::String(s), token) = parse_string(tokens)? {
-            if token[0]._type != TokenType::Colon {
+        if let (GenericValue::String(s), token) = parse_string(tokens)? {
+            if peek(token) != Some(TokenType::Colon) {
                 return Err("colon expected.");
             }
             let (value, token) = parse_value(&token[1..])?;
             m.insert(s, value);
             // if there is no more key value pair to deal with.
-            if token[0]._type != TokenType::Comma {
+            if peek(token) != Some(TokenType::Comma) {
                 tokens = token;
                 break;
             }
             tokens = &token[1..];
         }
     }
-    if tokens.is_empty() || tokens[0]._type != TokenType::RightBracket {
+    if peek(tokens) != Some(TokenType::RightBracket) {
         return Err("right bracket expected.");
     }
-    Ok((Value::Object(m), &tokens[1..]))
+    Ok((GenericValue::Object(m), &tokens[1..]))
 }
 
-fn parse_array<'a, 'b>(tokens: &'a [Token<'b>]) -> Result<(Value, &'a [Token<'b>]), &'static str> {
+fn parse_array<'a, 'b, S: Str>(
+    tokens: &'a [Token<'b>],
+) -> Result<(GenericValue<f64, S>, &'a [Token<'b>]), &'static str> {
     if tokens.len() < 2 || tokens[0]._type != TokenType::LeftSquareBracket {
         return Err("expect array");
     }
     let mut vec = vec![];
     let mut tokens = &tokens[1..];
-
+    if peek(tokens) == Some(TokenType::RightSquareBracket) {
+        return Ok((GenericValue::Array(vec), &tokens[1..]));
+    }
     loop {
-        if tokens.is_empty() {
-            return Err("array expected.");
-        }
-        if tokens[0]._type == TokenType::RightSquareBracket {
-            break;
-        }
         let (value, token) = parse_value(tokens)?;
         vec.push(value);
-        if token.is_empty() {
-            return Err("array expected.");
-        }
-        if token[0]._type == TokenType::Comma {
-            tokens = &token[1..];
-        } else {
-            tokens = token;
+        match peek(token) {
+            Some(TokenType::Comma) => tokens = &token[1..],
+            Some(TokenType::RightSquareBracket) => {
+                return Ok((GenericValue::Array(vec), &token[1..]))
+            }
+            _ => return Err("comma or closing bracket expected."),
         }
     }
-    Ok((Value::Array(vec), &tokens[1..]))
 }
 
-fn parse_string<'a, 'b>(tokens: &'a [Token<'b>]) -> Result<(Value, &'a [Token<'b>]), &'static str> {
-    if tokens.len() < 3
-        || tokens[0]._type != TokenType::Quote
-        || tokens[2]._type != TokenType::Quote
-        || tokens[1]._type != TokenType::String
-    {
-        return Err("expected string");
+fn parse_string<'a, 'b, S: Str>(
+    tokens: &'a [Token<'b>],
+) -> Result<(GenericValue<f64, S>, &'a [Token<'b>]), &'static str> {
+    match tokens.first() {
+        Some(t) if t._type == TokenType::String => {
+            let s = decode_string(t.s)?;
+            Ok((GenericValue::String(S::from(&s)), &tokens[1..]))
+        }
+        _ => Err("expected string"),
     }
-    Ok((
-        Value::String(std::str::from_utf8(tokens[1].s).unwrap().to_owned()),
-        &tokens[3..],
-    ))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::collections::HashMap;
     #[test]
+    #[allow(clippy::vec_init_then_push)]
     fn test_parsing() {
         {
             let v = parse("{}");
@@ -137,6 +671,14 @@ mod test {
             let exp = Value::Object(m);
             assert_eq!(exp, v.unwrap());
         }
+        {
+            let v = parse(r#"{"k\"ey": "a\\b\u00e9"}"#);
+            let mut m = HashMap::new();
+            m.insert("k\"ey".to_owned(), Value::String("a\\b\u{e9}".to_owned()));
+            let exp = Value::Object(m);
+            assert_eq!(exp, v.unwrap());
+            assert!(parse(r#"["\x"]"#).is_err());
+        }
         {
             let v = parse(r#"{"key": null}"#);
             let mut m = HashMap::new();
@@ -270,5 +812,253 @@ mod test {
             let exp = Value::Object(m);
             assert_eq!(exp, v.unwrap());
         }
+        {
+            let v = parse("42");
+            assert_eq!(Value::Number(42.0), v.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_checked() {
+        for s in &[
+            "",
+            "\"",
+            "\"ab",
+            "\"\u{e9}",
+            "{",
+            "{\"a\"",
+            "{\"a\":",
+            "{\"a\":1",
+            "{\"a\" 1}",
+            "[",
+            "[1",
+            "[1,",
+            "nul",
+            "tru",
+            "-",
+            "1x",
+            "{\"a\":1,}",
+            "{\"a\":1 \"b\":2}",
+            "[1 2]",
+            "[1,]",
+            "[,1]",
+            "[1,,2]",
+            "}",
+            "]",
+            ":",
+        ] {
+            assert!(parse_checked(s).is_err(), "{}", s);
+        }
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!(parse_checked(&deep), Err("json nested too deeply."));
+        let ok = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse_checked(&ok).is_ok());
+        assert_eq!(
+            parse_checked(r#"{"a": [1, true]}"#),
+            parse(r#"{"a": [1, true]}"#)
+        );
+        assert_eq!(parse_checked("-1"), Ok(Value::Number(-1.0)));
+        assert_eq!(
+            parse_checked("[-0.5e3, 2]"),
+            parse_with("[-0.5e3, 2]", Backend::Events)
+        );
+    }
+
+    #[test]
+    fn test_parse_with_limits() {
+        let limits = ParseLimits {
+            max_depth: 3,
+            max_key_len: 3,
+            max_string_len: 5,
+            max_array_len: 2,
+        };
+        let s = r#"{"abc": [["hello"], []], "k": {"x": "12345"}}"#;
+        assert_eq!(parse_with_limits(s, &limits), parse(s));
+        for (s, e) in &[
+            (r#"{"abcd": 1}"#, "key too long."),
+            (r#"["hello!"]"#, "string too long."),
+            (r#"{"k": [1, 2, 3]}"#, "array too long."),
+            ("[[[[]]]]", "json nested too deeply."),
+            (r#"[{"a": 1, "bcde": 2}]"#, "key too long."),
+            (r#"{"k": {"x": "123456"}}"#, "string too long."),
+        ] {
+            assert_eq!(parse_with_limits(s, &limits), Err(*e), "{}", s);
+        }
+        // rejected before the bad number at the end is lexed.
+        let long = format!("[{}1x]", "1, ".repeat(10));
+        assert_eq!(parse_with_limits(&long, &limits), Err("array too long."));
+    }
+
+    #[test]
+    fn test_parse_with_deadline() {
+        use std::time::Duration;
+        let s = format!("[{}1]", "[1, 2], ".repeat(1000));
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(parse_with_deadline(&s, later), parse(&s));
+        let past = Instant::now();
+        assert_eq!(parse_with_deadline(&s, past), Err("parse timed out."));
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let s = r#"{"k": ["v", 1, true]}"#;
+        assert_eq!(parse_bytes(s.as_bytes()), parse(s));
+        assert!(parse_bytes(b"[\"\xff\"]").is_err());
+        assert!(parse_bytes(b"[nul\xff]").is_err());
+        assert_eq!(parse_bytes(b"\xef\xbb\xbf[1]"), parse("[1]"));
+    }
+
+    #[test]
+    fn test_parse_with_options() {
+        let options = ParseOptions {
+            backend: Backend::Events,
+            skip_prefix: true,
+        };
+        let expected = parse("[1]");
+        for s in [
+            ")]}'\n[1]",
+            ")]}',\n[1]",
+            "while(1);[1]",
+            "for(;;); [1]",
+            "junk: [1]",
+        ] {
+            assert_eq!(parse_with_options(s, &options), expected, "{:?}", s);
+            assert!(parse_with_options(s, &ParseOptions::default()).is_err());
+        }
+        assert_eq!(skip_junk_prefix(" \"a [b]\""), "\"a [b]\"");
+        assert_eq!(skip_junk_prefix(")]}' -1"), "-1");
+        assert_eq!(skip_junk_prefix("nothing here"), "nothing here");
+    }
+
+    #[test]
+    fn test_parse_utf16() {
+        let s = "{\"k\": [\"v\u{e9}\u{1f600}\", -1, true]}";
+        let expected = parse_with(s, Backend::Events);
+        let le: Vec<u8> = s.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let be: Vec<u8> = s.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(parse_utf16le(&le), expected);
+        assert_eq!(parse_utf16be(&be), expected);
+        assert_eq!(parse_bytes(&[&[0xff, 0xfe][..], &le].concat()), expected);
+        assert_eq!(parse_bytes(&[&[0xfe, 0xff][..], &be].concat()), expected);
+        assert_eq!(
+            parse_utf16le(&le[..le.len() - 1]),
+            Err("invalid utf-16 in input.")
+        );
+        // a lone high surrogate inside a string.
+        let lone: Vec<u8> = [0x22, 0xd800, 0x22]
+            .iter()
+            .flat_map(|u: &u16| u.to_le_bytes())
+            .collect();
+        assert_eq!(parse_utf16le(&lone), Err("invalid utf-16 in input."));
+        assert!(parse_utf16be(&be[..be.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_parse_compact() {
+        let s = r#"{"id": 7, "name": "a value longer than twenty-two bytes"}"#;
+        let v = parse_compact(s).unwrap();
+        assert_eq!(
+            v.clone().map(&mut |n| n, &mut |s| s.to_string()),
+            parse(s).unwrap()
+        );
+        if let CompactValue::Object(m) = &v {
+            assert!(m.keys().all(|k| k.is_inline()));
+            assert_eq!(m.get("id"), Some(&CompactValue::Number(7.0)));
+            assert!(matches!(m.get("name"), Some(CompactValue::String(s)) if !s.is_inline()));
+        }
+    }
+
+    #[test]
+    fn test_parse_with_metrics() {
+        let (v, metrics) = parse_with_metrics(r#"{"a": [1, {"b": null}], "c": "d"}"#).unwrap();
+        assert_eq!(v, parse(r#"{"a": [1, {"b": null}], "c": "d"}"#).unwrap());
+        assert_eq!(
+            metrics,
+            ParseMetrics {
+                bytes: 33,
+                tokens: 17,
+                nodes: 6,
+                peak_depth: 3,
+            }
+        );
+        assert!(parse_with_metrics("[1,").is_err());
+    }
+
+    #[test]
+    fn test_parser_reuse() {
+        let mut parser = Parser::new();
+        let big = format!("[{}1]", "1, ".repeat(100));
+        assert!(parser.parse(&big).is_ok());
+        let (capacity, storage) = (parser.tokens.capacity(), parser.tokens.as_ptr());
+        assert!(capacity >= 201);
+        assert_eq!(parser.parse(r#"{"a": [true]}"#), parse(r#"{"a": [true]}"#));
+        assert!(parser.parse("[1,").is_err());
+        assert!(parser.parse("nul").is_err());
+        assert_eq!(parser.tokens.capacity(), capacity);
+        assert_eq!(parser.tokens.as_ptr(), storage);
+        assert!(parser.tokens.is_empty());
+    }
+
+    #[test]
+    fn test_parse_with() {
+        let s = r#"{"a": [1, true, null, "x"]}"#;
+        assert_eq!(
+            parse_with(s, Backend::Tokens),
+            parse_with(s, Backend::Events)
+        );
+        assert_eq!(
+            parse_with(r#""a\"b""#, Backend::Events),
+            Ok(Value::String("a\"b".to_owned()))
+        );
+        assert!(parse_with("[1] 2", Backend::Events).is_err());
+        assert!(parse_with("", Backend::Events).is_err());
+        assert_eq!("[-1]".parse(), Ok(Value::Array(vec![Value::Number(-1.0)])));
+        assert!("{".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn test_parse_located() {
+        assert_eq!(parse_located("[1, -2]").ok(), "[1, -2]".parse().ok());
+        let e = parse_located("[1,\n  2,\n  x]").unwrap_err();
+        assert_eq!((e.offset, e.line, e.column), (11, 3, 3));
+        assert_eq!(e.to_string(), "line 3, column 3: unexpected character.");
+        let e = parse_located("[\"é\", 1] 2").unwrap_err();
+        assert_eq!((e.offset, e.line, e.column), (10, 1, 10));
+        let e = parse_located("[1,\n\n").unwrap_err();
+        assert_eq!(
+            (e.message, e.line, e.column),
+            ("unexpected end of input.", 3, 1)
+        );
+        let e: Box<dyn std::error::Error> = Box::new(e);
+        assert!(e.source().is_none());
+    }
+
+    #[test]
+    fn test_parse_bytes_located() {
+        assert_eq!(
+            parse_bytes_located(b"\xef\xbb\xbf[1]"),
+            Ok(parse("[1]").unwrap())
+        );
+        let e = parse_bytes_located(b"\xef\xbb\xbf[1,\n x]").unwrap_err();
+        assert_eq!((e.offset, e.line, e.column), (8, 2, 2));
+        let e = parse_bytes_located(b"[\"\xc3\xa9\xff\", 1]").unwrap_err();
+        assert_eq!(e.message, "invalid utf-8 in string.");
+        assert_eq!((e.offset, e.line, e.column), (6, 1, 6));
+        let e = parse_bytes_located(b"[nul\xff]").unwrap_err();
+        assert_eq!((e.offset, e.line, e.column), (4, 1, 5));
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_parse_error_diagnostic() {
+        use miette::Diagnostic;
+
+        let e = parse_located(r#"{"a": }"#).unwrap_err();
+        let labels: Vec<miette::LabeledSpan> = e.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].offset(), 6);
+        assert_eq!(labels[0].label(), Some("unexpected character."));
+        let report = miette::Report::new(e).with_source_code(r#"{"a": }"#);
+        assert_eq!(report.code().unwrap().to_string(), "parsing::parse_error");
     }
 }
@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// JSON Patch (RFC 6902) operations, and a `TrackedValue` recording its edits
+/// as them.
+use crate::access::{array_index, lookup, lookup_mut, pointer_tokens, remove_at, set_at};
+use crate::value::Value;
+
+/// One JSON Patch operation. Paths are JSON pointers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    /// Add an object member, replacing any existing one, or insert into an
+    /// array, shifting the later elements; `-` appends.
+    Add {
+        path: String,
+        value: Value,
+    },
+    Remove {
+        path: String,
+    },
+    /// Replace the existing value at `path`.
+    Replace {
+        path: String,
+        value: Value,
+    },
+}
+
+impl PatchOp {
+    /// The operation as the JSON object RFC 6902 describes, e.g.
+    /// `{"op": "remove", "path": "/a"}`.
+    pub fn to_value(&self) -> Value {
+        let (op, path, value) = match self {
+            PatchOp::Add { path, value } => ("add", path, Some(value)),
+            PatchOp::Remove { path } => ("remove", path, None),
+            PatchOp::Replace { path, value } => ("replace", path, Some(value)),
+        };
+        let mut m = HashMap::new();
+        m.insert("op".to_owned(), Value::String(op.to_owned()));
+        m.insert("path".to_owned(), Value::String(path.clone()));
+        if let Some(value) = value {
+            m.insert("value".to_owned(), value.clone());
+        }
+        Value::Object(m)
+    }
+
+    /// Apply the operation to `target`.
+    pub fn apply(&self, target: &mut Value) -> Result<(), &'static str> {
+        match self {
+            PatchOp::Add { path, value } => add_at(target, &tokens(path)?, value.clone()),
+            PatchOp::Remove { path } => remove_at(target, &tokens(path)?).map(drop),
+            PatchOp::Replace { path, value } => {
+                let tokens = tokens(path)?;
+                let slot = lookup_mut(target, &tokens).ok_or("pointer not found.")?;
+                *slot = value.clone();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Apply `ops` to `target` in order, stopping at the first that fails; the
+/// operations before it stay applied.
+pub fn apply_patch(target: &mut Value, ops: &[PatchOp]) -> Result<(), &'static str> {
+    ops.iter().try_for_each(|op| op.apply(target))
+}
+
+fn tokens(pointer: &str) -> Result<Vec<String>, &'static str> {
+    pointer_tokens(pointer).ok_or("pointer not found.")
+}
+
+// JSON Patch's `add`: unlike `set_at`, an index inside an array inserts.
+fn add_at(root: &mut Value, tokens: &[String], value: Value) -> Result<(), &'static str> {
+    if let Some((last, parent)) = tokens.split_last() {
+        if let Some(Value::Array(a)) = lookup_mut(root, parent) {
+            let i = array_index(last, a.len()).ok_or("pointer not found.")?;
+            a.insert(i, value);
+            return Ok(());
+        }
+    }
+    set_at(root, tokens, value).map(drop)
+}
+
+// the operations of one undoable edit or transaction, and the operations
+// reverting them in reverse order.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Step {
+    ops: Vec<PatchOp>,
+    inverse: Vec<PatchOp>,
+}
+
+/// A `Value` whose edits through its methods are recorded as JSON Patch
+/// operations, so they can be replayed on a copy elsewhere with
+/// `apply_patch`.
+///
+/// Edits can be undone and redone, one at a time or a transaction at a time.
+/// Undoing, redoing and rolling back are themselves recorded in the log as
+/// the operations they apply.
+#[derive(Debug)]
+pub struct TrackedValue {
+    value: Value,
+    log: Vec<PatchOp>,
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    transaction: Option<Step>,
+    watchers: Watchers,
+}
+
+/// Identifies a callback registered with `TrackedValue::watch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(usize);
+
+type Callback = Box<dyn FnMut(Option<&Value>)>;
+
+#[derive(Default)]
+struct Watchers {
+    next: usize,
+    list: Vec<(WatchId, Vec<String>, Callback)>,
+}
+
+impl fmt::Debug for Watchers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.list.iter().map(|(id, path, _)| (id, path)))
+            .finish()
+    }
+}
+
+impl TrackedValue {
+    pub fn new(value: Value) -> TrackedValue {
+        TrackedValue {
+            value,
+            log: vec![],
+            undo: vec![],
+            redo: vec![],
+            transaction: None,
+            watchers: Watchers::default(),
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> Value {
+        self.value
+    }
+
+    /// The operations recorded so far.
+    pub fn log(&self) -> &[PatchOp] {
+        &self.log
+    }
+
+    /// Return the operations recorded so far and start a new log.
+    pub fn take_log(&mut self) -> Vec<PatchOp> {
+        std::mem::take(&mut self.log)
+    }
+
+    /// Add `value` at `pointer` as JSON Patch's `add` does.
+    pub fn insert(&mut self, pointer: &str, value: Value) -> Result<(), &'static str> {
+        let tokens = tokens(pointer)?;
+        let inverse = match tokens.split_last() {
+            None => PatchOp::Replace {
+                path: String::new(),
+                value: self.value.clone(),
+            },
+            Some((last, parent)) => match lookup(&self.value, parent) {
+                Some(Value::Array(a)) => PatchOp::Remove {
+                    path: with_index(pointer, array_index(last, a.len())),
+                },
+                Some(Value::Object(m)) => match m.get(last) {
+                    Some(old) => PatchOp::Replace {
+                        path: pointer.to_owned(),
+                        value: old.clone(),
+                    },
+                    None => PatchOp::Remove {
+                        path: pointer.to_owned(),
+                    },
+                },
+                _ => return Err("pointer not found."),
+            },
+        };
+        let op = PatchOp::Add {
+            path: pointer.to_owned(),
+            value,
+        };
+        op.apply(&mut self.value)?;
+        self.record(op, inverse);
+        Ok(())
+    }
+
+    /// Remove and return the value at `pointer`.
+    pub fn remove(&mut self, pointer: &str) -> Result<Value, &'static str> {
+        let removed = remove_at(&mut self.value, &tokens(pointer)?)?;
+        let path = pointer.to_owned();
+        let inverse = PatchOp::Add {
+            path: path.clone(),
+            value: removed.clone(),
+        };
+        self.record(PatchOp::Remove { path }, inverse);
+        Ok(removed)
+    }
+
+    /// Set the value at `pointer`, replacing an existing value, adding an
+    /// object member or appending to an array at `-` or one past its end.
+    /// Returns the value replaced, if any.
+    pub fn set_path(&mut self, pointer: &str, value: Value) -> Result<Option<Value>, &'static str> {
+        let tokens = tokens(pointer)?;
+        // the index `-` stands for, in case the value is appended.
+        let end = match tokens.split_last() {
+            Some((last, parent)) => match lookup(&self.value, parent) {
+                Some(Value::Array(a)) => array_index(last, a.len()),
+                _ => None,
+            },
+            None => None,
+        };
+        let old = set_at(&mut self.value, &tokens, value.clone())?;
+        let path = pointer.to_owned();
+        let (op, inverse) = match &old {
+            Some(old) => (
+                PatchOp::Replace {
+                    path: path.clone(),
+                    value,
+                },
+                PatchOp::Replace {
+                    path,
+                    value: old.clone(),
+                },
+            ),
+            None => (
+                PatchOp::Add { path, value },
+                PatchOp::Remove {
+                    path: with_index(pointer, end),
+                },
+            ),
+        };
+        self.record(op, inverse);
+        Ok(old)
+    }
+
+    /// Call `callback` with the value at `pointer`, or `None` once there is
+    /// none, after each edit, undo or redo changing that value or anything
+    /// inside it. Edits in a transaction are reported when it is committed,
+    /// and not at all when it is rolled back.
+    pub fn watch<F>(&mut self, pointer: &str, callback: F) -> Result<WatchId, &'static str>
+    where
+        F: FnMut(Option<&Value>) + 'static,
+    {
+        let path = tokens(pointer)?;
+        let id = WatchId(self.watchers.next);
+        self.watchers.next += 1;
+        self.watchers.list.push((id, path, Box::new(callback)));
+        Ok(id)
+    }
+
+    /// Stop calling the callback registered as `id`.
+    pub fn unwatch(&mut self, id: WatchId) {
+        self.watchers.list.retain(|(watched, _, _)| *watched != id);
+    }
+
+    /// Group the following edits into one step for `undo`, until `commit` or
+    /// `rollback`.
+    pub fn begin_transaction(&mut self) -> Result<(), &'static str> {
+        if self.transaction.is_some() {
+            return Err("transaction already open.");
+        }
+        self.transaction = Some(Step::default());
+        Ok(())
+    }
+
+    /// Close the open transaction, keeping its edits.
+    pub fn commit(&mut self) -> Result<(), &'static str> {
+        let step = self.transaction.take().ok_or("no open transaction.")?;
+        if !step.ops.is_empty() {
+            self.notify(&step.ops);
+            self.undo.push(step);
+        }
+        Ok(())
+    }
+
+    /// Close the open transaction, reverting its edits.
+    pub fn rollback(&mut self) -> Result<(), &'static str> {
+        let step = self.transaction.take().ok_or("no open transaction.")?;
+        self.replay(&step.inverse)
+    }
+
+    /// Revert the last edit or committed transaction, returning whether
+    /// there was one.
+    pub fn undo(&mut self) -> Result<bool, &'static str> {
+        if self.transaction.is_some() {
+            return Err("transaction still open.");
+        }
+        let step = match self.undo.pop() {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+        self.replay(&step.inverse)?;
+        self.notify(&step.inverse);
+        self.redo.push(step);
+        Ok(true)
+    }
+
+    /// Repeat the last undone edit or transaction, returning whether there
+    /// was one. Any new edit clears what can be redone.
+    pub fn redo(&mut self) -> Result<bool, &'static str> {
+        if self.transaction.is_some() {
+            return Err("transaction still open.");
+        }
+        let step = match self.redo.pop() {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+        self.replay(&step.ops)?;
+        self.notify(&step.ops);
+        self.undo.push(step);
+        Ok(true)
+    }
+
+    fn record(&mut self, op: PatchOp, inverse: PatchOp) {
+        self.log.push(op.clone());
+        self.redo.clear();
+        match &mut self.transaction {
+            Some(step) => {
+                step.ops.push(op);
+                step.inverse.insert(0, inverse);
+            }
+            None => {
+                self.notify(std::slice::from_ref(&op));
+                self.undo.push(Step {
+                    ops: vec![op],
+                    inverse: vec![inverse],
+                });
+            }
+        }
+    }
+
+    // call each watcher whose value `ops` may have changed, once.
+    fn notify(&mut self, ops: &[PatchOp]) {
+        let value = &self.value;
+        let touched: Vec<(Vec<String>, bool)> = ops
+            .iter()
+            .filter_map(|op| {
+                let (path, shifts) = match op {
+                    PatchOp::Replace { path, .. } => (path, false),
+                    PatchOp::Add { path, .. } | PatchOp::Remove { path } => (path, true),
+                };
+                Some((pointer_tokens(path)?, shifts))
+            })
+            .collect();
+        for (_, watched, callback) in &mut self.watchers.list {
+            let hit = touched.iter().any(|(path, shifts)| {
+                if watched.starts_with(path) || path.starts_with(watched) {
+                    return true;
+                }
+                // adding or removing array elements moves the later ones.
+                let parent = &path[..path.len().saturating_sub(1)];
+                *shifts
+                    && watched.starts_with(parent)
+                    && matches!(lookup(value, parent), Some(Value::Array(_)))
+            });
+            if hit {
+                callback(lookup(value, watched));
+            }
+        }
+    }
+
+    fn replay(&mut self, ops: &[PatchOp]) -> Result<(), &'static str> {
+        for op in ops {
+            op.apply(&mut self.value)?;
+            self.log.push(op.clone());
+        }
+        Ok(())
+    }
+}
+
+// `pointer` with its last token replaced by `index`, if any.
+fn with_index(pointer: &str, index: Option<usize>) -> String {
+    match (index, pointer.rfind('/')) {
+        (Some(i), Some(slash)) => format!("{}/{}", &pointer[..slash], i),
+        _ => pointer.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_tracked_value() {
+        let original = parse(r#"{"a": [1, 2], "b": {"c": "x"}}"#).unwrap();
+        let mut tracked = TrackedValue::new(original.clone());
+        tracked.insert("/a/0", Value::Number(0.0)).unwrap();
+        tracked.set_path("/b/c", Value::Null).unwrap();
+        tracked.set_path("/b/d", Value::Bool(true)).unwrap();
+        assert_eq!(tracked.remove("/a/2"), Ok(Value::Number(2.0)));
+        assert_eq!(tracked.remove("/z"), Err("pointer not found."));
+        assert_eq!(
+            tracked.value(),
+            &parse(r#"{"a": [0, 1], "b": {"c": null, "d": true}}"#).unwrap()
+        );
+        let ops: Vec<Value> = tracked.log().iter().map(PatchOp::to_value).collect();
+        assert_eq!(
+            Value::Array(ops),
+            parse(
+                r#"[{"op": "add", "path": "/a/0", "value": 0},
+                    {"op": "replace", "path": "/b/c", "value": null},
+                    {"op": "add", "path": "/b/d", "value": true},
+                    {"op": "remove", "path": "/a/2"}]"#
+            )
+            .unwrap()
+        );
+        let mut replica = original;
+        apply_patch(&mut replica, &tracked.take_log()).unwrap();
+        assert_eq!(&replica, tracked.value());
+        assert!(tracked.log().is_empty());
+    }
+
+    #[test]
+    fn test_undo() {
+        let original = parse(r#"{"a": [1, 2], "b": {"c": "x"}}"#).unwrap();
+        let mut tracked = TrackedValue::new(original.clone());
+        tracked.set_path("/a/-", Value::Number(3.0)).unwrap();
+        tracked.begin_transaction().unwrap();
+        tracked.insert("/a/0", Value::Number(0.0)).unwrap();
+        tracked.remove("/b/c").unwrap();
+        assert_eq!(tracked.undo(), Err("transaction still open."));
+        tracked.commit().unwrap();
+        let edited = tracked.value().clone();
+        assert_eq!(edited, parse(r#"{"a": [0, 1, 2, 3], "b": {}}"#).unwrap());
+
+        assert_eq!(tracked.undo(), Ok(true));
+        assert_eq!(
+            tracked.value(),
+            &parse(r#"{"a": [1, 2, 3], "b": {"c": "x"}}"#).unwrap()
+        );
+        assert_eq!(tracked.undo(), Ok(true));
+        assert_eq!(tracked.value(), &original);
+        assert_eq!(tracked.undo(), Ok(false));
+        assert_eq!(tracked.redo(), Ok(true));
+        assert_eq!(tracked.redo(), Ok(true));
+        assert_eq!(tracked.value(), &edited);
+
+        tracked.begin_transaction().unwrap();
+        tracked.set_path("/b/d", Value::Null).unwrap();
+        tracked.set_path("/a/0", Value::Null).unwrap();
+        tracked.rollback().unwrap();
+        assert_eq!(tracked.value(), &edited);
+        assert_eq!(tracked.redo(), Ok(false));
+
+        // the log replays every change, undo and rollback included.
+        let mut replica = original;
+        apply_patch(&mut replica, tracked.log()).unwrap();
+        assert_eq!(&replica, tracked.value());
+    }
+
+    #[test]
+    fn test_watch() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(vec![]));
+        let mut tracked =
+            TrackedValue::new(parse(r#"{"db": {"port": 1}, "list": [1, 2]}"#).unwrap());
+        let record = |name: &'static str| {
+            let seen = seen.clone();
+            move |v: Option<&Value>| seen.borrow_mut().push((name, v.cloned()))
+        };
+        let db = tracked.watch("/db", record("db")).unwrap();
+        tracked.watch("/list/1", record("second")).unwrap();
+
+        tracked.set_path("/db/port", Value::Number(2.0)).unwrap();
+        tracked.set_path("/other", Value::Null).unwrap();
+        tracked.remove("/list/0").unwrap();
+        tracked.begin_transaction().unwrap();
+        tracked.set_path("/db", Value::Null).unwrap();
+        assert_eq!(seen.borrow().len(), 2);
+        tracked.rollback().unwrap();
+        tracked.unwatch(db);
+        tracked.undo().unwrap();
+        tracked.set_path("/db/port", Value::Number(3.0)).unwrap();
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                ("db", Some(parse(r#"{"port": 2}"#).unwrap())),
+                ("second", None),
+                ("second", Some(Value::Number(2.0))),
+            ]
+        );
+    }
+}
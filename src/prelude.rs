@@ -0,0 +1,20 @@
+/// The commonly used items, for `use parsing_rs::prelude::*;`.
+pub use crate::csv::CsvOptions;
+pub use crate::parser::{parse, parse_checked, parse_with, Backend};
+pub use crate::query::Query;
+pub use crate::serializer::{to_string, to_writer};
+pub use crate::sink::{parse_into, ValueSink};
+pub use crate::value::Value;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prelude() {
+        let v = parse(r#"{"a": [1, 2]}"#).unwrap();
+        let query = Query::compile("a[1]").unwrap();
+        assert_eq!(query.evaluate(&v), Ok(vec![Value::Number(2.0)]));
+        assert_eq!(to_string(&v), r#"{"a":[1,2]}"#);
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Truncated copies of values for logging and UI display of huge payloads.
+use crate::event::{Event, EventReader};
+use crate::value::Value;
+
+const ELLIPSIS: &str = "…";
+
+// a container being previewed by `preview_from`, the key of its next member
+// and the number of entries left out of it.
+struct Frame {
+    value: Value,
+    key: Option<String>,
+    more: usize,
+}
+
+impl Frame {
+    fn len(&self) -> usize {
+        match &self.value {
+            Value::Array(v) => v.len(),
+            Value::Object(m) => m.len(),
+            _ => unreachable!("only containers are open"),
+        }
+    }
+}
+
+/// Preview the document in `reader` as `Value::preview` does, streaming it so
+/// that only the kept parts are built. Objects keep their first entries in
+/// document order rather than key order, and the entries left out are
+/// skipped without being decoded.
+pub fn preview_from<R: Read>(
+    reader: R,
+    max_array_items: usize,
+    max_string_len: usize,
+    max_depth: usize,
+) -> Result<Value, &'static str> {
+    let mut events = EventReader::new(reader);
+    let mut stack: Vec<Frame> = vec![];
+    loop {
+        if let Some(frame) = stack.last_mut() {
+            if frame.len() >= max_array_items {
+                while events.skip_value()? {
+                    frame.more += 1;
+                }
+            }
+        }
+        let value = match events.next_event()? {
+            Some(Event::Key(k)) => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.key = Some(k);
+                }
+                continue;
+            }
+            Some(event @ (Event::StartArray | Event::StartObject)) if stack.len() >= max_depth => {
+                // skip the contents, keeping empty containers as they are.
+                let empty = !events.skip_value()?;
+                while events.skip_value()? {}
+                events.next_event()?;
+                match (event, empty) {
+                    (Event::StartArray, true) => Value::Array(vec![]),
+                    (Event::StartArray, false) => Value::String(format!("[{}]", ELLIPSIS)),
+                    (_, true) => Value::Object(HashMap::new()),
+                    (_, false) => Value::String(format!("{{{}}}", ELLIPSIS)),
+                }
+            }
+            Some(Event::StartArray) => {
+                stack.push(Frame {
+                    value: Value::Array(vec![]),
+                    key: None,
+                    more: 0,
+                });
+                continue;
+            }
+            Some(Event::StartObject) => {
+                stack.push(Frame {
+                    value: Value::Object(HashMap::new()),
+                    key: None,
+                    more: 0,
+                });
+                continue;
+            }
+            Some(Event::EndArray) | Some(Event::EndObject) => {
+                let mut frame = stack.pop().expect("containers are balanced");
+                match &mut frame.value {
+                    Value::Array(v) if frame.more > 0 => {
+                        v.push(Value::String(format!("{} {} more", ELLIPSIS, frame.more)))
+                    }
+                    Value::Object(m) if frame.more > 0 => {
+                        m.insert(
+                            ELLIPSIS.to_owned(),
+                            Value::String(format!("{} more", frame.more)),
+                        );
+                    }
+                    _ => {}
+                }
+                frame.value
+            }
+            Some(Event::String(s)) => Value::String(truncate(&s, max_string_len).unwrap_or(s)),
+            Some(event) => events.build_value(event)?,
+            None => return Err("unexpected end of input."),
+        };
+        match stack.last_mut() {
+            Some(Frame {
+                value: Value::Array(v),
+                ..
+            }) => v.push(value),
+            Some(Frame {
+                value: Value::Object(m),
+                key,
+                ..
+            }) => {
+                m.insert(key.take().unwrap_or_default(), value);
+            }
+            Some(_) => unreachable!("only containers are open"),
+            None => {
+                // reject anything after the document.
+                events.next_event()?;
+                return Ok(value);
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Return a copy of the value truncated for display.
+    ///
+    /// Strings longer than `max_string_len` characters are cut and end with `…`.
+    /// Arrays and objects keep their first `max_array_items` entries (objects in
+    /// key order) followed by a `"… N more"` marker, an array element or the
+    /// `"…"` key respectively. Non-empty containers nested deeper than
+    /// `max_depth` are replaced by the strings `"[…]"` and `"{…}"`.
+    pub fn preview(
+        &self,
+        max_array_items: usize,
+        max_string_len: usize,
+        max_depth: usize,
+    ) -> Value {
+        self.preview_at(0, max_array_items, max_string_len, max_depth)
+    }
+
+    fn preview_at(
+        &self,
+        depth: usize,
+        max_items: usize,
+        max_len: usize,
+        max_depth: usize,
+    ) -> Value {
+        match self {
+            Value::String(s) => Value::String(truncate(s, max_len).unwrap_or_else(|| s.clone())),
+            Value::Array(v) if !v.is_empty() && depth >= max_depth => {
+                Value::String(format!("[{}]", ELLIPSIS))
+            }
+            Value::Object(m) if !m.is_empty() && depth >= max_depth => {
+                Value::String(format!("{{{}}}", ELLIPSIS))
+            }
+            Value::Array(v) => {
+                let mut items: Vec<Value> = v
+                    .iter()
+                    .take(max_items)
+                    .map(|item| item.preview_at(depth + 1, max_items, max_len, max_depth))
+                    .collect();
+                if v.len() > max_items {
+                    items.push(Value::String(format!(
+                        "{} {} more",
+                        ELLIPSIS,
+                        v.len() - max_items
+                    )));
+                }
+                Value::Array(items)
+            }
+            Value::Object(m) => {
+                let mut keys: Vec<&String> = m.keys().collect();
+                keys.sort();
+                let mut entries: HashMap<String, Value> = keys
+                    .iter()
+                    .take(max_items)
+                    .map(|&k| {
+                        (
+                            k.clone(),
+                            m[k].preview_at(depth + 1, max_items, max_len, max_depth),
+                        )
+                    })
+                    .collect();
+                if m.len() > max_items {
+                    entries.insert(
+                        ELLIPSIS.to_owned(),
+                        Value::String(format!("{} more", m.len() - max_items)),
+                    );
+                }
+                Value::Object(entries)
+            }
+            v => v.clone(),
+        }
+    }
+}
+
+// `s` cut to `max_len` characters and ending with `…`, if it is longer.
+fn truncate(s: &str, max_len: usize) -> Option<String> {
+    if s.chars().count() <= max_len {
+        return None;
+    }
+    let mut s: String = s.chars().take(max_len).collect();
+    s.push_str(ELLIPSIS);
+    Some(s)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_owned())
+    }
+
+    #[test]
+    fn test_preview() {
+        let v = parse(r#"[1, 2, 3, 4, 5]"#).unwrap();
+        assert_eq!(
+            v.preview(2, 10, 5),
+            Value::Array(vec![Value::Number(1.0), Value::Number(2.0), s("… 3 more")])
+        );
+        assert_eq!(s("abcdef").preview(2, 3, 5), s("abc…"));
+        assert_eq!(s("abc").preview(2, 3, 5), s("abc"));
+        let v = parse(r#"{"b": {"c": [1]}, "a": [], "z": 1}"#).unwrap();
+        let mut inner = HashMap::new();
+        inner.insert("c".to_owned(), s("[…]"));
+        let mut m = HashMap::new();
+        m.insert("a".to_owned(), Value::Array(vec![]));
+        m.insert("b".to_owned(), Value::Object(inner));
+        m.insert("…".to_owned(), s("1 more"));
+        assert_eq!(v.preview(2, 10, 2), Value::Object(m));
+        assert_eq!(v.preview(2, 10, 0), s("{…}"));
+        assert_eq!(v.preview(10, 10, 10), v);
+    }
+
+    #[test]
+    fn test_preview_from() {
+        let doc = r#"{"a": [], "b": {"c": [1], "d": "abcdef"}, "e": [1, [2], {"x": 3}], "f": {}}"#;
+        let v = parse(doc).unwrap();
+        for &(items, len, depth) in &[(2, 3, 2), (1, 10, 1), (10, 10, 10), (3, 0, 0), (0, 1, 5)] {
+            assert_eq!(
+                preview_from(doc.as_bytes(), items, len, depth),
+                Ok(v.preview(items, len, depth)),
+                "{} {} {}",
+                items,
+                len,
+                depth
+            );
+        }
+        assert!(preview_from("[1, 2, 3".as_bytes(), 1, 1, 1).is_err());
+        assert!(preview_from("[1] 2".as_bytes(), 1, 1, 1).is_err());
+    }
+}
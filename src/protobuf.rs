@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Conversions between `Value` and the well-known `google.protobuf.Struct`,
+/// `Value` and `ListValue` types from `prost-types`. Enabled by the `prost`
+/// feature.
+///
+/// Every number is a double in protobuf, as in `Value`. A protobuf value
+/// with no kind set becomes `null`.
+use crate::value::Value;
+use prost_types::value::Kind;
+use prost_types::{ListValue, Struct};
+
+impl From<Value> for prost_types::Value {
+    fn from(value: Value) -> prost_types::Value {
+        let kind = match value {
+            Value::Null => Kind::NullValue(0),
+            Value::Bool(b) => Kind::BoolValue(b),
+            Value::Number(n) => Kind::NumberValue(n),
+            Value::String(s) => Kind::StringValue(s),
+            Value::Array(v) => Kind::ListValue(list(v)),
+            Value::Object(m) => Kind::StructValue(object(m)),
+        };
+        prost_types::Value { kind: Some(kind) }
+    }
+}
+
+impl From<prost_types::Value> for Value {
+    fn from(value: prost_types::Value) -> Value {
+        match value.kind {
+            None | Some(Kind::NullValue(_)) => Value::Null,
+            Some(Kind::BoolValue(b)) => Value::Bool(b),
+            Some(Kind::NumberValue(n)) => Value::Number(n),
+            Some(Kind::StringValue(s)) => Value::String(s),
+            Some(Kind::ListValue(l)) => l.into(),
+            Some(Kind::StructValue(s)) => s.into(),
+        }
+    }
+}
+
+impl From<Struct> for Value {
+    fn from(s: Struct) -> Value {
+        Value::Object(s.fields.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl From<ListValue> for Value {
+    fn from(l: ListValue) -> Value {
+        Value::Array(l.values.into_iter().map(Value::from).collect())
+    }
+}
+
+impl TryFrom<Value> for Struct {
+    type Error = &'static str;
+
+    fn try_from(value: Value) -> Result<Struct, &'static str> {
+        match value {
+            Value::Object(m) => Ok(object(m)),
+            _ => Err("expected an object."),
+        }
+    }
+}
+
+impl TryFrom<Value> for ListValue {
+    type Error = &'static str;
+
+    fn try_from(value: Value) -> Result<ListValue, &'static str> {
+        match value {
+            Value::Array(v) => Ok(list(v)),
+            _ => Err("expected an array."),
+        }
+    }
+}
+
+fn object(m: HashMap<String, Value>) -> Struct {
+    Struct {
+        fields: m.into_iter().map(|(k, v)| (k, v.into())).collect(),
+    }
+}
+
+fn list(v: Vec<Value>) -> ListValue {
+    ListValue {
+        values: v.into_iter().map(prost_types::Value::from).collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_with, Backend};
+
+    #[test]
+    fn test_protobuf() {
+        let value = parse_with(
+            r#"{"a": [1, -2.5, "x", true, null], "b": {"c": {}}}"#,
+            Backend::Events,
+        )
+        .unwrap();
+        let s = Struct::try_from(value.clone()).unwrap();
+        assert_eq!(
+            s.fields["b"],
+            prost_types::Value {
+                kind: Some(Kind::StructValue(Struct {
+                    fields: vec![("c".to_owned(), Value::Object(HashMap::new()).into())]
+                        .into_iter()
+                        .collect(),
+                })),
+            }
+        );
+        assert_eq!(Value::from(s), value);
+        assert_eq!(Value::from(prost_types::Value { kind: None }), Value::Null);
+        assert!(Struct::try_from(Value::Null).is_err());
+        assert_eq!(
+            ListValue::try_from(Value::Array(vec![Value::Bool(true)])),
+            Ok(ListValue {
+                values: vec![prost_types::Value {
+                    kind: Some(Kind::BoolValue(true))
+                }],
+            })
+        );
+    }
+}
@@ -0,0 +1,150 @@
+/// Precompiled queries, so hot loops evaluating the same expression over many
+/// documents parse it only once.
+use crate::jmespath;
+use crate::jq;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Compiled {
+    JmesPath(jmespath::Node),
+    Jq(jq::Filter),
+}
+
+/// One step of a path-shaped query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+    /// every element of an array.
+    Elements,
+    /// every value of an object.
+    Values,
+    /// every element of an array or value of an object.
+    Children,
+}
+
+/// A query expression compiled into a reusable matcher.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    compiled: Compiled,
+}
+
+impl Query {
+    /// Compile `expr`, treating it as a jq filter when it starts with `.` and
+    /// as a JMESPath expression otherwise.
+    pub fn compile(expr: &str) -> Result<Query, &'static str> {
+        if expr.trim_start().starts_with('.') {
+            Query::jq(expr)
+        } else {
+            Query::jmespath(expr)
+        }
+    }
+
+    /// Compile a JMESPath expression.
+    pub fn jmespath(expr: &str) -> Result<Query, &'static str> {
+        Ok(Query {
+            compiled: Compiled::JmesPath(jmespath::compile(expr)?),
+        })
+    }
+
+    /// Compile a jq filter.
+    pub fn jq(expr: &str) -> Result<Query, &'static str> {
+        Ok(Query {
+            compiled: Compiled::Jq(jq::compile_filter(expr)?),
+        })
+    }
+
+    /// Evaluate the query against `value`. A JMESPath query always yields
+    /// exactly one result, a jq filter yields every value it outputs.
+    pub fn evaluate(&self, value: &Value) -> Result<Vec<Value>, &'static str> {
+        match &self.compiled {
+            Compiled::JmesPath(node) => Ok(vec![jmespath::evaluate(node, value)?]),
+            Compiled::Jq(filter) => filter.apply(value),
+        }
+    }
+
+    /// The query as a sequence of path segments, if it only navigates into the
+    /// document (e.g. `.items[].name` or `items[*].name`) without computing anything.
+    pub fn path(&self) -> Result<Vec<Segment>, &'static str> {
+        let path = match &self.compiled {
+            Compiled::JmesPath(node) => jmespath::path(node),
+            Compiled::Jq(filter) => filter.path(),
+        };
+        path.ok_or("query is not a simple path.")
+    }
+
+    /// Whether the query produces a truthy result for `value`, using the truthiness
+    /// rules of the query language. Evaluation errors count as no match.
+    pub fn matches(&self, value: &Value) -> bool {
+        match &self.compiled {
+            Compiled::JmesPath(node) => jmespath::evaluate(node, value)
+                .map(|v| jmespath::is_truthy(&v))
+                .unwrap_or(false),
+            Compiled::Jq(filter) => filter
+                .apply(value)
+                .map(|v| v.iter().any(jq::is_truthy))
+                .unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_query() {
+        let docs: Vec<Value> = [
+            r#"{"user": {"name": "a", "age": 20}}"#,
+            r#"{"user": {"name": "b", "age": 40}}"#,
+            r#"{"other": []}"#,
+        ]
+        .iter()
+        .map(|s| parse(s).unwrap())
+        .collect();
+        {
+            let q = Query::compile("user.age > `30`").unwrap();
+            let matched: Vec<bool> = docs.iter().map(|d| q.matches(d)).collect();
+            assert_eq!(matched, vec![false, true, false]);
+            assert_eq!(q.evaluate(&docs[0]).unwrap(), vec![Value::Bool(false)]);
+        }
+        {
+            let q = Query::compile(".user.age > 30").unwrap();
+            let matched: Vec<bool> = docs.iter().map(|d| q.matches(d)).collect();
+            assert_eq!(matched, vec![false, true, false]);
+        }
+        {
+            let q = Query::compile(".user.name").unwrap();
+            assert_eq!(q, Query::jq(".user.name").unwrap());
+            assert_eq!(
+                q.evaluate(&docs[1]).unwrap(),
+                vec![Value::String("b".to_owned())]
+            );
+            assert!(q.matches(&docs[0]));
+            assert!(!q.matches(&docs[2]));
+        }
+        assert_eq!(
+            Query::compile(".a[].b[0]").unwrap().path().unwrap(),
+            vec![
+                Segment::Key("a".to_owned()),
+                Segment::Children,
+                Segment::Key("b".to_owned()),
+                Segment::Index(0)
+            ]
+        );
+        assert_eq!(
+            Query::compile("a[*].b.*").unwrap().path().unwrap(),
+            vec![
+                Segment::Key("a".to_owned()),
+                Segment::Elements,
+                Segment::Key("b".to_owned()),
+                Segment::Values
+            ]
+        );
+        assert!(Query::compile(".a | length").unwrap().path().is_err());
+        assert!(Query::compile("a[*] | [0]").unwrap().path().is_err());
+        assert!(Query::compile("user.").is_err());
+        assert!(Query::compile(".user.").is_err());
+    }
+}
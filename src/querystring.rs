@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use crate::parser::MAX_DEPTH;
+use crate::serializer;
+/// Convert URL query strings to and from `Value`s, and serialize `Value`s as
+/// form bodies.
+///
+/// Keys use the bracket convention of web frameworks: `a[b]=1` sets the key
+/// `b` of the object `a`, `a[]=x` appends to the array `a` and `a[0]=x` sets
+/// an array element by index (appending when the index is the array's length).
+/// All values decode as strings; a repeated plain key keeps its last value.
+/// Keys with more than `MAX_DEPTH` segments are rejected.
+use crate::value::Value;
+
+/// Parse a query string, with or without its leading `?`, into an object.
+pub fn from_query_string(s: &str) -> Result<Value, &'static str> {
+    let s = s.strip_prefix('?').unwrap_or(s);
+    let mut root = Value::Object(HashMap::new());
+    for pair in s.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = match pair.find('=') {
+            Some(i) => (&pair[..i], &pair[i + 1..]),
+            None => (pair, ""),
+        };
+        let key = percent_decode(key)?;
+        let value = Value::String(percent_decode(value)?);
+        let path = key_path(&key);
+        if path[0].is_empty() {
+            return Err("empty query string key.");
+        }
+        if path.len() > MAX_DEPTH {
+            return Err("query string key nested too deeply.");
+        }
+        insert(&mut root, &path, value)?;
+    }
+    Ok(root)
+}
+
+/// Serialize an object into a query string, the inverse of `from_query_string`.
+///
+/// Keys are written in sorted order, `null` as an empty value and empty arrays
+/// or objects, which have no representation, are left out.
+pub fn to_query_string(value: &Value) -> Result<String, &'static str> {
+    encode(value, false)
+}
+
+/// Serialize an object as an `application/x-www-form-urlencoded` body.
+///
+/// Spaces are written as `+`. Nested objects and arrays are only accepted when
+/// `brackets` is set and then use the keys of `to_query_string`.
+pub fn to_form_urlencoded(value: &Value, brackets: bool) -> Result<String, &'static str> {
+    if let Value::Object(m) = value {
+        let nested = m
+            .values()
+            .any(|v| matches!(v, Value::Object(_) | Value::Array(_)));
+        if nested && !brackets {
+            return Err("nested value in a flat form.");
+        }
+    }
+    encode(value, true)
+}
+
+fn encode(value: &Value, form: bool) -> Result<String, &'static str> {
+    let m = match value {
+        Value::Object(m) => m,
+        _ => return Err("object expected."),
+    };
+    let mut pairs = vec![];
+    let mut keys: Vec<&String> = m.keys().collect();
+    keys.sort();
+    for k in keys {
+        let mut name = String::new();
+        percent_encode(k, form, &mut name);
+        flatten(name, &m[k], form, &mut pairs);
+    }
+    Ok(pairs.join("&"))
+}
+
+// split `a[b][]` into `["a", "b", ""]`; a key with unbalanced brackets is
+// taken literally.
+fn key_path(key: &str) -> Vec<&str> {
+    let base = match key.find('[') {
+        Some(i) if i > 0 => i,
+        _ => return vec![key],
+    };
+    let mut path = vec![&key[..base]];
+    let mut rest = &key[base..];
+    while !rest.is_empty() {
+        match (rest.strip_prefix('['), rest.find(']')) {
+            (Some(_), Some(end)) => {
+                path.push(&rest[1..end]);
+                rest = &rest[end + 1..];
+            }
+            _ => return vec![key],
+        }
+    }
+    path
+}
+
+fn insert(slot: &mut Value, path: &[&str], value: Value) -> Result<(), &'static str> {
+    let (segment, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *slot = value;
+            return Ok(());
+        }
+    };
+    let index = if segment.is_empty() {
+        Some(usize::MAX)
+    } else {
+        segment.parse::<usize>().ok()
+    };
+    if let Value::Null = slot {
+        *slot = match index {
+            Some(_) => Value::Array(vec![]),
+            None => Value::Object(HashMap::new()),
+        };
+    }
+    match (slot, index) {
+        (Value::Array(items), Some(i)) if i >= items.len() => {
+            if i != usize::MAX && i > items.len() {
+                return Err("query string array index out of order.");
+            }
+            items.push(Value::Null);
+            insert(items.last_mut().unwrap(), rest, value)
+        }
+        (Value::Array(items), Some(i)) => insert(&mut items[i], rest, value),
+        (Value::Object(m), _) => insert(
+            m.entry(segment.to_string()).or_insert(Value::Null),
+            rest,
+            value,
+        ),
+        _ => Err("conflicting query string keys."),
+    }
+}
+
+fn flatten(name: String, value: &Value, form: bool, pairs: &mut Vec<String>) {
+    match value {
+        Value::Object(m) => {
+            let mut keys: Vec<&String> = m.keys().collect();
+            keys.sort();
+            for k in keys {
+                let mut key = format!("{}[", name);
+                percent_encode(k, form, &mut key);
+                key.push(']');
+                flatten(key, &m[k], form, pairs);
+            }
+        }
+        Value::Array(a) => {
+            for (i, v) in a.iter().enumerate() {
+                // containers need the index so their fields land in the same element.
+                let key = match v {
+                    Value::Object(_) | Value::Array(_) => format!("{}[{}]", name, i),
+                    _ => format!("{}[]", name),
+                };
+                flatten(key, v, form, pairs);
+            }
+        }
+        v => {
+            let mut pair = name;
+            pair.push('=');
+            match v {
+                Value::Null => {}
+                Value::String(s) => percent_encode(s, form, &mut pair),
+                v => percent_encode(&serializer::to_string(v), form, &mut pair),
+            }
+            pairs.push(pair);
+        }
+    }
+}
+
+// append `s` to `out` escaping everything but the unreserved characters, and
+// spaces as `+` in forms.
+pub(crate) fn percent_encode(s: &str, form: bool, out: &mut String) {
+    for b in s.bytes() {
+        match b {
+            b' ' if form => out.push('+'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+// decode `%XX` escapes and `+` as a space.
+pub(crate) fn percent_decode(s: &str) -> Result<String, &'static str> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or("invalid percent escape.")?;
+                out.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| "invalid utf-8 in query string.")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_from_query_string() {
+        let v = from_query_string("?a=1&b[]=x&b[]=y&c[d][e]=hello+world%21&f&c[g]=&a=2").unwrap();
+        let exp = parse(
+            r#"{"a": "2", "b": ["x", "y"], "c": {"d": {"e": "hello world!"}, "g": ""}, "f": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(v, exp);
+
+        let v = from_query_string("i[0][n]=a&i[0][m]=b&i[1][n]=c&x[y=1").unwrap();
+        let exp = parse(r#"{"i": [{"n": "a", "m": "b"}, {"n": "c"}], "x[y": "1"}"#).unwrap();
+        assert_eq!(v, exp);
+
+        assert!(from_query_string("a=1&a[b]=2").is_err());
+        assert!(from_query_string("a[2]=1").is_err());
+        assert!(from_query_string("=1").is_err());
+        assert!(from_query_string("a=%zz").is_err());
+        assert!(from_query_string("a=%+f").is_err());
+        let deep = |n: usize| format!("a{}=x", "[]".repeat(n));
+        assert!(from_query_string(&deep(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(
+            from_query_string(&deep(200_000)),
+            Err("query string key nested too deeply.")
+        );
+    }
+
+    #[test]
+    fn test_to_query_string() {
+        let v = parse(
+            r#"{"b": ["x", "y"], "a": 1, "c": {"d e": true, "n": null}, "i": [{"n": "a/b"}], "e": []}"#,
+        )
+        .unwrap();
+        let s = to_query_string(&v).unwrap();
+        assert_eq!(s, "a=1&b[]=x&b[]=y&c[d%20e]=true&c[n]=&i[0][n]=a%2Fb");
+        let back = from_query_string(&s).unwrap();
+        assert_eq!(
+            back,
+            from_query_string("a=1&b[]=x&b[]=y&c[d e]=true&c[n]=&i[0][n]=a/b").unwrap()
+        );
+        assert!(to_query_string(&Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_to_form_urlencoded() {
+        let v = parse(r#"{"name": "a b&c", "n": 2, "ok": false}"#).unwrap();
+        assert_eq!(
+            to_form_urlencoded(&v, false).unwrap(),
+            "n=2&name=a+b%26c&ok=false"
+        );
+        let v = parse(r#"{"user": {"name": "x y"}, "tags": ["a"]}"#).unwrap();
+        assert!(to_form_urlencoded(&v, false).is_err());
+        assert_eq!(
+            to_form_urlencoded(&v, true).unwrap(),
+            "tags[]=a&user[name]=x+y"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+/// Pretty-print or compact JSON straight from its bytes, without building
+/// values, so documents of any size are reformatted in constant memory.
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// The layout `reformat` writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    /// No whitespace at all.
+    Compact,
+    /// One element per line, indented by this many spaces per level.
+    Pretty(usize),
+}
+
+struct Output<W: Write> {
+    writer: BufWriter<W>,
+    style: Style,
+    depth: usize,
+}
+
+impl<W: Write> Output<W> {
+    fn write(&mut self, b: &[u8]) -> Result<(), &'static str> {
+        self.writer
+            .write_all(b)
+            .map_err(|_| "failed to write output.")
+    }
+
+    fn newline(&mut self) -> Result<(), &'static str> {
+        if let Style::Pretty(indent) = self.style {
+            self.write(b"\n")?;
+            for _ in 0..self.depth * indent {
+                self.write(b" ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy the JSON in `reader` to `writer` in `style`. Strings, numbers and
+/// literals are copied byte for byte; only the whitespace between them
+/// changes. Top-level values separated by whitespace, as in NDJSON, are
+/// written one per line.
+///
+/// Only the nesting of brackets and the termination of strings are checked,
+/// other malformed input is copied as it is.
+pub fn reformat<R: Read, W: Write>(reader: R, writer: W, style: Style) -> Result<(), &'static str> {
+    let mut out = Output {
+        writer: BufWriter::new(writer),
+        style,
+        depth: 0,
+    };
+    let mut in_string = false;
+    let mut escaped = false;
+    // an array or object was opened and nothing written in it yet.
+    let mut opened = false;
+    // a top-level value was written, and whitespace followed it.
+    let mut started = false;
+    let mut separate = false;
+    for b in BufReader::new(reader).bytes() {
+        let b = b.map_err(|_| "failed to read input.")?;
+        if in_string {
+            out.write(&[b])?;
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                separate = started && out.depth == 0;
+                continue;
+            }
+            b'}' | b']' if opened => {
+                opened = false;
+                out.depth -= 1;
+                out.write(&[b])?;
+                continue;
+            }
+            _ => {}
+        }
+        if opened {
+            opened = false;
+            out.newline()?;
+        } else if separate && out.depth == 0 {
+            out.write(b"\n")?;
+        }
+        separate = false;
+        started = true;
+        match b {
+            b'{' | b'[' => {
+                out.write(&[b])?;
+                out.depth += 1;
+                opened = true;
+            }
+            b'}' | b']' => {
+                out.depth = out
+                    .depth
+                    .checked_sub(1)
+                    .ok_or("unexpected closing bracket.")?;
+                out.newline()?;
+                out.write(&[b])?;
+            }
+            b',' => {
+                out.write(b",")?;
+                out.newline()?;
+            }
+            b':' if style == Style::Compact => out.write(b":")?,
+            b':' => out.write(b": ")?,
+            b'"' => {
+                out.write(b"\"")?;
+                in_string = true;
+            }
+            _ => out.write(&[b])?,
+        }
+    }
+    if in_string || out.depth > 0 {
+        return Err("unexpected end of input.");
+    }
+    out.writer.flush().map_err(|_| "failed to write output.")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(s: &str, style: Style) -> Result<String, &'static str> {
+        let mut out = vec![];
+        reformat(s.as_bytes(), &mut out, style)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_reformat() {
+        let s = r#" {"a" : [1.50e3, "x, \"y\" ]"], "b":{ }, "c": [ ] ,"d":{"e":null}} "#;
+        assert_eq!(
+            run(s, Style::Compact).unwrap(),
+            r#"{"a":[1.50e3,"x, \"y\" ]"],"b":{},"c":[],"d":{"e":null}}"#
+        );
+        assert_eq!(
+            run(s, Style::Pretty(2)).unwrap(),
+            r#"{
+  "a": [
+    1.50e3,
+    "x, \"y\" ]"
+  ],
+  "b": {},
+  "c": [],
+  "d": {
+    "e": null
+  }
+}"#
+        );
+        assert_eq!(
+            run("1 \"a\"\n[ 2 ]", Style::Compact).unwrap(),
+            "1\n\"a\"\n[2]"
+        );
+        assert!(run("[1", Style::Compact).is_err());
+        assert!(run("[\"1]", Style::Compact).is_err());
+        assert!(run("1]", Style::Compact).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Expand `{"$ref": "..."}` references into the tree they point at.
+///
+/// A reference is a file path relative to the document containing it, a JSON
+/// pointer fragment such as `#/defs/x`, or both (`other.json#/defs/x`). An
+/// object holding a string `$ref` is replaced as a whole by the expanded
+/// target, so any sibling keys are dropped. References that lead back into
+/// one being expanded are reported as cycles.
+use crate::parser::{parse_with, Backend};
+use crate::value::Value;
+
+/// Read the JSON file at `path` and expand its references.
+pub fn resolve_file(path: &Path) -> Result<Value, &'static str> {
+    let path = path.canonicalize().map_err(|_| "failed to read input.")?;
+    let mut resolver = Resolver::default();
+    resolver.load(&path)?;
+    resolver.expand_ref(&path, "")
+}
+
+/// Expand the references in `value`, reading files relative to `base`.
+pub fn resolve_refs(value: &Value, base: &Path) -> Result<Value, &'static str> {
+    let mut resolver = Resolver::default();
+    // the in-memory document is keyed by the empty path, which no file has.
+    let root = PathBuf::new();
+    resolver
+        .docs
+        .insert(root.clone(), (value.clone(), base.to_path_buf()));
+    resolver.expand_ref(&root, "")
+}
+
+#[derive(Default)]
+struct Resolver {
+    // every document loaded so far, with the directory its references are relative to.
+    docs: HashMap<PathBuf, (Value, PathBuf)>,
+    // the references being expanded, to detect cycles.
+    stack: Vec<(PathBuf, String)>,
+}
+
+impl Resolver {
+    fn load(&mut self, path: &Path) -> Result<(), &'static str> {
+        if !self.docs.contains_key(path) {
+            let s = fs::read_to_string(path).map_err(|_| "failed to read input.")?;
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            self.docs
+                .insert(path.to_path_buf(), (parse_with(&s, Backend::Events)?, dir));
+        }
+        Ok(())
+    }
+
+    fn expand_ref(&mut self, doc: &Path, fragment: &str) -> Result<Value, &'static str> {
+        let key = (doc.to_path_buf(), fragment.to_owned());
+        if self.stack.contains(&key) {
+            return Err("cyclic $ref.");
+        }
+        let target = self.docs[doc]
+            .0
+            .pointer(fragment)
+            .ok_or("$ref target not found.")?
+            .clone();
+        self.stack.push(key);
+        let expanded = self.expand(doc, &target);
+        self.stack.pop();
+        expanded
+    }
+
+    fn expand(&mut self, doc: &Path, value: &Value) -> Result<Value, &'static str> {
+        match value {
+            Value::Object(m) => {
+                if let Some(Value::String(r)) = m.get("$ref") {
+                    let (file, fragment) = match r.find('#') {
+                        Some(i) => (&r[..i], &r[i + 1..]),
+                        None => (r.as_str(), ""),
+                    };
+                    if file.is_empty() {
+                        return self.expand_ref(doc, fragment);
+                    }
+                    let path = self.docs[doc]
+                        .1
+                        .join(file)
+                        .canonicalize()
+                        .map_err(|_| "failed to read input.")?;
+                    self.load(&path)?;
+                    return self.expand_ref(&path, fragment);
+                }
+                let mut out = HashMap::with_capacity(m.len());
+                for (k, v) in m {
+                    out.insert(k.clone(), self.expand(doc, v)?);
+                }
+                Ok(Value::Object(out))
+            }
+            Value::Array(v) => Ok(Value::Array(
+                v.iter()
+                    .map(|v| self.expand(doc, v))
+                    .collect::<Result<Vec<Value>, &'static str>>()?,
+            )),
+            v => Ok(v.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_resolve_refs() {
+        let dir = std::env::temp_dir().join(format!("refs-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("defs.json"),
+            r##"{"defs": {"id": {"minimum": -1}, "user": {"id": {"$ref": "#/defs/id"}}}}"##,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("main.json"),
+            r##"{"user": {"$ref": "defs.json#/defs/user"}, "items": [{"$ref": "#/local"}],
+                "local": {"a~b": 1}, "whole": {"$ref": "defs.json"}}"##,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("cycle.json"),
+            r##"{"a": {"$ref": "#/b"}, "b": [{"$ref": "#/a"}]}"##,
+        )
+        .unwrap();
+        let v = resolve_file(&dir.join("main.json"));
+        let cycle = resolve_file(&dir.join("cycle.json"));
+        let missing = resolve_refs(&parse(r##"{"$ref": "nope.json"}"##).unwrap(), &dir);
+        let local = resolve_refs(
+            &parse(r##"{"x": {"$ref": "#/y/a~0b"}, "y": {"a~b": [1]}}"##).unwrap(),
+            &dir,
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let exp = parse(
+            r#"{"user": {"id": {"minimum": -1}}, "items": [{"a~b": 1}], "local": {"a~b": 1},
+                "whole": {"defs": {"id": {"minimum": -1},
+                                   "user": {"id": {"minimum": -1}}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(v.unwrap(), exp);
+        assert_eq!(cycle, Err("cyclic $ref."));
+        assert!(missing.is_err());
+        assert_eq!(
+            local.unwrap(),
+            parse(r#"{"x": [1], "y": {"a~b": [1]}}"#).unwrap()
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+
+/// Check that values survive serialization and parsing unchanged.
+use crate::parser::{parse_with, Backend};
+use crate::serializer;
+use crate::value::Value;
+
+/// A place where the reparsed value differs from the original.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// The JSON pointer of the differing value, `""` for the root.
+    pub path: String,
+    /// The original value there, `None` if it had no value there.
+    pub before: Option<Value>,
+    /// The reparsed value there, `None` if it lost the value.
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundtripReport {
+    /// The serialized text that was parsed back.
+    pub text: String,
+    pub divergences: Vec<Divergence>,
+}
+
+impl RoundtripReport {
+    pub fn is_ok(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Serialize `value`, parse the text back and report where the result differs.
+/// Fails if the serialized text does not parse at all.
+pub fn roundtrip(value: &Value) -> Result<RoundtripReport, &'static str> {
+    let text = serializer::to_string(value);
+    let after = parse_with(&text, Backend::Events)?;
+    let mut divergences = vec![];
+    diff(String::new(), value, &after, &mut divergences);
+    Ok(RoundtripReport { text, divergences })
+}
+
+/// Parse `s`, then check its value round-trips, panicking with the report if not.
+pub fn assert_roundtrip(s: &str) {
+    let value = parse_with(s, Backend::Events).expect("input is not valid json");
+    let report = roundtrip(&value).expect("serialized text does not parse");
+    assert!(report.is_ok(), "round-trip diverged: {:#?}", report);
+}
+
+fn diff(path: String, before: &Value, after: &Value, out: &mut Vec<Divergence>) {
+    match (before, after) {
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let p = format!("{}/{}", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff(p, x, y, out),
+                    (x, y) => out.push(Divergence {
+                        path: p,
+                        before: x.cloned(),
+                        after: y.cloned(),
+                    }),
+                }
+            }
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for k in keys {
+                let p = format!("{}/{}", path, k.replace('~', "~0").replace('/', "~1"));
+                match (a.get(k), b.get(k)) {
+                    (Some(x), Some(y)) => diff(p, x, y, out),
+                    (x, y) => out.push(Divergence {
+                        path: p,
+                        before: x.cloned(),
+                        after: y.cloned(),
+                    }),
+                }
+            }
+        }
+        (a, b) if a != b => out.push(Divergence {
+            path,
+            before: Some(a.clone()),
+            after: Some(b.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_roundtrip() {
+        assert_roundtrip(r#"{"a": [1, 2.5, -3e-7, "q\"é\n"], "b/c": {"d": null}}"#);
+
+        let mut v = parse(r#"{"a": [1, 2], "b": {"c": 0}}"#).unwrap();
+        if let Value::Object(m) = &mut v {
+            m.insert("b".to_owned(), Value::Number(f64::NAN));
+            m.insert(
+                "x/y".to_owned(),
+                Value::Array(vec![Value::Number(f64::INFINITY)]),
+            );
+        }
+        let report = roundtrip(&v).unwrap();
+        assert!(!report.is_ok());
+        let paths: Vec<&str> = report.divergences.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["/b", "/x~1y/0"]);
+        assert_eq!(report.divergences[1].after, Some(Value::Null));
+    }
+}
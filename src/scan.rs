@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+use std::io::{BufReader, Read};
+
+/// Find the JSON documents embedded in other text, such as log lines with
+/// prefixes or program output mixing JSON with plain messages.
+use crate::parser::{parse_with, Backend};
+use crate::value::Value;
+
+/// The longest candidate `scan_json` looks at by default, 1 MiB.
+pub const MAX_CANDIDATE_LEN: usize = 1 << 20;
+
+/// Iterator over the values found by `scan_json`.
+pub struct ScanJson<R: Read> {
+    reader: BufReader<R>,
+    // bytes read but not yet scanned past, starting at `base` in the input.
+    buf: Vec<u8>,
+    base: usize,
+    // the index in `buf` of the next byte to scan.
+    pos: usize,
+    // the candidates opened and not yet balanced, outermost first.
+    open: Vec<Open>,
+    in_string: bool,
+    escaped: bool,
+    // values found and not yet returned.
+    ready: VecDeque<(usize, Value)>,
+    eof: bool,
+    done: bool,
+    max_len: usize,
+}
+
+// a candidate whose closing bracket has not been seen yet.
+struct Open {
+    start: usize,
+    closer: u8,
+    // the balanced candidates directly inside it.
+    inner: Vec<Span>,
+}
+
+// a balanced candidate, from its opening bracket to the one closing it.
+struct Span {
+    start: usize,
+    end: usize,
+    inner: Vec<Span>,
+}
+
+/// Yield every object or array in `reader` that is valid JSON, with the byte
+/// offset it starts at, skipping everything around them.
+///
+/// A candidate runs from an opening bracket to the bracket balancing it. If
+/// it does not parse, is not balanced, or grows past `MAX_CANDIDATE_LEN`
+/// bytes, the candidates balanced inside it are tried instead. The input is
+/// scanned once, strings being tracked from the outermost open candidate,
+/// so stray brackets cannot make the scan quadratic or buffer the rest of
+/// the input. A read error ends the iteration after yielding it.
+pub fn scan_json<R: Read>(reader: R) -> ScanJson<R> {
+    ScanJson {
+        reader: BufReader::new(reader),
+        buf: vec![],
+        base: 0,
+        pos: 0,
+        open: vec![],
+        in_string: false,
+        escaped: false,
+        ready: VecDeque::new(),
+        eof: false,
+        done: false,
+        max_len: MAX_CANDIDATE_LEN,
+    }
+}
+
+impl<R: Read> ScanJson<R> {
+    /// Give up on candidates longer than `max_len` bytes instead of
+    /// `MAX_CANDIDATE_LEN`, so larger documents can be found.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
+
+    // read more input, returning false at the end of it.
+    fn fill(&mut self) -> Result<bool, &'static str> {
+        if self.eof {
+            return Ok(false);
+        }
+        // drop what no open candidate needs before growing the buffer.
+        let keep = self
+            .open
+            .first()
+            .map_or(self.pos, |open| open.start - self.base);
+        if keep > 0 && keep * 2 >= self.buf.len() {
+            self.buf.drain(..keep);
+            self.base += keep;
+            self.pos -= keep;
+        }
+        let mut chunk = [0; 8192];
+        let n = self
+            .reader
+            .read(&mut chunk)
+            .map_err(|_| "failed to read input.")?;
+        self.buf.extend_from_slice(&chunk[..n]);
+        self.eof = n == 0;
+        Ok(n > 0)
+    }
+
+    fn next_value(&mut self) -> Result<Option<(usize, Value)>, &'static str> {
+        loop {
+            if let Some(found) = self.ready.pop_front() {
+                return Ok(Some(found));
+            }
+            if self.pos == self.buf.len() && !self.fill()? {
+                if self.open.is_empty() {
+                    return Ok(None);
+                }
+                self.abandon(self.open.len());
+                continue;
+            }
+            let c = self.buf[self.pos];
+            self.pos += 1;
+            let end = self.base + self.pos;
+            let max_len = self.max_len;
+            let stale = self
+                .open
+                .iter()
+                .take_while(|open| end - open.start > max_len)
+                .count();
+            if stale > 0 {
+                self.abandon(stale);
+            }
+            if self.escaped {
+                self.escaped = false;
+            } else if self.in_string {
+                match c {
+                    b'"' => self.in_string = false,
+                    b'\\' => self.escaped = true,
+                    _ => {}
+                }
+            } else {
+                match c {
+                    b'"' if !self.open.is_empty() => self.in_string = true,
+                    b'{' => self.push(end - 1, b'}'),
+                    b'[' => self.push(end - 1, b']'),
+                    b'}' | b']' => match self.open.last() {
+                        Some(open) if open.closer == c => self.close(end),
+                        Some(_) => self.abandon(self.open.len()),
+                        None => {}
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, start: usize, closer: u8) {
+        self.open.push(Open {
+            start,
+            closer,
+            inner: vec![],
+        });
+    }
+
+    // balance the innermost open candidate, ending before `end`. Only the
+    // outermost is tried right away; the others wait for it to fail.
+    fn close(&mut self, end: usize) {
+        let open = self.open.pop().unwrap();
+        let span = Span {
+            start: open.start,
+            end,
+            inner: open.inner,
+        };
+        match self.open.last_mut() {
+            Some(parent) => parent.inner.push(span),
+            None => self.try_spans(vec![span]),
+        }
+    }
+
+    // give up on the `n` outermost open candidates, trying what was balanced
+    // inside them.
+    fn abandon(&mut self, n: usize) {
+        let spans: Vec<Span> = self.open.drain(..n).flat_map(|open| open.inner).collect();
+        if self.open.is_empty() {
+            self.in_string = false;
+            self.escaped = false;
+        }
+        self.try_spans(spans);
+    }
+
+    // parse each of `spans`, falling back to the spans inside one that fails.
+    fn try_spans(&mut self, spans: Vec<Span>) {
+        let mut todo: Vec<Span> = spans.into_iter().rev().collect();
+        while let Some(span) = todo.pop() {
+            let bytes = &self.buf[span.start - self.base..span.end - self.base];
+            let parsed = std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| parse_with(s, Backend::Events).ok());
+            match parsed {
+                Some(value) => self.ready.push_back((span.start, value)),
+                None => todo.extend(span.inner.into_iter().rev()),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for ScanJson<R> {
+    type Item = Result<(usize, Value), &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.next_value().transpose();
+        self.done = !matches!(item, Some(Ok(_)));
+        item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    // a reader handing out one byte at a time.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_scan_json() {
+        let text = "INFO [main] started {\"port\": 80}\nWARN {bad [1, 2]} \"{\" }\n[\"]\", {}]";
+        let found: Vec<(usize, Value)> = scan_json(text.as_bytes())
+            .collect::<Result<Vec<_>, &'static str>>()
+            .unwrap();
+        // the `[` at 57 is read as inside a string of the candidate at 52,
+        // which the `]` after it leaves unbalanced.
+        let expected = vec![
+            (20, parse(r#"{"port": 80}"#).unwrap()),
+            (43, parse("[1, 2]").unwrap()),
+            (63, parse("{}").unwrap()),
+        ];
+        assert_eq!(found, expected);
+        assert_eq!(scan_json(OneByte(text.as_bytes())).count(), 3);
+        assert_eq!(scan_json("no json { here".as_bytes()).count(), 0);
+        let mut scan = scan_json(text.as_bytes());
+        scan.set_max_len(10);
+        let found: Vec<usize> = scan.map(|r| r.unwrap().0).collect();
+        assert_eq!(found, vec![43, 63]);
+        let stray = "{".repeat(100_000) + "[1]";
+        let mut scan = scan_json(stray.as_bytes());
+        scan.set_max_len(100);
+        assert_eq!(scan.next(), Some(Ok((100_000, parse("[1]").unwrap()))));
+        // every bracket is scanned once, however many are left open.
+        let stray = "[".repeat(1_000_000) + "{\"a\": 1}";
+        assert_eq!(
+            scan_json(stray.as_bytes()).collect::<Vec<_>>(),
+            vec![Ok((1_000_000, parse(r#"{"a": 1}"#).unwrap()))]
+        );
+    }
+}
@@ -0,0 +1,501 @@
+use std::collections::hash_map;
+use std::io::{self, Write};
+
+/// Serialize `Value`s back into JSON text.
+use crate::digest::Sha256;
+use crate::value::Value;
+
+/// How `to_string_with` and `to_writer_with` write JSON.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SerializerOptions {
+    /// Escape `/` after `<` and the line terminators U+2028 and U+2029, so the
+    /// output can be embedded in a `<script>` element or a JavaScript string.
+    pub html_safe: bool,
+    /// Write one member per line with this layout instead of compact JSON.
+    pub pretty: Option<Pretty>,
+    /// Write object members ordered by key, comparing UTF-16 code units as
+    /// RFC 8785 does, rather than in map order.
+    pub sort_keys: bool,
+}
+
+/// The layout of pretty-printed JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pretty {
+    /// Spaces per level of nesting.
+    pub indent: usize,
+    /// Arrays and objects that fit on the rest of their line within this many
+    /// columns are kept on one line; larger ones are broken up. With `0`
+    /// every non-empty array and object is broken up.
+    pub width: usize,
+}
+
+impl Default for Pretty {
+    fn default() -> Pretty {
+        Pretty {
+            indent: 2,
+            width: 80,
+        }
+    }
+}
+
+/// Serialize `value` into a compact JSON string.
+pub fn to_string(value: &Value) -> String {
+    to_string_with(value, &SerializerOptions::default())
+}
+
+/// Serialize `value` as compact JSON into `writer`.
+pub fn to_writer<W: Write>(writer: &mut W, value: &Value) -> Result<(), &'static str> {
+    to_writer_with(writer, value, &SerializerOptions::default())
+}
+
+/// Serialize `value` into its canonical form: compact, with members ordered
+/// by key. Equal values have the same canonical form, which is the one
+/// RFC 8785 specifies for numbers the shortest round-trip form represents.
+pub fn to_canonical_string(value: &Value) -> String {
+    to_string_with(value, &CANONICAL)
+}
+
+const CANONICAL: SerializerOptions = SerializerOptions {
+    html_safe: false,
+    pretty: None,
+    sort_keys: true,
+};
+
+impl Value {
+    /// A SHA-256 hex digest of the canonical form, usable as an HTTP entity
+    /// tag once quoted. Equal values have equal tags.
+    pub fn etag(&self) -> String {
+        let mut digest = Sha256::new();
+        write_value(&mut digest, self, &CANONICAL).unwrap(); // hashing cannot fail.
+        digest.finish_hex()
+    }
+}
+
+/// Like `to_string`, with `options`.
+pub fn to_string_with(value: &Value, options: &SerializerOptions) -> String {
+    let mut buf = vec![];
+    write_value(&mut buf, value, options).unwrap(); // writing into a Vec cannot fail.
+    String::from_utf8(buf).unwrap()
+}
+
+/// Like `to_writer`, with `options`.
+pub fn to_writer_with<W: Write>(
+    writer: &mut W,
+    value: &Value,
+    options: &SerializerOptions,
+) -> Result<(), &'static str> {
+    write_value(writer, value, options).map_err(|_| "failed to write output.")
+}
+
+// an array or object being written, with its members still to write and
+// whether the next one is the first.
+enum Frame<'a> {
+    Array(std::slice::Iter<'a, Value>, bool),
+    Object(Members<'a>, bool),
+}
+
+// the members of an object in map order, or sorted for `sort_keys`.
+enum Members<'a> {
+    Unsorted(hash_map::Iter<'a, String, Value>),
+    Sorted(std::vec::IntoIter<(&'a String, &'a Value)>),
+}
+
+impl<'a> Members<'a> {
+    fn new(m: &'a hash_map::HashMap<String, Value>, options: &SerializerOptions) -> Members<'a> {
+        if !options.sort_keys {
+            return Members::Unsorted(m.iter());
+        }
+        let mut members: Vec<(&String, &Value)> = m.iter().collect();
+        members.sort_unstable_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+        Members::Sorted(members.into_iter())
+    }
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (&'a String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Members::Unsorted(it) => it.next(),
+            Members::Sorted(it) => it.next(),
+        }
+    }
+}
+
+fn write_value<W: Write>(w: &mut W, value: &Value, options: &SerializerOptions) -> io::Result<()> {
+    match options.pretty {
+        None => write_flat(w, value, options, b",", b":"),
+        Some(pretty) => write_pretty(&mut Columns { w, column: 0 }, value, options, pretty),
+    }
+}
+
+// write `value` on one line with the given separators. Containers are tracked
+// on an explicit stack rather than by recursion, so values of any depth can be
+// written.
+fn write_flat<W: Write>(
+    w: &mut W,
+    value: &Value,
+    options: &SerializerOptions,
+    comma: &[u8],
+    colon: &[u8],
+) -> io::Result<()> {
+    let mut stack = vec![];
+    let mut next = Some(value);
+    loop {
+        match next.take() {
+            Some(Value::Null) => w.write_all(b"null")?,
+            Some(Value::Bool(b)) => write!(w, "{}", b)?,
+            Some(Value::Number(n)) => write_number(w, *n)?,
+            Some(Value::String(s)) => write_string(w, s, options)?,
+            Some(Value::Array(v)) => {
+                w.write_all(b"[")?;
+                stack.push(Frame::Array(v.iter(), true));
+            }
+            Some(Value::Object(m)) => {
+                w.write_all(b"{")?;
+                stack.push(Frame::Object(Members::new(m, options), true));
+            }
+            None => {}
+        }
+        let (item, first) = match stack.last_mut() {
+            None => return Ok(()),
+            Some(Frame::Array(items, first)) => (items.next().map(|v| (None, v)), first),
+            Some(Frame::Object(members, first)) => {
+                (members.next().map(|(k, v)| (Some(k), v)), first)
+            }
+        };
+        match item {
+            Some((key, item)) => {
+                if !std::mem::replace(first, false) {
+                    w.write_all(comma)?;
+                }
+                if let Some(key) = key {
+                    write_string(w, key, options)?;
+                    w.write_all(colon)?;
+                }
+                next = Some(item);
+            }
+            None => match stack.pop() {
+                Some(Frame::Array(..)) => w.write_all(b"]")?,
+                _ => w.write_all(b"}")?,
+            },
+        }
+    }
+}
+
+fn write_pretty<W: Write>(
+    w: &mut Columns<W>,
+    value: &Value,
+    options: &SerializerOptions,
+    pretty: Pretty,
+) -> io::Result<()> {
+    let mut stack = vec![];
+    let mut next = Some(value);
+    loop {
+        if let Some(value) = next.take() {
+            let frame = match value {
+                Value::Array(v) if !v.is_empty() => Some(Frame::Array(v.iter(), true)),
+                Value::Object(m) if !m.is_empty() => {
+                    Some(Frame::Object(Members::new(m, options), true))
+                }
+                _ => None,
+            };
+            let line = match frame {
+                Some(_) => one_line(value, options, pretty.width.saturating_sub(w.column)),
+                None => None,
+            };
+            match (frame, line) {
+                (Some(frame), None) => {
+                    w.write_all(match frame {
+                        Frame::Array(..) => b"[",
+                        Frame::Object(..) => b"{",
+                    })?;
+                    stack.push(frame);
+                }
+                (_, Some(line)) => w.write_all(&line)?,
+                (None, None) => write_flat(w, value, options, b", ", b": ")?,
+            }
+        }
+        let depth = stack.len();
+        let (item, first) = match stack.last_mut() {
+            None => return Ok(()),
+            Some(Frame::Array(items, first)) => (items.next().map(|v| (None, v)), first),
+            Some(Frame::Object(members, first)) => {
+                (members.next().map(|(k, v)| (Some(k), v)), first)
+            }
+        };
+        match item {
+            Some((key, item)) => {
+                if !std::mem::replace(first, false) {
+                    w.write_all(b",")?;
+                }
+                newline(w, depth * pretty.indent)?;
+                if let Some(key) = key {
+                    write_string(w, key, options)?;
+                    w.write_all(b": ")?;
+                }
+                next = Some(item);
+            }
+            None => {
+                newline(w, (depth - 1) * pretty.indent)?;
+                match stack.pop() {
+                    Some(Frame::Array(..)) => w.write_all(b"]")?,
+                    _ => w.write_all(b"}")?,
+                }
+            }
+        }
+    }
+}
+
+fn newline<W: Write>(w: &mut W, indent: usize) -> io::Result<()> {
+    w.write_all(b"\n")?;
+    for _ in 0..indent {
+        w.write_all(b" ")?;
+    }
+    Ok(())
+}
+
+// `value` written on one line if that takes at most `width` bytes.
+fn one_line(value: &Value, options: &SerializerOptions, width: usize) -> Option<Vec<u8>> {
+    let mut line = Limited {
+        buf: vec![],
+        limit: width,
+    };
+    write_flat(&mut line, value, options, b", ", b": ").ok()?;
+    Some(line.buf)
+}
+
+// a writer keeping track of the column it is at.
+struct Columns<'a, W: Write> {
+    w: &'a mut W,
+    column: usize,
+}
+
+impl<W: Write> Write for Columns<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.w.write_all(buf)?;
+        for &b in buf {
+            match b {
+                b'\n' => self.column = 0,
+                // count characters rather than UTF-8 continuation bytes.
+                b if b & 0xc0 != 0x80 => self.column += 1,
+                _ => {}
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+// a buffer failing writes once `limit` bytes would be exceeded, so measuring
+// a large value stops early.
+struct Limited {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for Limited {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.limit {
+            return Err(io::Error::other("line too long"));
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// JSON has no representation for NaN and the infinities, they become null.
+// numbers are written with the fewest digits that parse back to the same
+// `f64`, integers without a fraction, and an exponent only outside
+// 1e-7..1e21, as JavaScript does. That includes writing -0 as `0`, which
+// keeps the canonical form of equal values the same.
+fn write_number<W: Write>(w: &mut W, n: f64) -> io::Result<()> {
+    if !n.is_finite() {
+        return w.write_all(b"null");
+    }
+    if n == 0.0 {
+        return w.write_all(b"0");
+    }
+    // `{:e}` gives the shortest round-trip digits as `d.ddde-x`.
+    let scientific = format!("{:e}", n);
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    if (-6..21).contains(&exponent) {
+        write!(w, "{}", n)
+    } else {
+        let sign = if exponent > 0 { "+" } else { "-" };
+        write!(w, "{}e{}{}", mantissa, sign, exponent.abs())
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str, options: &SerializerOptions) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    let mut previous = '\0';
+    for c in s.chars() {
+        match c {
+            '/' if options.html_safe && previous == '<' => w.write_all(b"\\/")?,
+            '\u{2028}' if options.html_safe => w.write_all(b"\\u2028")?,
+            '\u{2029}' if options.html_safe => w.write_all(b"\\u2029")?,
+            '"' => w.write_all(b"\\\"")?,
+            '\\' => w.write_all(b"\\\\")?,
+            '\n' => w.write_all(b"\\n")?,
+            '\r' => w.write_all(b"\\r")?,
+            '\t' => w.write_all(b"\\t")?,
+            '\u{08}' => w.write_all(b"\\b")?,
+            '\u{0c}' => w.write_all(b"\\f")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+        previous = c;
+    }
+    w.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(to_string(&Value::Null), "null");
+        assert_eq!(to_string(&Value::Bool(true)), "true");
+        assert_eq!(to_string(&Value::Number(345.0)), "345");
+        assert_eq!(to_string(&Value::Number(1.5)), "1.5");
+        assert_eq!(to_string(&Value::Number(f64::NAN)), "null");
+        assert_eq!(
+            to_string(&Value::String("a\"b\\c\n\u{1}é".to_owned())),
+            r#""a\"b\\c\n\u0001é""#
+        );
+        assert_eq!(
+            to_string(&Value::Array(vec![Value::Null, Value::Array(vec![])])),
+            "[null,[]]"
+        );
+        let mut m = HashMap::new();
+        m.insert("k".to_owned(), Value::Array(vec![Value::Bool(false)]));
+        assert_eq!(to_string(&Value::Object(m)), r#"{"k":[false]}"#);
+        let s = r#"{"key": false , "k2": {"k3": [1, 2.5, "x"]}}"#;
+        let v = parse(s).unwrap();
+        assert_eq!(parse(&to_string(&v)).unwrap(), v);
+    }
+
+    #[test]
+    fn test_numbers() {
+        let cases = [
+            (1.0, "1"),
+            (-25.0, "-25"),
+            (0.1, "0.1"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e+21"),
+            (1.5e300, "1.5e+300"),
+            (0.000001, "0.000001"),
+            (1.25e-7, "1.25e-7"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (5e-324, "5e-324"),
+        ];
+        for &(n, s) in cases.iter() {
+            assert_eq!(to_string(&Value::Number(n)), s);
+            let back = crate::parser::parse_with(s, crate::parser::Backend::Events).unwrap();
+            assert_eq!(back, Value::Number(n));
+        }
+    }
+
+    #[test]
+    fn test_html_safe() {
+        let v = Value::String("</script><b>a/b</b>\u{2028}\u{2029}".to_owned());
+        let options = SerializerOptions {
+            html_safe: true,
+            ..SerializerOptions::default()
+        };
+        assert_eq!(
+            to_string_with(&v, &options),
+            r#""<\/script><b>a/b<\/b>\u2028\u2029""#
+        );
+        assert_eq!(parse(&to_string_with(&v, &options)).unwrap(), v);
+        assert_eq!(to_string(&v), "\"</script><b>a/b</b>\u{2028}\u{2029}\"");
+    }
+
+    #[test]
+    fn test_canonical() {
+        let value = crate::parser::parse_with(
+            concat!(
+                "{\"\u{fb01}\": 0.5, \"\u{1f600}\": 0, ",
+                "\"b\": 1e21, \"a\": [{\"d\": null, \"c\": \"x\"}]}",
+            ),
+            crate::parser::Backend::Events,
+        )
+        .unwrap();
+        let canonical =
+            "{\"a\":[{\"c\":\"x\",\"d\":null}],\"b\":1e+21,\"\u{1f600}\":0,\"\u{fb01}\":0.5}";
+        assert_eq!(to_canonical_string(&value), canonical);
+        assert_eq!(
+            value.etag(),
+            "061f3fecff5471d21337cc99aad09af2b976000056f9d1ac75bcc09f5a18a704"
+        );
+        assert_eq!(Value::Number(0.0).etag(), Value::Number(-0.0).etag());
+        assert!(Value::Number(0.0) == Value::Number(-0.0));
+        assert_eq!(to_canonical_string(&Value::Number(-0.0)), "0");
+        // a message spanning several blocks.
+        let mut digest = Sha256::new();
+        digest.update(&[b'a'; 1000]);
+        assert_eq!(
+            digest.finish_hex(),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_pretty() {
+        let v =
+            parse(r#"[[1, 2, 3], {"k": "v"}, [], ["aaaaaaaaaa", "bbbbbbbbbb", [1, 2]]]"#).unwrap();
+        let pretty = |indent, width| SerializerOptions {
+            pretty: Some(Pretty { indent, width }),
+            ..SerializerOptions::default()
+        };
+        assert_eq!(
+            to_string_with(&v, &pretty(2, 20)),
+            r#"[
+  [1, 2, 3],
+  {"k": "v"},
+  [],
+  [
+    "aaaaaaaaaa",
+    "bbbbbbbbbb",
+    [1, 2]
+  ]
+]"#
+        );
+        assert_eq!(to_string_with(&v, &pretty(1, 0)).lines().count(), 19);
+        assert_eq!(
+            to_string_with(&v, &Default::default())
+                .replace(",", ", ")
+                .replace(":", ": "),
+            to_string_with(&v, &pretty(2, 80))
+        );
+        assert_eq!(parse(&to_string_with(&v, &pretty(2, 0))).unwrap(), v);
+    }
+
+    #[test]
+    fn test_serialize_deep() {
+        let depth = 100_000;
+        let mut v = Value::Null;
+        for _ in 0..depth {
+            v = Value::Array(vec![v]);
+        }
+        let s = to_string(&v);
+        assert_eq!(s, "[".repeat(depth) + "null" + &"]".repeat(depth));
+        // take the value apart level by level, dropping it whole would recurse.
+        while let Value::Array(mut items) = v {
+            v = items.pop().unwrap_or(Value::Null);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+
+/// Structural checks on values, built in code rather than written as a JSON
+/// Schema document.
+use crate::value::{Value, ValueKind};
+
+/// The expected shape of a value, e.g. `Shape::object().required("id",
+/// Shape::number()).optional("tags", Shape::array_of(Shape::string()))`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    kind: Kind,
+    nullable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Any,
+    Null,
+    Bool,
+    Number,
+    Integer,
+    String,
+    ArrayOf(Box<Shape>),
+    Object {
+        // each field's key, whether it is required, and its shape.
+        fields: Vec<(String, bool, Shape)>,
+        deny_unknown: bool,
+    },
+}
+
+/// Why a value does not have its shape.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeErrorKind {
+    /// The value has the type `found` instead of `expected`.
+    Type {
+        expected: &'static str,
+        found: ValueKind,
+    },
+    /// A required member is missing.
+    Missing,
+    /// A member not in the shape, with `deny_unknown`.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeError {
+    /// The JSON pointer of the offending value or member.
+    pub path: String,
+    pub kind: ShapeErrorKind,
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        match &self.kind {
+            ShapeErrorKind::Type { expected, found } => {
+                write!(f, "{}: expected {}, found {}", path, expected, found)
+            }
+            ShapeErrorKind::Missing => write!(f, "{}: missing", path),
+            ShapeErrorKind::Unknown => write!(f, "{}: unknown member", path),
+        }
+    }
+}
+
+impl Shape {
+    fn new(kind: Kind) -> Shape {
+        Shape {
+            kind,
+            nullable: false,
+        }
+    }
+
+    /// Any value.
+    pub fn any() -> Shape {
+        Shape::new(Kind::Any)
+    }
+
+    pub fn null() -> Shape {
+        Shape::new(Kind::Null)
+    }
+
+    pub fn bool() -> Shape {
+        Shape::new(Kind::Bool)
+    }
+
+    pub fn number() -> Shape {
+        Shape::new(Kind::Number)
+    }
+
+    /// A number without a fractional part.
+    pub fn integer() -> Shape {
+        Shape::new(Kind::Integer)
+    }
+
+    pub fn string() -> Shape {
+        Shape::new(Kind::String)
+    }
+
+    /// An array whose elements all have the shape `item`.
+    pub fn array_of(item: Shape) -> Shape {
+        Shape::new(Kind::ArrayOf(Box::new(item)))
+    }
+
+    /// An object, with the members added by `required` and `optional`.
+    /// Other members are allowed unless `deny_unknown` is called.
+    pub fn object() -> Shape {
+        Shape::new(Kind::Object {
+            fields: vec![],
+            deny_unknown: false,
+        })
+    }
+
+    /// Also accept `null`.
+    pub fn nullable(mut self) -> Shape {
+        self.nullable = true;
+        self
+    }
+
+    /// Require the member `key` with the shape `shape`.
+    ///
+    /// Panics if this is not an object shape.
+    pub fn required(self, key: &str, shape: Shape) -> Shape {
+        self.field(key, true, shape)
+    }
+
+    /// Allow the member `key`, which must have the shape `shape` if present.
+    ///
+    /// Panics if this is not an object shape.
+    pub fn optional(self, key: &str, shape: Shape) -> Shape {
+        self.field(key, false, shape)
+    }
+
+    /// Reject members that were not added with `required` or `optional`.
+    ///
+    /// Panics if this is not an object shape.
+    pub fn deny_unknown(mut self) -> Shape {
+        match &mut self.kind {
+            Kind::Object { deny_unknown, .. } => *deny_unknown = true,
+            _ => panic!("only object shapes have members"),
+        }
+        self
+    }
+
+    fn field(mut self, key: &str, required: bool, shape: Shape) -> Shape {
+        match &mut self.kind {
+            Kind::Object { fields, .. } => fields.push((key.to_owned(), required, shape)),
+            _ => panic!("only object shapes have members"),
+        }
+        self
+    }
+
+    /// Check `value` against the shape, returning every mismatch found.
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<ShapeError>> {
+        let mut errors = vec![];
+        self.check(value, &mut String::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check(&self, value: &Value, path: &mut String, errors: &mut Vec<ShapeError>) {
+        if self.nullable && value.is_null() {
+            return;
+        }
+        let expected = match (&self.kind, value) {
+            (Kind::Any, _)
+            | (Kind::Null, Value::Null)
+            | (Kind::Bool, Value::Bool(_))
+            | (Kind::Number, Value::Number(_))
+            | (Kind::String, Value::String(_)) => return,
+            (Kind::Integer, Value::Number(n)) if n.fract() == 0.0 => return,
+            (Kind::ArrayOf(item), Value::Array(v)) => {
+                for (i, element) in v.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("/{}", i));
+                    item.check(element, path, errors);
+                    path.truncate(len);
+                }
+                return;
+            }
+            (
+                Kind::Object {
+                    fields,
+                    deny_unknown,
+                },
+                Value::Object(m),
+            ) => {
+                for (key, required, shape) in fields {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&escape(key));
+                    match m.get(key) {
+                        Some(member) => shape.check(member, path, errors),
+                        None if *required => errors.push(ShapeError {
+                            path: path.clone(),
+                            kind: ShapeErrorKind::Missing,
+                        }),
+                        None => {}
+                    }
+                    path.truncate(len);
+                }
+                if *deny_unknown {
+                    let mut unknown: Vec<&String> = m
+                        .keys()
+                        .filter(|k| !fields.iter().any(|(key, _, _)| key == *k))
+                        .collect();
+                    unknown.sort();
+                    for key in unknown {
+                        errors.push(ShapeError {
+                            path: format!("{}/{}", path, escape(key)),
+                            kind: ShapeErrorKind::Unknown,
+                        });
+                    }
+                }
+                return;
+            }
+            (Kind::Null, _) => "null",
+            (Kind::Bool, _) => "boolean",
+            (Kind::Number, _) => "number",
+            (Kind::Integer, _) => "integer",
+            (Kind::String, _) => "string",
+            (Kind::ArrayOf(_), _) => "array",
+            (Kind::Object { .. }, _) => "object",
+        };
+        errors.push(ShapeError {
+            path: path.clone(),
+            kind: ShapeErrorKind::Type {
+                expected,
+                found: value.kind(),
+            },
+        });
+    }
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_shape() {
+        let shape = Shape::object()
+            .required("id", Shape::integer())
+            .optional("tags", Shape::array_of(Shape::string()))
+            .optional(
+                "owner",
+                Shape::object().required("name", Shape::string()).nullable(),
+            )
+            .deny_unknown();
+        assert_eq!(
+            shape.validate(&parse(r#"{"id": 1, "tags": ["a"], "owner": null}"#).unwrap()),
+            Ok(())
+        );
+        let errors = shape
+            .validate(&parse(r#"{"id": 1.5, "tags": ["a", 2], "owner": {}, "x": 0}"#).unwrap())
+            .unwrap_err();
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "/id: expected integer, found number",
+                "/tags/1: expected string, found number",
+                "/owner/name: missing",
+                "/x: unknown member",
+            ]
+        );
+        assert_eq!(
+            Shape::array_of(Shape::any()).validate(&Value::Null),
+            Err(vec![ShapeError {
+                path: String::new(),
+                kind: ShapeErrorKind::Type {
+                    expected: "array",
+                    found: ValueKind::Null,
+                },
+            }])
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Drive user-defined builders from the parser, so documents can be built
+/// straight into other data structures without an intermediate `Value`.
+use crate::event::{Event, EventReader};
+use crate::value::Value;
+
+/// Receives a document as a well-formed sequence of calls: every container is
+/// opened and closed in order and each object value is preceded by its key.
+/// Returning an error stops parsing.
+pub trait ValueSink {
+    type Output;
+
+    fn start_object(&mut self) -> Result<(), &'static str>;
+    fn end_object(&mut self) -> Result<(), &'static str>;
+    fn start_array(&mut self) -> Result<(), &'static str>;
+    fn end_array(&mut self) -> Result<(), &'static str>;
+    fn key(&mut self, key: String) -> Result<(), &'static str>;
+    fn null(&mut self) -> Result<(), &'static str>;
+    fn bool(&mut self, b: bool) -> Result<(), &'static str>;
+    fn number(&mut self, n: f64) -> Result<(), &'static str>;
+    fn string(&mut self, s: String) -> Result<(), &'static str>;
+    /// Called once the whole document has been delivered.
+    fn finish(self) -> Result<Self::Output, &'static str>;
+}
+
+/// Parse the document in `reader` into `sink` and return what it built.
+pub fn parse_into<R: Read, S: ValueSink>(
+    reader: R,
+    mut sink: S,
+) -> Result<S::Output, &'static str> {
+    let mut events = EventReader::new(reader);
+    while let Some(event) = events.next_event()? {
+        match event {
+            Event::StartObject => sink.start_object()?,
+            Event::EndObject => sink.end_object()?,
+            Event::StartArray => sink.start_array()?,
+            Event::EndArray => sink.end_array()?,
+            Event::Key(k) => sink.key(k)?,
+            Event::Null => sink.null()?,
+            Event::Bool(b) => sink.bool(b)?,
+            Event::Number(n) => sink.number(n)?,
+            Event::String(s) => sink.string(s)?,
+        }
+    }
+    sink.finish()
+}
+
+/// The sink building `Value`s.
+#[derive(Debug, Default)]
+pub struct ValueBuilder {
+    // open containers with the key of the value being read in each object.
+    stack: Vec<(Value, Option<String>)>,
+    root: Option<Value>,
+}
+
+impl ValueBuilder {
+    pub fn new() -> ValueBuilder {
+        ValueBuilder::default()
+    }
+
+    fn value(&mut self, value: Value) -> Result<(), &'static str> {
+        match self.stack.last_mut() {
+            Some((Value::Array(v), _)) => v.push(value),
+            Some((Value::Object(m), key)) => {
+                m.insert(key.take().ok_or("key expected.")?, value);
+            }
+            Some(_) => unreachable!("only containers are stacked"),
+            None if self.root.is_none() => self.root = Some(value),
+            None => return Err("trailing string after json."),
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), &'static str> {
+        let (value, _) = self.stack.pop().ok_or("unbalanced container.")?;
+        self.value(value)
+    }
+}
+
+impl ValueSink for ValueBuilder {
+    type Output = Value;
+
+    fn start_object(&mut self) -> Result<(), &'static str> {
+        self.stack.push((Value::Object(HashMap::new()), None));
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), &'static str> {
+        self.end()
+    }
+
+    fn start_array(&mut self) -> Result<(), &'static str> {
+        self.stack.push((Value::Array(vec![]), None));
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), &'static str> {
+        self.end()
+    }
+
+    fn key(&mut self, key: String) -> Result<(), &'static str> {
+        match self.stack.last_mut() {
+            Some((Value::Object(_), k)) => {
+                *k = Some(key);
+                Ok(())
+            }
+            _ => Err("key outside of an object."),
+        }
+    }
+
+    fn null(&mut self) -> Result<(), &'static str> {
+        self.value(Value::Null)
+    }
+
+    fn bool(&mut self, b: bool) -> Result<(), &'static str> {
+        self.value(Value::Bool(b))
+    }
+
+    fn number(&mut self, n: f64) -> Result<(), &'static str> {
+        self.value(Value::Number(n))
+    }
+
+    fn string(&mut self, s: String) -> Result<(), &'static str> {
+        self.value(Value::String(s))
+    }
+
+    fn finish(self) -> Result<Value, &'static str> {
+        if !self.stack.is_empty() {
+            return Err("unexpected end of input.");
+        }
+        self.root.ok_or("unexpected end of input.")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    // sums the numbers found under the key "n" at any depth.
+    #[derive(Default)]
+    struct SumN {
+        key: Option<String>,
+        total: f64,
+    }
+
+    impl ValueSink for SumN {
+        type Output = f64;
+
+        fn start_object(&mut self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn end_object(&mut self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn start_array(&mut self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn end_array(&mut self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn key(&mut self, key: String) -> Result<(), &'static str> {
+            self.key = Some(key);
+            Ok(())
+        }
+        fn null(&mut self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn bool(&mut self, _: bool) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn number(&mut self, n: f64) -> Result<(), &'static str> {
+            if self.key.as_deref() == Some("n") {
+                self.total += n;
+            }
+            Ok(())
+        }
+        fn string(&mut self, s: String) -> Result<(), &'static str> {
+            if s == "stop" {
+                return Err("stopped.");
+            }
+            Ok(())
+        }
+        fn finish(self) -> Result<f64, &'static str> {
+            Ok(self.total)
+        }
+    }
+
+    #[test]
+    fn test_parse_into() {
+        let doc = r#"{"a": [{"n": 1}, {"n": 2.5, "m": 9}], "n": 3, "s": "x"}"#;
+        assert_eq!(parse_into(doc.as_bytes(), SumN::default()), Ok(6.5));
+        assert_eq!(
+            parse_into(r#"["stop"]"#.as_bytes(), SumN::default()),
+            Err("stopped.")
+        );
+        assert_eq!(
+            parse_into(doc.as_bytes(), ValueBuilder::new()).unwrap(),
+            parse(doc).unwrap()
+        );
+        assert!(parse_into("[1, {".as_bytes(), ValueBuilder::new()).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// A string stored inline when short, for keys and values of `CompactValue`.
+use crate::value::GenericValue;
+
+// the longest string kept inline; with the length byte it fills the 24 bytes
+// a `String` takes.
+const INLINE: usize = 22;
+
+/// An immutable string that holds up to 22 bytes without allocating, which
+/// covers most object keys and many short values.
+#[derive(Clone)]
+pub struct SmallString(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, buf: [u8; INLINE] },
+    Heap(Box<str>),
+}
+
+/// A `Value` whose keys and strings are `SmallString`s, see
+/// `parser::parse_compact`.
+pub type CompactValue = GenericValue<f64, SmallString>;
+
+impl SmallString {
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Inline { len, buf } => {
+                std::str::from_utf8(&buf[..*len as usize]).expect("inline bytes come from a str")
+            }
+            Repr::Heap(s) => s,
+        }
+    }
+
+    /// Whether the string is stored without a heap allocation.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+impl From<&str> for SmallString {
+    fn from(s: &str) -> SmallString {
+        if s.len() > INLINE {
+            return SmallString(Repr::Heap(s.into()));
+        }
+        let mut buf = [0; INLINE];
+        buf[..s.len()].copy_from_slice(s.as_bytes());
+        SmallString(Repr::Inline {
+            len: s.len() as u8,
+            buf,
+        })
+    }
+}
+
+impl From<String> for SmallString {
+    fn from(s: String) -> SmallString {
+        if s.len() > INLINE {
+            return SmallString(Repr::Heap(s.into_boxed_str()));
+        }
+        SmallString::from(s.as_str())
+    }
+}
+
+impl Deref for SmallString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// hashes and compares like `str`, so maps keyed by `SmallString` can be looked
+// up with a `&str`.
+impl Borrow<str> for SmallString {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Hash for SmallString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq for SmallString {
+    fn eq(&self, other: &SmallString) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SmallString {}
+
+impl PartialEq<str> for SmallString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_small_string() {
+        assert_eq!(
+            std::mem::size_of::<SmallString>(),
+            std::mem::size_of::<String>()
+        );
+        let short = SmallString::from("user_id");
+        assert!(short.is_inline());
+        assert_eq!(short, "user_id");
+        let edge = SmallString::from("é".repeat(11));
+        assert!(edge.is_inline());
+        assert_eq!(edge.len(), 22);
+        let long = SmallString::from("a key that is longer than inline");
+        assert!(!long.is_inline());
+        assert_eq!(long.to_string(), "a key that is longer than inline");
+
+        let mut m = HashMap::new();
+        m.insert(short.clone(), 1);
+        assert_eq!(m.get("user_id"), Some(&1));
+        assert_eq!(format!("{:?}", short), "\"user_id\"");
+    }
+}
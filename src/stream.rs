@@ -0,0 +1,220 @@
+use std::io::Read;
+use std::marker::PhantomData;
+
+/// Extract the subtrees matching a path query, or the elements of a root
+/// array, from a stream, building `Value`s only for what is returned.
+use crate::access::FromValue;
+use crate::event::{Event, EventReader};
+use crate::query::{Query, Segment};
+use crate::value::Value;
+
+// an open container and the position of the value currently being read in it.
+enum Frame {
+    Object(Option<String>),
+    Array(Option<usize>),
+}
+
+/// Iterator over the values matched by `stream_select`.
+pub struct Select<R: Read> {
+    events: EventReader<R>,
+    path: Vec<Segment>,
+    frames: Vec<Frame>,
+}
+
+/// Yield every subtree of the document in `reader` located by `query`.
+///
+/// The query must be path-shaped (see `Query::path`); a path that does not
+/// exist in the document yields nothing rather than `null`.
+pub fn stream_select<R: Read>(reader: R, query: &Query) -> Result<Select<R>, &'static str> {
+    Ok(Select {
+        events: EventReader::new(reader),
+        path: query.path()?,
+        frames: vec![],
+    })
+}
+
+fn is_match(segment: &Segment, frame: &Frame) -> bool {
+    match (segment, frame) {
+        (Segment::Key(k), Frame::Object(Some(key))) => k == key,
+        (Segment::Index(i), Frame::Array(Some(n))) => i == n,
+        (Segment::Elements, Frame::Array(_))
+        | (Segment::Values, Frame::Object(_))
+        | (Segment::Children, _) => true,
+        _ => false,
+    }
+}
+
+impl<R: Read> Select<R> {
+    fn next_match(&mut self) -> Result<Option<Value>, &'static str> {
+        while let Some(event) = self.events.next_event()? {
+            match event {
+                Event::Key(k) => {
+                    if let Some(Frame::Object(key)) = self.frames.last_mut() {
+                        *key = Some(k);
+                    }
+                    continue;
+                }
+                Event::EndObject | Event::EndArray => {
+                    self.frames.pop();
+                    continue;
+                }
+                _ => {}
+            }
+            // a value starts here, move the enclosing array to its index.
+            if let Some(Frame::Array(index)) = self.frames.last_mut() {
+                *index = Some(index.map_or(0, |i| i + 1));
+            }
+            let on_path = self
+                .frames
+                .iter()
+                .zip(&self.path)
+                .all(|(f, s)| is_match(s, f));
+            let is_container = matches!(event, Event::StartObject | Event::StartArray);
+            if on_path && self.frames.len() == self.path.len() {
+                return self.events.build_value(event).map(Some);
+            }
+            if on_path && is_container {
+                self.frames.push(match event {
+                    Event::StartObject => Frame::Object(None),
+                    _ => Frame::Array(None),
+                });
+            } else if is_container {
+                // skip the rest of the container and its closing bracket.
+                while self.events.skip_value()? {}
+                self.events.next_event()?;
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for Select<R> {
+    type Item = Result<Value, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_match().transpose()
+    }
+}
+
+/// Iterator over the elements read by `iter_array`.
+pub struct ArrayIter<R: Read, T> {
+    events: EventReader<R>,
+    started: bool,
+    done: bool,
+    item: PhantomData<T>,
+}
+
+/// Read the array at the root of the document in `reader` one element at a
+/// time, converting each to a `T`. Only one element is held in memory at a
+/// time. Iteration stops after the first error.
+pub fn iter_array<T: FromValue, R: Read>(reader: R) -> ArrayIter<R, T> {
+    ArrayIter {
+        events: EventReader::new(reader),
+        started: false,
+        done: false,
+        item: PhantomData,
+    }
+}
+
+impl<R: Read, T: FromValue> ArrayIter<R, T> {
+    fn next_item(&mut self) -> Result<Option<T>, &'static str> {
+        if !self.started {
+            self.started = true;
+            if self.events.next_event()? != Some(Event::StartArray) {
+                return Err("expect array");
+            }
+        }
+        let event = match self.events.next_event()? {
+            Some(Event::EndArray) => {
+                // reject anything after the array.
+                self.events.next_event()?;
+                return Ok(None);
+            }
+            Some(event) => event,
+            None => return Err("unexpected end of input."),
+        };
+        let value = self.events.build_value(event)?;
+        T::from_value(&value)
+            .map(Some)
+            .ok_or("unexpected type in array.")
+    }
+}
+
+impl<R: Read, T: FromValue> Iterator for ArrayIter<R, T> {
+    type Item = Result<T, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.next_item().transpose();
+        self.done = !matches!(item, Some(Ok(_)));
+        item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn select(doc: &str, query: &str) -> Vec<Value> {
+        let query = Query::compile(query).unwrap();
+        stream_select(doc.as_bytes(), &query)
+            .unwrap()
+            .collect::<Result<Vec<Value>, &'static str>>()
+            .unwrap()
+    }
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_owned())
+    }
+
+    #[test]
+    fn test_stream_select() {
+        let doc = r#"{"skip": {"deep": [1, [2, {"name": "x"}]]},
+            "items": [{"name": "a", "n": [1]}, {"n": 2}, {"name": "c"}],
+            "meta": {"count": 3}}"#;
+        assert_eq!(select(doc, ".items[].name"), vec![s("a"), s("c")]);
+        assert_eq!(select(doc, "items[*].name"), vec![s("a"), s("c")]);
+        assert_eq!(select(doc, ".items[2].name"), vec![s("c")]);
+        assert_eq!(select(doc, "meta.count"), vec![Value::Number(3.0)]);
+        assert_eq!(
+            select(doc, ".items[0].n"),
+            vec![Value::Array(vec![Value::Number(1.0)])]
+        );
+        assert_eq!(select(doc, ".missing"), vec![]);
+        assert_eq!(
+            select("[1, 2]", "."),
+            vec![Value::Array(vec![Value::Number(1.0), Value::Number(2.0)])]
+        );
+        assert_eq!(select(r#"{"a": {"x": 1, "y": 2}}"#, "a.*").len(), 2);
+    }
+
+    #[test]
+    fn test_stream_select_errors() {
+        let query = Query::compile(".a | length").unwrap();
+        assert!(stream_select("{}".as_bytes(), &query).is_err());
+        let query = Query::compile(".b").unwrap();
+        let mut it = stream_select(r#"{"a": [1,, "b": 1}"#.as_bytes(), &query).unwrap();
+        assert!(it.next().unwrap().is_err());
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_iter_array() {
+        let items: Vec<Result<Vec<u32>, &'static str>> =
+            iter_array("[[1, 2], [], [3]]".as_bytes()).collect();
+        assert_eq!(items, vec![Ok(vec![1, 2]), Ok(vec![]), Ok(vec![3])]);
+        let mut it = iter_array::<String, _>(r#"["a", 1, "b"]"#.as_bytes());
+        assert_eq!(it.next(), Some(Ok("a".to_owned())));
+        assert_eq!(it.next(), Some(Err("unexpected type in array.")));
+        assert_eq!(it.next(), None);
+        let mut it = iter_array::<f64, _>("{}".as_bytes());
+        assert_eq!(it.next(), Some(Err("expect array")));
+        assert_eq!(it.next(), None);
+        assert_eq!(iter_array::<f64, _>("[]".as_bytes()).count(), 0);
+        let items: Vec<_> = iter_array::<f64, _>("[1] 2".as_bytes()).collect();
+        assert_eq!(items.len(), 2);
+        assert!(items[1].is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{hash_map, HashMap};
+use std::convert::TryInto;
+use std::slice;
+
+/// A compact binary dump of parsed documents that reloads without parsing
+/// text, for caches of large documents that rarely change.
+///
+/// The tape starts with `MAGIC` and holds the values in document order, each
+/// a tag byte followed by its payload: numbers as 8 little-endian bytes,
+/// strings and object keys as a length and their bytes, and arrays and
+/// objects as a count followed by their contents. Lengths and counts are
+/// LEB128 varints.
+use crate::value::Value;
+
+/// The bytes every tape starts with, ending in the format version.
+pub const MAGIC: &[u8; 4] = b"PJT\x01";
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const NUMBER: u8 = 3;
+const STRING: u8 = 4;
+const ARRAY: u8 = 5;
+const OBJECT: u8 = 6;
+
+enum Open<'a> {
+    Array(slice::Iter<'a, Value>),
+    Object(hash_map::Iter<'a, String, Value>),
+}
+
+impl Value {
+    /// Dump the value as a tape that `from_tape_bytes` reloads.
+    pub fn to_tape_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        let mut stack = vec![];
+        let mut next = Some(self);
+        loop {
+            let value = match next.take() {
+                Some(value) => value,
+                None => match stack.last_mut() {
+                    None => return out,
+                    Some(Open::Array(it)) => match it.next() {
+                        Some(v) => v,
+                        None => {
+                            stack.pop();
+                            continue;
+                        }
+                    },
+                    Some(Open::Object(it)) => match it.next() {
+                        Some((k, v)) => {
+                            write_bytes(&mut out, k.as_bytes());
+                            v
+                        }
+                        None => {
+                            stack.pop();
+                            continue;
+                        }
+                    },
+                },
+            };
+            match value {
+                Value::Null => out.push(NULL),
+                Value::Bool(false) => out.push(FALSE),
+                Value::Bool(true) => out.push(TRUE),
+                Value::Number(n) => {
+                    out.push(NUMBER);
+                    out.extend_from_slice(&n.to_le_bytes());
+                }
+                Value::String(s) => {
+                    out.push(STRING);
+                    write_bytes(&mut out, s.as_bytes());
+                }
+                Value::Array(v) => {
+                    out.push(ARRAY);
+                    write_varint(&mut out, v.len());
+                    stack.push(Open::Array(v.iter()));
+                }
+                Value::Object(m) => {
+                    out.push(OBJECT);
+                    write_varint(&mut out, m.len());
+                    stack.push(Open::Object(m.iter()));
+                }
+            }
+        }
+    }
+
+    /// Reload a value dumped by `to_tape_bytes`.
+    pub fn from_tape_bytes(bytes: &[u8]) -> Result<Value, &'static str> {
+        let mut tape = bytes
+            .strip_prefix(&MAGIC[..])
+            .ok_or("not a tape or unsupported version.")?;
+        // open containers, the number of values still to read into each and
+        // the key of the next object member.
+        let mut stack: Vec<(Value, usize, Option<String>)> = vec![];
+        loop {
+            if let Some((Value::Object(_), _, key)) = stack.last_mut() {
+                *key = Some(read_string(&mut tape)?);
+            }
+            let mut value = match read_value(&mut tape)? {
+                (value, 0) => value,
+                (container, n) => {
+                    stack.push((container, n, None));
+                    continue;
+                }
+            };
+            // add the value to its container, closing every container
+            // that completes.
+            loop {
+                let (container, left, key) = match stack.last_mut() {
+                    Some(open) => open,
+                    None if tape.is_empty() => return Ok(value),
+                    None => return Err("trailing bytes after tape."),
+                };
+                match container {
+                    Value::Array(v) => v.push(value),
+                    Value::Object(m) => {
+                        m.insert(key.take().unwrap_or_default(), value);
+                    }
+                    _ => unreachable!("only containers are open"),
+                }
+                *left -= 1;
+                if *left > 0 {
+                    break;
+                }
+                value = stack.pop().unwrap().0;
+            }
+        }
+    }
+}
+
+// read the next value, or the start of a container along with the number of
+// values to read into it.
+fn read_value(tape: &mut &[u8]) -> Result<(Value, usize), &'static str> {
+    Ok(match take(tape, 1)?[0] {
+        NULL => (Value::Null, 0),
+        FALSE => (Value::Bool(false), 0),
+        TRUE => (Value::Bool(true), 0),
+        NUMBER => {
+            let bytes = take(tape, 8)?.try_into().unwrap();
+            (Value::Number(f64::from_le_bytes(bytes)), 0)
+        }
+        STRING => (Value::String(read_string(tape)?), 0),
+        // every element takes at least a byte, and every member two.
+        ARRAY => {
+            let n = read_varint(tape)?;
+            (Value::Array(Vec::with_capacity(n.min(tape.len()))), n)
+        }
+        OBJECT => {
+            let n = read_varint(tape)?;
+            (
+                Value::Object(HashMap::with_capacity(n.min(tape.len() / 2))),
+                n,
+            )
+        }
+        _ => return Err("invalid tape."),
+    })
+}
+
+fn take<'a>(tape: &mut &'a [u8], n: usize) -> Result<&'a [u8], &'static str> {
+    if tape.len() < n {
+        return Err("unexpected end of input.");
+    }
+    let (head, rest) = tape.split_at(n);
+    *tape = rest;
+    Ok(head)
+}
+
+fn read_string(tape: &mut &[u8]) -> Result<String, &'static str> {
+    let n = read_varint(tape)?;
+    let bytes = take(tape, n)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| "invalid utf-8 in string.")
+}
+
+fn read_varint(tape: &mut &[u8]) -> Result<usize, &'static str> {
+    let mut n: usize = 0;
+    for shift in (0..64).step_by(7) {
+        let b = take(tape, 1)?[0];
+        n |= ((b & 0x7f) as usize)
+            .checked_shl(shift)
+            .ok_or("invalid tape.")?;
+        if b & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err("invalid tape.")
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_with, Backend};
+
+    #[test]
+    fn test_tape() {
+        let doc = format!(
+            r#"{{"a": [1, -2.5e300, "é", true, false, null, [], {{}}], "b": {{"c": [["{}"]]}}}}"#,
+            "x".repeat(300)
+        );
+        let value = parse_with(&doc, Backend::Events).unwrap();
+        let tape = value.to_tape_bytes();
+        assert!(tape.starts_with(MAGIC));
+        assert_eq!(Value::from_tape_bytes(&tape), Ok(value.clone()));
+        assert_eq!(
+            Value::from_tape_bytes(&Value::Number(1.0).to_tape_bytes()),
+            Ok(Value::Number(1.0))
+        );
+        for len in 0..tape.len() {
+            assert!(Value::from_tape_bytes(&tape[..len]).is_err(), "{}", len);
+        }
+        let mut trailing = tape.clone();
+        trailing.push(NULL);
+        assert!(Value::from_tape_bytes(&trailing).is_err());
+        let mut deep = Value::Null;
+        for _ in 0..100_000 {
+            deep = Value::Array(vec![deep]);
+        }
+        let tape = deep.to_tape_bytes();
+        let reloaded = Value::from_tape_bytes(&tape).unwrap();
+        assert_eq!(reloaded.to_tape_bytes(), tape);
+        // take the values apart level by level, dropping them whole would
+        // recurse.
+        for mut v in [deep, reloaded] {
+            while let Value::Array(mut items) = v {
+                v = items.pop().unwrap_or(Value::Null);
+            }
+        }
+    }
+}
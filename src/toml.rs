@@ -0,0 +1,63 @@
+/// TOML frontend producing `Value`s, so TOML and JSON configs can be handled
+/// uniformly. Enabled by the `toml` feature.
+use crate::value::Value;
+
+/// Parse a TOML document into a `Value::Object`.
+///
+/// Integers become numbers and datetimes become their RFC 3339 strings.
+pub fn from_toml(s: &str) -> Result<Value, &'static str> {
+    let table = s
+        .parse::<::toml::Table>()
+        .map_err(|_| "cannot parse the string as toml.")?;
+    Ok(convert(::toml::Value::Table(table)))
+}
+
+fn convert(value: ::toml::Value) -> Value {
+    match value {
+        ::toml::Value::String(s) => Value::String(s),
+        ::toml::Value::Integer(i) => Value::Number(i as f64),
+        ::toml::Value::Float(f) => Value::Number(f),
+        ::toml::Value::Boolean(b) => Value::Bool(b),
+        ::toml::Value::Datetime(d) => Value::String(d.to_string()),
+        ::toml::Value::Array(v) => Value::Array(v.into_iter().map(convert).collect()),
+        ::toml::Value::Table(t) => {
+            Value::Object(t.into_iter().map(|(k, v)| (k, convert(v))).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_from_toml() {
+        let v = from_toml(
+            r#"
+title = "cfg"
+ports = [80, 443]
+
+[db]
+host = "localhost"
+ratio = 0.5
+enabled = true
+since = 1979-05-27T07:32:00Z
+
+[[users]]
+name = "a"
+"#,
+        )
+        .unwrap();
+        let exp = parse(
+            r#"{"title": "cfg", "ports": [80, 443],
+                "db": {"host": "localhost", "ratio": 0.5, "enabled": true,
+                       "since": "1979-05-27T07:32:00Z"},
+                "users": [{"name": "a"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(v, exp);
+        assert!(from_toml("a = ").is_err());
+        assert!(from_toml("a = 1\na = 2").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::mem;
+
+/// A JSON value, shared by every parser backend, serializer and format in the
+/// crate.
+pub type Value = GenericValue<f64, String>;
+
+/// A JSON value with numbers of type `N` and strings of type `S`. Embedders
+/// may pick types such as a decimal or an interned string and convert parsed
+/// values with `GenericValue::map`. Object keys use the string type too.
+///
+/// Use `Value` for the default types: default type parameters do not take
+/// part in inference, so `GenericValue::Number(0.0)` alone needs annotations.
+#[derive(Clone)]
+pub enum GenericValue<N = f64, S = String> {
+    Null,
+    Bool(bool),
+    Number(N),
+    String(S),
+    Array(Vec<GenericValue<N, S>>),
+    Object(HashMap<S, GenericValue<N, S>>),
+}
+
+/// The type of a `Value`, without its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl ValueKind {
+    /// The name of the type as JSON Schema, jq and JMESPath spell it.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::Null => "null",
+            ValueKind::Bool => "boolean",
+            ValueKind::Number => "number",
+            ValueKind::String => "string",
+            ValueKind::Array => "array",
+            ValueKind::Object => "object",
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// `{:?}` shows the enum variants, `{:#?}` shows the value as indented JSON,
+/// writing numbers, strings and keys with their own `Debug` formatting. For
+/// the default types that is JSON too, except for control characters, which
+/// are escaped as Rust does.
+impl<N: fmt::Debug, S: fmt::Debug> fmt::Debug for GenericValue<N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return write_indented(f, self, 0);
+        }
+        match self {
+            GenericValue::Null => f.write_str("Null"),
+            GenericValue::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            GenericValue::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            GenericValue::String(s) => f.debug_tuple("String").field(s).finish(),
+            GenericValue::Array(v) => f.debug_tuple("Array").field(v).finish(),
+            GenericValue::Object(m) => f.debug_tuple("Object").field(m).finish(),
+        }
+    }
+}
+
+fn write_indented<N: fmt::Debug, S: fmt::Debug>(
+    f: &mut fmt::Formatter,
+    value: &GenericValue<N, S>,
+    depth: usize,
+) -> fmt::Result {
+    let indent = |f: &mut fmt::Formatter, depth: usize| write!(f, "\n{:1$}", "", depth * 2);
+    match value {
+        GenericValue::Null => f.write_str("null"),
+        GenericValue::Bool(b) => write!(f, "{}", b),
+        GenericValue::Number(n) => write!(f, "{:?}", n),
+        GenericValue::String(s) => write!(f, "{:?}", s),
+        GenericValue::Array(v) if v.is_empty() => f.write_str("[]"),
+        GenericValue::Object(m) if m.is_empty() => f.write_str("{}"),
+        GenericValue::Array(v) => {
+            f.write_str("[")?;
+            for (i, item) in v.iter().enumerate() {
+                f.write_str(if i == 0 { "" } else { "," })?;
+                indent(f, depth + 1)?;
+                write_indented(f, item, depth + 1)?;
+            }
+            indent(f, depth)?;
+            f.write_str("]")
+        }
+        GenericValue::Object(m) => {
+            f.write_str("{")?;
+            for (i, (k, v)) in m.iter().enumerate() {
+                f.write_str(if i == 0 { "" } else { "," })?;
+                indent(f, depth + 1)?;
+                write!(f, "{:?}: ", k)?;
+                write_indented(f, v, depth + 1)?;
+            }
+            indent(f, depth)?;
+            f.write_str("}")
+        }
+    }
+}
+
+impl<N: PartialEq, S: Eq + Hash> PartialEq for GenericValue<N, S> {
+    fn eq(&self, other: &GenericValue<N, S>) -> bool {
+        match (self, other) {
+            (GenericValue::Null, GenericValue::Null) => true,
+            (GenericValue::Bool(a), GenericValue::Bool(b)) => a == b,
+            (GenericValue::Number(a), GenericValue::Number(b)) => a == b,
+            (GenericValue::String(a), GenericValue::String(b)) => a == b,
+            (GenericValue::Array(a), GenericValue::Array(b)) => a == b,
+            (GenericValue::Object(a), GenericValue::Object(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<N, S> GenericValue<N, S> {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            GenericValue::Null => ValueKind::Null,
+            GenericValue::Bool(_) => ValueKind::Bool,
+            GenericValue::Number(_) => ValueKind::Number,
+            GenericValue::String(_) => ValueKind::String,
+            GenericValue::Array(_) => ValueKind::Array,
+            GenericValue::Object(_) => ValueKind::Object,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, GenericValue::Null)
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, GenericValue::Bool(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, GenericValue::Number(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, GenericValue::String(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, GenericValue::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, GenericValue::Object(_))
+    }
+
+    /// Convert the numbers and strings of the tree, object keys included,
+    /// keeping its shape.
+    pub fn map<N2, S2: Eq + Hash>(
+        self,
+        number: &mut impl FnMut(N) -> N2,
+        string: &mut impl FnMut(S) -> S2,
+    ) -> GenericValue<N2, S2> {
+        match self {
+            GenericValue::Null => GenericValue::Null,
+            GenericValue::Bool(b) => GenericValue::Bool(b),
+            GenericValue::Number(n) => GenericValue::Number(number(n)),
+            GenericValue::String(s) => GenericValue::String(string(s)),
+            GenericValue::Array(v) => {
+                GenericValue::Array(v.into_iter().map(|v| v.map(number, string)).collect())
+            }
+            GenericValue::Object(m) => GenericValue::Object(
+                m.into_iter()
+                    .map(|(k, v)| (string(k), v.map(number, string)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Like `map`, but the conversions may fail; the first error is returned.
+    pub fn try_map<N2, S2: Eq + Hash, E>(
+        self,
+        number: &mut impl FnMut(N) -> Result<N2, E>,
+        string: &mut impl FnMut(S) -> Result<S2, E>,
+    ) -> Result<GenericValue<N2, S2>, E> {
+        Ok(match self {
+            GenericValue::Null => GenericValue::Null,
+            GenericValue::Bool(b) => GenericValue::Bool(b),
+            GenericValue::Number(n) => GenericValue::Number(number(n)?),
+            GenericValue::String(s) => GenericValue::String(string(s)?),
+            GenericValue::Array(v) => GenericValue::Array(
+                v.into_iter()
+                    .map(|v| v.try_map(number, string))
+                    .collect::<Result<Vec<GenericValue<N2, S2>>, E>>()?,
+            ),
+            GenericValue::Object(m) => {
+                let mut out = HashMap::with_capacity(m.len());
+                for (k, v) in m {
+                    out.insert(string(k)?, v.try_map(number, string)?);
+                }
+                GenericValue::Object(out)
+            }
+        })
+    }
+}
+
+impl Value {
+    /// An estimate of the bytes the tree occupies, heap allocations included:
+    /// string capacities, the spare capacity of arrays, and the entries and
+    /// control bytes of object tables. Allocator overhead is not counted.
+    pub fn estimated_size(&self) -> usize {
+        let mut size = mem::size_of::<Value>();
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                GenericValue::String(s) => size += s.capacity(),
+                GenericValue::Array(v) => {
+                    size += v.capacity() * mem::size_of::<Value>();
+                    stack.extend(v);
+                }
+                GenericValue::Object(m) => {
+                    size += m.capacity() * (mem::size_of::<(String, Value)>() + 1);
+                    for (k, v) in m {
+                        size += k.capacity();
+                        stack.push(v);
+                    }
+                }
+                _ => {}
+            }
+        }
+        size
+    }
+}
+
+/// Collect values into an array.
+impl<N, S> FromIterator<GenericValue<N, S>> for GenericValue<N, S> {
+    fn from_iter<I: IntoIterator<Item = GenericValue<N, S>>>(iter: I) -> GenericValue<N, S> {
+        GenericValue::Array(iter.into_iter().collect())
+    }
+}
+
+/// Collect members into an object; a later member replaces an earlier one
+/// with the same key.
+impl<N, S: Eq + Hash> FromIterator<(S, GenericValue<N, S>)> for GenericValue<N, S> {
+    fn from_iter<I: IntoIterator<Item = (S, GenericValue<N, S>)>>(iter: I) -> GenericValue<N, S> {
+        GenericValue::Object(iter.into_iter().collect())
+    }
+}
+
+/// Append to an array.
+///
+/// Panics if the value is not an array.
+impl<N, S> Extend<GenericValue<N, S>> for GenericValue<N, S> {
+    fn extend<I: IntoIterator<Item = GenericValue<N, S>>>(&mut self, iter: I) {
+        match self {
+            GenericValue::Array(v) => v.extend(iter),
+            _ => panic!("only arrays can be extended with values"),
+        }
+    }
+}
+
+/// Insert members into an object.
+///
+/// Panics if the value is not an object.
+impl<N, S: Eq + Hash> Extend<(S, GenericValue<N, S>)> for GenericValue<N, S> {
+    fn extend<I: IntoIterator<Item = (S, GenericValue<N, S>)>>(&mut self, iter: I) {
+        match self {
+            GenericValue::Object(m) => m.extend(iter),
+            _ => panic!("only objects can be extended with members"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_map() {
+        let v = parse(r#"{"a": [1, "x", null], "b": 2}"#).unwrap();
+        let ints: GenericValue<i128, Rc<str>> =
+            v.clone().map(&mut |n| n as i128, &mut |s| Rc::from(s));
+        let mut exp = HashMap::new();
+        exp.insert(
+            Rc::from("a"),
+            GenericValue::Array(vec![
+                GenericValue::Number(1),
+                GenericValue::String(Rc::from("x")),
+                GenericValue::Null,
+            ]),
+        );
+        exp.insert(Rc::from("b"), GenericValue::Number(2));
+        assert_eq!(ints, GenericValue::Object(exp));
+
+        let small: Result<GenericValue<u8, String>, _> =
+            v.try_map(&mut |n| u8::try_from(n as i64), &mut |s| Ok(s));
+        assert!(small.is_ok());
+        let big: Result<GenericValue<u8, String>, _> =
+            Value::Number(300.0).try_map(&mut |n| u8::try_from(n as i64), &mut |s| Ok(s));
+        assert!(big.is_err());
+    }
+
+    #[test]
+    fn test_collect() {
+        let mut array: Value = (1..3).map(|n| Value::Number(n as f64)).collect();
+        array.extend(vec![Value::Null]);
+        assert_eq!(array, parse("[1, 2, null]").unwrap());
+        let mut object: Value = vec![("a".to_owned(), Value::Bool(true))]
+            .into_iter()
+            .collect();
+        object.extend(vec![("b".to_owned(), array)]);
+        assert_eq!(object, parse(r#"{"a": true, "b": [1, 2, null]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_kind() {
+        let v = parse(r#"{"a": [1, "x", null, true]}"#).unwrap();
+        assert_eq!(v.kind(), ValueKind::Object);
+        assert!(v.is_object() && !v.is_array());
+        let kinds: Vec<String> = match &v {
+            Value::Object(m) => match &m["a"] {
+                Value::Array(items) => items.iter().map(|i| i.kind().to_string()).collect(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert_eq!(kinds, vec!["number", "string", "null", "boolean"]);
+    }
+
+    #[test]
+    fn test_debug() {
+        let v = parse(r#"{"a": [1.5, "x\n", null, true, []]}"#).unwrap();
+        assert_eq!(
+            format!("{:#?}", v),
+            "{\n  \"a\": [\n    1.5,\n    \"x\\n\",\n    null,\n    true,\n    []\n  ]\n}"
+        );
+        assert_eq!(
+            format!("{:?}", v),
+            r#"Object({"a": Array([Number(1.5), String("x\n"), Null, Bool(true), Array([])])})"#
+        );
+    }
+
+    #[test]
+    fn test_estimated_size() {
+        let unit = mem::size_of::<Value>();
+        assert_eq!(Value::Null.estimated_size(), unit);
+        let s = || Value::String(String::with_capacity(100));
+        assert_eq!(s().estimated_size(), unit + 100);
+        let v = Value::Array(vec![s(), s()]);
+        assert_eq!(v.estimated_size(), unit + 2 * (unit + 100));
+        let small = parse(r#"{"a": 1}"#).unwrap().estimated_size();
+        let large = parse(r#"{"a": 1, "b": {"c": [1, 2, 3, "some text"]}}"#).unwrap();
+        assert!(large.estimated_size() > small + 4 * unit);
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+/// Convert XML documents into `Value`s.
+///
+/// The mapping convention:
+///
+/// * the document becomes an object with the root element's name as its only key;
+/// * an element with neither attributes nor child elements becomes its text, or
+///   `null` when it has no text;
+/// * any other element becomes an object holding its attributes as `"@name"`,
+///   its child elements by name and its text, if any, as `"#text"`;
+/// * repeated child elements with the same name are collected into an array in
+///   document order;
+/// * text is trimmed and whitespace-only text is dropped; CDATA sections count as
+///   text; comments, processing instructions and the DOCTYPE are ignored;
+/// * all values are strings, no type inference is done, and namespace prefixes
+///   are kept as part of the names.
+use crate::parser::MAX_DEPTH;
+use crate::value::Value;
+
+/// Parse the XML document `s` into a `Value` following the module's convention.
+/// Elements nested deeper than `MAX_DEPTH` are rejected, so that no input
+/// can exhaust the stack.
+pub fn from_xml(s: &str) -> Result<Value, &'static str> {
+    let mut parser = XmlParser { s, pos: 0 };
+    parser.skip_misc()?;
+    if !parser.eat("<") {
+        return Err("root element expected.");
+    }
+    let (name, value) = parser.element(1)?;
+    parser.skip_misc()?;
+    if parser.pos < s.len() {
+        return Err("trailing string after xml.");
+    }
+    let mut m = HashMap::new();
+    m.insert(name, value);
+    Ok(Value::Object(m))
+}
+
+struct XmlParser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            return true;
+        }
+        false
+    }
+
+    // move past the next `end` and return the text before it.
+    fn until(&mut self, end: &str) -> Result<&'a str, &'static str> {
+        let rest = self.rest();
+        let i = rest.find(end).ok_or("unexpected end of xml.")?;
+        self.pos += i + end.len();
+        Ok(&rest[..i])
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // skip whitespace, comments, processing instructions and the DOCTYPE.
+    fn skip_misc(&mut self) -> Result<(), &'static str> {
+        loop {
+            self.skip_whitespace();
+            if self.eat("<!--") {
+                self.until("-->")?;
+            } else if self.eat("<?") {
+                self.until("?>")?;
+            } else if self.eat("<!DOCTYPE") {
+                let mut depth = 0;
+                loop {
+                    let c = self.rest().chars().next().ok_or("unexpected end of xml.")?;
+                    self.pos += c.len_utf8();
+                    match c {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        '>' if depth == 0 => break,
+                        _ => {}
+                    }
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, &'static str> {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || "/>=<\"'".contains(c))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err("name expected.");
+        }
+        self.pos += end;
+        Ok(rest[..end].to_owned())
+    }
+
+    // parse an element whose `<` is already consumed, nested `depth` deep.
+    fn element(&mut self, depth: usize) -> Result<(String, Value), &'static str> {
+        if depth > MAX_DEPTH {
+            return Err("xml nested too deeply.");
+        }
+        let name = self.name()?;
+        let mut attributes = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat("/>") {
+                return Ok((name, element_value(attributes, vec![], String::new())));
+            }
+            if self.eat(">") {
+                break;
+            }
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            if !self.eat("=") {
+                return Err("attribute value expected.");
+            }
+            self.skip_whitespace();
+            let quote = if self.eat("\"") {
+                "\""
+            } else if self.eat("'") {
+                "'"
+            } else {
+                return Err("quoted attribute value expected.");
+            };
+            let raw = self.until(quote)?;
+            if raw.contains('<') {
+                return Err("'<' in attribute value.");
+            }
+            attributes.push((attribute, decode(raw)?));
+        }
+        let mut children = vec![];
+        let mut text = String::new();
+        loop {
+            if self.eat("</") {
+                if self.name()? != name {
+                    return Err("mismatched end tag.");
+                }
+                self.skip_whitespace();
+                if !self.eat(">") {
+                    return Err("'>' expected.");
+                }
+                return Ok((name, element_value(attributes, children, text)));
+            } else if self.eat("<!--") {
+                self.until("-->")?;
+            } else if self.eat("<![CDATA[") {
+                text.push_str(self.until("]]>")?);
+            } else if self.eat("<?") {
+                self.until("?>")?;
+            } else if self.eat("<") {
+                children.push(self.element(depth + 1)?);
+            } else {
+                let rest = self.rest();
+                let end = rest.find('<').ok_or("unexpected end of xml.")?;
+                text.push_str(&decode(&rest[..end])?);
+                self.pos += end;
+            }
+        }
+    }
+}
+
+fn element_value(
+    attributes: Vec<(String, String)>,
+    children: Vec<(String, Value)>,
+    text: String,
+) -> Value {
+    let text = text.trim();
+    if attributes.is_empty() && children.is_empty() {
+        if text.is_empty() {
+            return Value::Null;
+        }
+        return Value::String(text.to_owned());
+    }
+    let mut m = HashMap::new();
+    for (k, v) in attributes {
+        m.insert(format!("@{}", k), Value::String(v));
+    }
+    for (k, v) in children {
+        // element values are never arrays, so an array here holds repeated elements.
+        match m.remove(&k) {
+            None => m.insert(k, v),
+            Some(Value::Array(mut items)) => {
+                items.push(v);
+                m.insert(k, Value::Array(items))
+            }
+            Some(first) => m.insert(k, Value::Array(vec![first, v])),
+        };
+    }
+    if !text.is_empty() {
+        m.insert("#text".to_owned(), Value::String(text.to_owned()));
+    }
+    Value::Object(m)
+}
+
+// replace the predefined entities and character references.
+fn decode(s: &str) -> Result<String, &'static str> {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let end = rest.find(';').ok_or("unterminated entity.")?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse::<u32>().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32).ok_or("unknown entity.")?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_from_xml() {
+        let v = from_xml(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE rss [<!ENTITY x "y">]>
+<!-- feed -->
+<rss version="2.0">
+  <channel>
+    <title>News &amp; more</title>
+    <item id="1"><title>a</title></item>
+    <item id='2'><title><![CDATA[<b>]]></title><empty/></item>
+    <note lang="en">hi &#x41;</note>
+  </channel>
+</rss>"#,
+        )
+        .unwrap();
+        let exp = parse(
+            r##"{"rss": {"@version": "2.0", "channel": {
+                "title": "News & more",
+                "item": [{"@id": "1", "title": "a"}, {"@id": "2", "title": "<b>", "empty": null}],
+                "note": {"@lang": "en", "#text": "hi A"}}}}"##,
+        )
+        .unwrap();
+        assert_eq!(v, exp);
+    }
+
+    #[test]
+    fn test_invalid_xml() {
+        for s in &[
+            "",
+            "text",
+            "<a>",
+            "<a></b>",
+            "<a b=c/>",
+            "<a>&bogus;</a>",
+            "<a/><b/>",
+            "<a b='<'/>",
+        ] {
+            assert!(from_xml(s).is_err(), "{}", s);
+        }
+        let deep = |n: usize| "<a>".repeat(n) + &"</a>".repeat(n);
+        assert!(from_xml(&deep(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            from_xml(&deep(MAX_DEPTH + 1)),
+            Err("xml nested too deeply.")
+        );
+        assert!(from_xml(&"<a>".repeat(1_000_000)).is_err());
+    }
+}
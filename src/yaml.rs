@@ -0,0 +1,133 @@
+use yaml_rust2::parser::{Event, Parser};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+/// YAML frontend producing `Value`s and serializing them back. Enabled by the
+/// `yaml` feature.
+use crate::value::Value;
+
+/// Parse a single-document YAML string into a `Value`.
+///
+/// Only the safe subset is accepted: aliases are rejected, so a small document
+/// cannot expand into an exponentially large one, and mapping keys must be
+/// scalars. An empty document is `null`.
+pub fn from_yaml(s: &str) -> Result<Value, &'static str> {
+    let mut parser = Parser::new_from_str(s);
+    loop {
+        match parser.next_token() {
+            Ok((Event::StreamEnd, _)) => break,
+            Ok((Event::Alias(_), _)) => return Err("yaml aliases are not supported."),
+            Ok(_) => {}
+            Err(_) => return Err("cannot parse the string as yaml."),
+        }
+    }
+    let mut docs = YamlLoader::load_from_str(s).map_err(|_| "cannot parse the string as yaml.")?;
+    if docs.len() > 1 {
+        return Err("multiple yaml documents are not supported.");
+    }
+    match docs.pop() {
+        Some(doc) => convert(doc),
+        None => Ok(Value::Null),
+    }
+}
+
+fn convert(yaml: Yaml) -> Result<Value, &'static str> {
+    match yaml {
+        Yaml::Null => Ok(Value::Null),
+        Yaml::Boolean(b) => Ok(Value::Bool(b)),
+        Yaml::Integer(i) => Ok(Value::Number(i as f64)),
+        Yaml::Real(_) => yaml
+            .as_f64()
+            .map(Value::Number)
+            .ok_or("cannot parse the string into the numbers."),
+        Yaml::String(s) => Ok(Value::String(s)),
+        Yaml::Array(v) => v
+            .into_iter()
+            .map(convert)
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Yaml::Hash(h) => {
+            let mut m = std::collections::HashMap::new();
+            for (k, v) in h {
+                let key = match k {
+                    Yaml::String(s) | Yaml::Real(s) => s,
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    Yaml::Null => "null".to_owned(),
+                    _ => return Err("yaml mapping keys must be scalars."),
+                };
+                m.insert(key, convert(v)?);
+            }
+            Ok(Value::Object(m))
+        }
+        Yaml::Alias(_) => Err("yaml aliases are not supported."),
+        Yaml::BadValue => Err("cannot parse the string as yaml."),
+    }
+}
+
+/// Serialize `value` into a YAML document. Object keys are written sorted.
+pub fn to_yaml(value: &Value) -> Result<String, &'static str> {
+    let mut out = String::new();
+    YamlEmitter::new(&mut out)
+        .dump(&to_yaml_node(value))
+        .map_err(|_| "failed to write output.")?;
+    out.push('\n');
+    Ok(out)
+}
+
+fn to_yaml_node(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(*b),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 9.0e15 => Yaml::Integer(*n as i64),
+        Value::Number(n) if n.is_finite() => Yaml::Real(n.to_string()),
+        Value::Number(_) => Yaml::Null,
+        Value::String(s) => Yaml::String(s.clone()),
+        Value::Array(v) => Yaml::Array(v.iter().map(to_yaml_node).collect()),
+        Value::Object(m) => {
+            let mut keys: Vec<&String> = m.keys().collect();
+            keys.sort();
+            let mut h = Hash::new();
+            for k in keys {
+                h.insert(Yaml::String(k.clone()), to_yaml_node(&m[k]));
+            }
+            Yaml::Hash(h)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_from_yaml() {
+        let v = from_yaml(
+            "name: app\nport: 8080\nratio: 0.5\ndebug: false\nnothing: ~\n\
+             hosts:\n  - a\n  - b\ndb: {user: root, 1: one}\n",
+        )
+        .unwrap();
+        let exp = parse(
+            r#"{"name": "app", "port": 8080, "ratio": 0.5, "debug": false, "nothing": null,
+                "hosts": ["a", "b"], "db": {"user": "root", "1": "one"}}"#,
+        )
+        .unwrap();
+        assert_eq!(v, exp);
+        assert_eq!(from_yaml("").unwrap(), Value::Null);
+        assert!(from_yaml("a: &x [1]\nb: *x\n").is_err());
+        assert!(from_yaml("a: [1\n").is_err());
+        assert!(from_yaml("---\na: 1\n---\nb: 2\n").is_err());
+        assert!(from_yaml("? [1, 2]\n: x\n").is_err());
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let v = parse(r#"{"b": [1, 2.5, "x"], "a": {"c": null, "d": true}}"#).unwrap();
+        let s = to_yaml(&v).unwrap();
+        assert_eq!(s, "---\na:\n  c: ~\n  d: true\nb:\n  - 1\n  - 2.5\n  - x\n");
+        assert_eq!(from_yaml(&s).unwrap(), v);
+        let tricky = Value::String("true".to_owned());
+        assert_eq!(from_yaml(&to_yaml(&tricky).unwrap()).unwrap(), tricky);
+    }
+}